@@ -1,5 +1,10 @@
-use aurora_kvm::client;
+use aurora_kvm::client::{self, FocusHooks};
+use aurora_kvm::config::FocusOverlay;
+use aurora_kvm::input::InputBackend;
+use aurora_kvm::overlay::{self, FocusNotice};
+use aurora_kvm::tray::{self, TrayAction, TrayStatus};
 use clap::Parser;
+use tokio::sync::{mpsc, watch};
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
@@ -9,6 +14,30 @@ struct Cli {
     /// Server host (auto-discovers if not specified)
     #[arg(short = 'H', long)]
     host: Option<String>,
+
+    /// Input injection backend (defaults to evdev on Wayland, rdev otherwise)
+    #[arg(long, value_enum)]
+    input_backend: Option<InputBackend>,
+
+    /// Shell command to run when this machine gains focus
+    #[arg(long)]
+    on_focus_gained: Option<String>,
+
+    /// Shell command to run when focus leaves this machine
+    #[arg(long)]
+    on_focus_lost: Option<String>,
+
+    /// Flash this machine's name on screen when it gains focus
+    #[arg(long)]
+    focus_overlay: bool,
+
+    /// How long the focus flash stays up, in milliseconds
+    #[arg(long, default_value_t = FocusOverlay::default().flash_ms)]
+    flash_ms: u64,
+
+    /// Do not show a tray icon
+    #[arg(long)]
+    no_tray: bool,
 }
 
 #[tokio::main]
@@ -60,7 +89,65 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
-    client::run(host).await?;
+    let backend = cli.input_backend.unwrap_or_else(InputBackend::detect);
+    let hooks = FocusHooks {
+        on_gained: cli.on_focus_gained,
+        on_lost: cli.on_focus_lost,
+    };
+    let settings = FocusOverlay {
+        enabled: cli.focus_overlay,
+        flash_ms: cli.flash_ms,
+    };
+    let overlay = if settings.enabled {
+        let (tx, rx) = mpsc::unbounded_channel();
+        overlay::attach(settings, &overlay::this_machine(), rx)
+            .inspect_err(|e| println!("Focus overlay unavailable: {}", e))
+            .ok()
+            .map(|timer| (timer, tx))
+    } else {
+        None
+    };
+
+    // Tray icon with the connection and focus, whose Quit ends the client
+    let (status_tx, status) = watch::channel(TrayStatus::for_client(&host, false));
+    let (actions_tx, mut actions) = mpsc::unbounded_channel();
+    if !cli.no_tray && tray::spawn(status, actions_tx).await {
+        tokio::spawn(async move {
+            while let Some(action) = actions.recv().await {
+                if action == TrayAction::Quit {
+                    std::process::exit(0);
+                }
+            }
+        });
+    }
+
+    // Focus changes go to both the tray and the overlay
+    let (notices_tx, mut notices) = mpsc::unbounded_channel();
+    let overlay_tx = overlay.as_ref().map(|(_, tx)| tx.clone());
+    let tray_host = host.clone();
+    tokio::spawn(async move {
+        while let Some(notice) = notices.recv().await {
+            let focused = notice != FocusNotice::Lost;
+            let _ = status_tx.send(TrayStatus::for_client(&tray_host, focused));
+            if let Some(overlay) = &overlay_tx {
+                let _ = overlay.send(notice);
+            }
+        }
+    });
+
+    match overlay {
+        None => client::run(host, backend, hooks, Some(notices_tx)).await?,
+        Some((_timer, _)) => {
+            // The overlay needs the Slint event loop on this thread
+            let session = tokio::spawn(async move {
+                let result = client::run(host, backend, hooks, Some(notices_tx)).await;
+                let _ = slint::quit_event_loop();
+                result
+            });
+            slint::run_event_loop_until_quit()?;
+            session.await??;
+        }
+    }
 
     Ok(())
 }
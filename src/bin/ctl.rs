@@ -0,0 +1,112 @@
+use aurora_kvm::control::ControlClient;
+use clap::{Parser, Subcommand};
+use serde_json::{Value, json};
+
+#[derive(Parser)]
+#[command(author, version, about = "Control a running aurora_server", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show port, active profile, focus, pause state and client count
+    Status,
+    /// List connected clients and clients waiting for approval
+    Clients,
+    /// Show the current focus, or move it to a client or back to `local`
+    Focus {
+        /// Client name, or `local`
+        target: Option<String>,
+    },
+    /// Pin focus on the current machine (`on`), release it (`off`), or toggle
+    Lock {
+        #[arg(value_parser = ["on", "off"])]
+        state: Option<String>,
+    },
+    /// Pause input sharing (`on`), resume it (`off`), or toggle
+    Pause {
+        #[arg(value_parser = ["on", "off"])]
+        state: Option<String>,
+    },
+    /// Reload config.json
+    Reload,
+    /// Disconnect a client
+    Kick { name: String },
+    /// Approve a client waiting to pair
+    Approve { name: String },
+    /// Switch to a layout profile
+    Profile { name: String },
+    /// Run a macro from config.json
+    Macro { name: String },
+    /// Stream focus and connection events as JSON lines
+    Watch,
+}
+
+fn print_json(value: &Value) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let mut client = ControlClient::connect().await?;
+
+    let result = match cli.command {
+        Command::Status => client.call("status", Value::Null).await?,
+        Command::Clients => client.call("clients", Value::Null).await?,
+        Command::Focus { target: None } => client.call("focus", Value::Null).await?,
+        Command::Focus {
+            target: Some(target),
+        } => {
+            client
+                .call("set_focus", json!({ "target": target }))
+                .await?
+        }
+        Command::Lock { state } => {
+            let locked = state.map(|state| state == "on");
+            client
+                .call("lock_focus", json!({ "locked": locked }))
+                .await?
+        }
+        Command::Pause { state } => {
+            let paused = state.map(|state| state == "on");
+            client
+                .call("set_paused", json!({ "paused": paused }))
+                .await?
+        }
+        Command::Reload => client.call("reload", Value::Null).await?,
+        Command::Kick { name } => client.call("kick", json!({ "name": name })).await?,
+        Command::Approve { name } => {
+            client
+                .call("approve_pairing", json!({ "name": name }))
+                .await?
+        }
+        Command::Profile { name } => {
+            client
+                .call("switch_profile", json!({ "name": name }))
+                .await?
+        }
+        Command::Macro { name } => client.call("run_macro", json!({ "name": name })).await?,
+        Command::Watch => {
+            client.call("subscribe", Value::Null).await?;
+            while let Some(event) = client.next_event().await? {
+                // One compact JSON object per line for easy piping
+                println!("{}", serde_json::to_string(&event)?);
+            }
+            return Ok(());
+        }
+    };
+
+    print_json(&result)
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli).await {
+        eprintln!("aurora_ctl: {:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,5 +1,8 @@
-use aurora_kvm::{gui_slint, server};
-use clap::Parser;
+use aurora_kvm::config::{self, Config, config_path};
+use aurora_kvm::input::InputBackend;
+use aurora_kvm::{gui_slint, overlay, server, tray, validate};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
@@ -12,6 +15,50 @@ struct Cli {
     /// Run in headless mode (no GUI, server only)
     #[arg(long)]
     headless: bool,
+
+    /// Start with only the tray icon, without opening the configuration window
+    #[arg(long)]
+    minimized: bool,
+
+    /// Input capture backend (defaults to evdev on Wayland, rdev otherwise)
+    #[arg(long, value_enum)]
+    input_backend: Option<InputBackend>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validate a config file and report every problem found
+    CheckConfig {
+        /// Config file to check (defaults to the server's config.json)
+        path: Option<PathBuf>,
+    },
+    /// Print the JSON Schema for config.json
+    ConfigSchema,
+    /// List layout profiles, or switch the running server to one
+    Profile {
+        /// Profile to activate
+        name: Option<String>,
+    },
+}
+
+/// Print all diagnostics for a config file and return whether it is usable
+fn check_config(path: PathBuf) -> anyhow::Result<bool> {
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let (_, diagnostics) = validate::check_source(&contents);
+
+    for diagnostic in &diagnostics {
+        println!("{}: {}", path.display(), diagnostic);
+    }
+
+    let ok = !validate::has_errors(&diagnostics);
+    if ok {
+        println!("{}: OK ({} warnings)", path.display(), diagnostics.len());
+    }
+    Ok(ok)
 }
 
 #[tokio::main]
@@ -24,26 +71,92 @@ async fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
 
+    match cli.command {
+        Some(Command::CheckConfig { path }) => {
+            let ok = check_config(path.unwrap_or_else(config_path))?;
+            std::process::exit(if ok { 0 } else { 1 });
+        }
+        Some(Command::ConfigSchema) => {
+            println!("{}", serde_json::to_string_pretty(&config::json_schema())?);
+            return Ok(());
+        }
+        Some(Command::Profile { name }) => {
+            // The running server picks the change up through config hot-reload
+            let mut config = Config::load()?;
+            match name {
+                Some(name) => {
+                    config.set_active_profile(&name)?;
+                    config.save()?;
+                    println!("Active profile: {}", name);
+                }
+                None => {
+                    for profile in &config.profiles {
+                        let marker = if profile.name == config.active_profile {
+                            "*"
+                        } else {
+                            " "
+                        };
+                        println!("{} {}", marker, profile.name);
+                    }
+                }
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let backend = cli.input_backend.unwrap_or_else(InputBackend::detect);
+    let port = cli.port;
+
+    // Create shared state
+    let connected_clients = aurora_kvm::connected::create_connected_clients();
+    let topology = aurora_kvm::topology::create_shared_topology(
+        aurora_kvm::reload::load_server_config()?,
+        connected_clients.clone(),
+    );
+
     if cli.headless {
-        // Run server only
-        server::run(cli.port).await?;
+        // Run server only, next to the focus overlay's event loop if it is enabled
+        match overlay::attach_local(&topology) {
+            None => server::run_with_state(port, backend, connected_clients, topology).await?,
+            Some(_overlay) => {
+                let server = tokio::spawn(async move {
+                    let result =
+                        server::run_with_state(port, backend, connected_clients, topology).await;
+                    let _ = slint::quit_event_loop();
+                    result
+                });
+                slint::run_event_loop_until_quit()?;
+                server.await??;
+            }
+        }
     } else {
         // Default: Launch GUI with server running in background
-        let port = cli.port;
-
-        // Create connected clients state
-        let connected_clients = aurora_kvm::connected::create_connected_clients();
         let connected_for_server = connected_clients.clone();
+        let topology_for_server = topology.clone();
 
         // Spawn server in background
         tokio::spawn(async move {
-            if let Err(e) = server::run_with_state(port, connected_for_server).await {
+            if let Err(e) =
+                server::run_with_state(port, backend, connected_for_server, topology_for_server)
+                    .await
+            {
                 eprintln!("Server error: {}", e);
             }
         });
 
-        // Launch GUI (blocks until closed)
-        if let Err(e) = gui_slint::run_gui_slint(Some(connected_clients)) {
+        // With a tray icon the window can close while the server keeps running
+        let (actions_tx, actions) = tokio::sync::mpsc::unbounded_channel();
+        let status = tray::watch_server(topology.clone(), connected_clients.clone());
+        let tray = tray::spawn(status, actions_tx).await.then_some(actions);
+        if cli.minimized && tray.is_none() {
+            println!("No tray to minimize to, opening the configuration window");
+        }
+
+        // Launch GUI (blocks until closed, or until Quit in the tray)
+        if let Err(e) =
+            gui_slint::run_gui_slint(Some(connected_clients), Some(topology), tray, cli.minimized)
+        {
             eprintln!("GUI error: {}", e);
             std::process::exit(1);
         }
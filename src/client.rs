@@ -1,9 +1,52 @@
+use crate::event::KvmEvent;
+use crate::input::held::HeldInput;
+use crate::input::{InputBackend, create_injector};
 use crate::net::{KvmStream, PROTOCOL_VERSION, Packet};
+use crate::overlay::FocusNotice;
+use crate::session::SessionMirror;
 use anyhow::Result;
-use rdev::{EventType, simulate};
 use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
 
-pub async fn run(host: String) -> Result<()> {
+/// Shell commands run when this client gains or loses focus
+#[derive(Debug, Clone, Default)]
+pub struct FocusHooks {
+    pub on_gained: Option<String>,
+    pub on_lost: Option<String>,
+}
+
+/// Start a focus hook without waiting for it, logging when it fails
+fn run_hook(command: &Option<String>) {
+    let Some(command) = command.clone() else {
+        return;
+    };
+    #[cfg(windows)]
+    let mut process = tokio::process::Command::new("cmd");
+    #[cfg(windows)]
+    process.arg("/C");
+    #[cfg(not(windows))]
+    let mut process = tokio::process::Command::new("sh");
+    #[cfg(not(windows))]
+    process.arg("-c");
+    process.arg(&command);
+
+    tokio::spawn(async move {
+        match process.status().await {
+            Ok(status) if status.success() => {}
+            Ok(status) => println!("Focus hook `{}` failed: {}", command, status),
+            Err(e) => println!("Cannot run focus hook `{}`: {}", command, e),
+        }
+    });
+}
+
+/// Connect to the server at `host` and inject its input until disconnected.
+/// Focus changes on this machine go to `focus`, for the tray and overlay.
+pub async fn run(
+    host: String,
+    backend: InputBackend,
+    hooks: FocusHooks,
+    focus: Option<UnboundedSender<FocusNotice>>,
+) -> Result<()> {
     println!("Connecting to {}", host);
     let stream = TcpStream::connect(host).await?;
     let kvm_stream = KvmStream::new(stream);
@@ -20,23 +63,103 @@ pub async fn run(host: String) -> Result<()> {
     };
     writer.send(&handshake).await?;
 
-    // Receive loop
-    loop {
-        match reader.receive().await {
-            Ok(Packet::Event(event)) => {
-                let event_type = EventType::from(event);
-                if let Err(e) = simulate(&event_type) {
-                    println!("Failed to simulate event: {:?}", e);
+    let mut injector = create_injector(backend)?;
+    println!("Injecting input with the {:?} backend", backend);
+
+    // Read on a task of its own, so the loop can also wait for session changes
+    let (packets_tx, mut packets) = tokio::sync::mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            match reader.receive().await {
+                Ok(packet) => {
+                    if packets_tx.send(packet).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    println!("Connection lost: {}", e);
+                    break;
                 }
             }
-            Ok(_) => {} // Ignore other packets for now
-            Err(e) => {
-                println!("Connection lost: {}", e);
-                break;
+        }
+    });
+
+    // Screen lock and screensaver changes on this machine
+    let (session_tx, mut session_changes) = tokio::sync::mpsc::unbounded_channel();
+    let session = crate::session::watch_local(session_tx)
+        .await
+        .map(crate::session::apply_in_background);
+    let mut mirror = SessionMirror::new();
+    let awake = crate::inhibit::keep_awake_in_background(crate::inhibit::create_inhibitor);
+    let mut held = HeldInput::new();
+
+    // Receive loop
+    let result = loop {
+        tokio::select! {
+            packet = packets.recv() => match packet {
+                Some(Packet::Event(event)) => {
+                    match injector.inject(&event) {
+                        Ok(()) => held.track(&event),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                Some(Packet::FocusGained { entry_point: (x, y), entry_edge }) => {
+                    if let Err(e) = injector.inject(&KvmEvent::MouseMove { x, y }) {
+                        println!("{}", e);
+                    }
+                    let _ = awake.send(true);
+                    if let Some(focus) = &focus {
+                        let _ = focus.send(FocusNotice::Gained { edge: entry_edge });
+                    }
+                    run_hook(&hooks.on_gained);
+                }
+                Some(Packet::FocusLost) => {
+                    // Releases that went to the newly focused machine instead
+                    for event in held.release_all() {
+                        if let Err(e) = injector.inject(&event) {
+                            println!("{}", e);
+                        }
+                    }
+                    let _ = awake.send(false);
+                    if let Some(focus) = &focus {
+                        let _ = focus.send(FocusNotice::Lost);
+                    }
+                    run_hook(&hooks.on_lost);
+                    // Lets the server put the cursor back here when focus returns
+                    if let Some((x, y)) = injector.cursor_position()
+                        && let Err(e) = writer.send(&Packet::CursorPosition { x, y }).await
+                    {
+                        break Err(e);
+                    }
+                }
+                Some(Packet::Session(event)) => {
+                    if mirror.mirrored(event)
+                        && let Some(apply) = &session
+                    {
+                        println!("{:?} on the server, mirroring here", event);
+                        let _ = apply.send(event);
+                    }
+                }
+                Some(_) => {} // Ignore other packets for now
+                None => break Ok(()),
+            },
+            Some(change) = session_changes.recv() => {
+                if mirror.observed(change.event)
+                    && let Err(e) = writer.send(&Packet::Session(change.event)).await
+                {
+                    break Err(e);
+                }
             }
         }
+    };
+
+    // The server can no longer send the releases for keys and buttons held here
+    for event in held.release_all() {
+        if let Err(e) = injector.inject(&event) {
+            println!("{}", e);
+        }
     }
-    Ok(())
+    result
 }
 
 fn detect_screen_info() -> crate::net::ScreenInfo {
@@ -51,6 +174,7 @@ fn detect_screen_info() -> crate::net::ScreenInfo {
                 height: display.height,
                 x: 0,
                 y: 0,
+                scale: display.scale_factor as f64,
                 name: hostname::get()
                     .ok()
                     .and_then(|h| h.into_string().ok())
@@ -64,6 +188,7 @@ fn detect_screen_info() -> crate::net::ScreenInfo {
                 height: 1080,
                 x: 0,
                 y: 0,
+                scale: 1.0,
                 name: hostname::get()
                     .ok()
                     .and_then(|h| h.into_string().ok())
@@ -1,16 +1,123 @@
+use crate::edge::Edge;
+use crate::{migrate, validate};
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+/// Schema version written by this build. Older files are upgraded by `migrate`.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Name given to the single profile created for configs that predate profiles
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct Config {
+    /// Schema version of this file
+    #[serde(default)]
+    pub version: u32,
     pub port: u16,
     pub secret: Option<String>,
     pub input_grab_hotkey: Option<String>,
+    /// Hotkey that pins focus on the current machine until pressed again
+    #[serde(default)]
+    pub focus_lock_hotkey: Option<String>,
+    /// Hotkey that moves focus back to the server's own screens
+    #[serde(default)]
+    pub local_hotkey: Option<String>,
+    /// Focus hotkeys and control API switches put the cursor back where it last
+    /// was on that machine, instead of the middle of its screen
+    #[serde(default)]
+    pub jump_mode: bool,
+    /// Hold clients not named in the active profile until approved over the control API
+    #[serde(default)]
+    pub require_pairing: bool,
+    /// Name of the profile the server routes with
+    #[serde(default = "default_profile_name")]
+    pub active_profile: String,
+    #[serde(default = "default_profiles")]
+    pub profiles: Vec<Profile>,
+    /// Named key sequences, started by a hotkey or over the control API
+    #[serde(default)]
+    pub macros: Vec<MacroConfig>,
+    /// Conditions for switching focus at a screen edge
+    #[serde(default)]
+    pub edge_policy: EdgePolicy,
+    /// Replacements for `edge_policy` on individual edges
+    #[serde(default)]
+    pub edge_overrides: EdgeOverrides,
+    /// On-screen indicator on the server's own screen when focus comes back to it
+    #[serde(default)]
+    pub focus_overlay: FocusOverlay,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            port: 0,
+            secret: None,
+            input_grab_hotkey: None,
+            focus_lock_hotkey: None,
+            local_hotkey: None,
+            jump_mode: false,
+            require_pairing: false,
+            active_profile: default_profile_name(),
+            profiles: vec![Profile {
+                local_screens: Vec::new(),
+                ..Profile::default()
+            }],
+            macros: Vec::new(),
+            edge_policy: EdgePolicy::default(),
+            edge_overrides: EdgeOverrides::default(),
+            focus_overlay: FocusOverlay::default(),
+        }
+    }
+}
+
+/// A named topology: the local screens and where each client sits around them
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Profile {
+    pub name: String,
+    /// Hotkey that switches to this profile, e.g. `Ctrl+Alt+F1`
+    #[serde(default)]
+    pub hotkey: Option<String>,
+    /// Select this profile automatically when the detected monitors match `local_screens`
+    #[serde(default)]
+    pub match_monitors: bool,
     #[serde(default = "default_local_screens")]
     pub local_screens: Vec<LocalScreen>,
+    #[serde(default)]
     pub clients: Vec<ClientConfig>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: default_profile_name(),
+            hotkey: None,
+            match_monitors: false,
+            local_screens: default_local_screens(),
+            clients: Vec::new(),
+        }
+    }
+}
+
+fn default_profile_name() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+fn default_profiles() -> Vec<Profile> {
+    vec![Profile::default()]
+}
+
+/// Stands in for the active profile of a config with no profiles, which only
+/// validation rejects
+static FALLBACK_PROFILE: LazyLock<Profile> = LazyLock::new(Profile::default);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct LocalScreen {
     #[serde(default)]
     pub x: i32,
@@ -29,7 +136,7 @@ fn default_local_screens() -> Vec<LocalScreen> {
     }]
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct ClientConfig {
     pub name: String,
     pub ip: String,
@@ -41,6 +148,162 @@ pub struct ClientConfig {
     pub width: u32,
     #[serde(default = "default_height")]
     pub height: u32,
+    /// Hotkey that moves focus to this client
+    #[serde(default)]
+    pub hotkey: Option<String>,
+    /// How keystrokes are sent to this client
+    #[serde(default)]
+    pub keyboard_mode: KeyboardMode,
+    /// Reverse scrolling on this client, e.g. for natural scrolling on a Mac
+    #[serde(default)]
+    pub invert_scroll: bool,
+    /// Multiplier for scroll distance on this client
+    #[serde(default = "default_scroll_speed")]
+    pub scroll_speed: f64,
+    /// Multiplier for pointer motion on this client, on top of display scaling
+    #[serde(default = "default_pointer_speed")]
+    pub pointer_speed: f64,
+    /// Extra speed for fast mouse movements, as `[pixels per second, multiplier]`
+    /// points in increasing speed order; motion is not accelerated when empty
+    #[serde(default)]
+    pub pointer_acceleration: Vec<[f64; 2]>,
+    /// Keys or chords to send as something else on this client, e.g.
+    /// `"MetaLeft": "ControlLeft"` or `"Ctrl+C": "Meta+C"`
+    #[serde(default)]
+    pub remap: BTreeMap<String, String>,
+    /// Which screen lock and screensaver changes this client shares with the server
+    #[serde(default)]
+    pub session_sync: SessionSync,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            ip: String::new(),
+            x: 0,
+            y: 0,
+            width: default_width(),
+            height: default_height(),
+            hotkey: None,
+            keyboard_mode: KeyboardMode::default(),
+            invert_scroll: false,
+            scroll_speed: default_scroll_speed(),
+            pointer_speed: default_pointer_speed(),
+            pointer_acceleration: Vec::new(),
+            remap: BTreeMap::new(),
+            session_sync: SessionSync::default(),
+        }
+    }
+}
+
+/// How the server forwards typing to a client
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyboardMode {
+    /// Physical keys; the client's own layout decides what they type
+    #[default]
+    Keys,
+    /// Text resolved with the server's layout, so characters come out the same
+    /// whatever layout the client uses. Shortcuts and chords are still sent as keys.
+    Text,
+}
+
+/// Which session changes a client and the server mirror on each other
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSync {
+    /// Each machine locks and blanks on its own
+    #[default]
+    Ignore,
+    /// Starting or stopping the screensaver on either machine does so on the other
+    MirrorScreensaver,
+    /// Locking either machine locks the other, and the screensaver is mirrored too
+    MirrorLock,
+}
+
+/// A named sequence of input sent to one client
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct MacroConfig {
+    pub name: String,
+    /// Chord that starts the macro, e.g. `Ctrl+Alt+T`. It is never forwarded.
+    #[serde(default)]
+    pub hotkey: Option<String>,
+    /// Client to send to; the focused client when unset
+    #[serde(default)]
+    pub target: Option<String>,
+    pub steps: Vec<MacroStep>,
+}
+
+/// One step of a macro, e.g. `{"tap": "Ctrl+L"}` or `{"delay_ms": 200}`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroStep {
+    /// Press and release a key or chord
+    Tap(String),
+    /// Press a key or chord and leave it down
+    Press(String),
+    /// Release a key or chord
+    Release(String),
+    /// Type a string, whatever the client's keyboard layout
+    Text(String),
+    /// Wait before the next step
+    DelayMs(u64),
+}
+
+/// When pushing the cursor against an edge of the local screens switches focus.
+/// The default switches immediately.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, JsonSchema)]
+pub struct EdgePolicy {
+    /// Keep pushing against the edge this long before switching
+    #[serde(default)]
+    pub switch_delay_ms: u64,
+    /// Switch only on the second push against the same edge within this window
+    #[serde(default)]
+    pub double_tap_ms: u64,
+    /// Never switch within this many pixels of either end of an edge
+    #[serde(default)]
+    pub corner_guard_px: u32,
+    /// Never switch while a mouse button is held, e.g. while dragging a scrollbar
+    #[serde(default)]
+    pub block_while_dragging: bool,
+}
+
+/// Edge policies that replace the global one on a single edge of the local screens
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, JsonSchema)]
+pub struct EdgeOverrides {
+    #[serde(default)]
+    pub left: Option<EdgePolicy>,
+    #[serde(default)]
+    pub right: Option<EdgePolicy>,
+    #[serde(default)]
+    pub top: Option<EdgePolicy>,
+    #[serde(default)]
+    pub bottom: Option<EdgePolicy>,
+}
+
+/// What a machine shows on its screen when it gains focus
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct FocusOverlay {
+    /// Flash the machine name and an arrow pointing the way the cursor came in
+    #[serde(default)]
+    pub enabled: bool,
+    /// How long the flash stays up
+    #[serde(default = "default_flash_ms")]
+    pub flash_ms: u64,
+}
+
+impl Default for FocusOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            flash_ms: default_flash_ms(),
+        }
+    }
+}
+
+fn default_flash_ms() -> u64 {
+    800
 }
 
 fn default_width() -> u32 {
@@ -49,3 +312,187 @@ fn default_width() -> u32 {
 fn default_height() -> u32 {
     1080
 }
+fn default_scroll_speed() -> f64 {
+    1.0
+}
+fn default_pointer_speed() -> f64 {
+    1.0
+}
+
+/// Location of the shared config file: `config_dir()/aurora_kvm/config.json`
+pub fn config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("aurora_kvm")
+        .join("config.json")
+}
+
+impl Config {
+    /// The profile currently in effect, falling back to the first one, or to a
+    /// default profile if there are none
+    pub fn active(&self) -> &Profile {
+        self.profiles
+            .iter()
+            .find(|p| p.name == self.active_profile)
+            .or_else(|| self.profiles.first())
+            .unwrap_or(&FALLBACK_PROFILE)
+    }
+
+    /// Like `active`, adding a default profile to edit if there are none
+    pub fn active_mut(&mut self) -> &mut Profile {
+        if self.profiles.is_empty() {
+            self.profiles.push(Profile {
+                name: self.active_profile.clone(),
+                ..Profile::default()
+            });
+        }
+        let index = self
+            .profiles
+            .iter()
+            .position(|p| p.name == self.active_profile)
+            .unwrap_or(0);
+        &mut self.profiles[index]
+    }
+
+    /// The edge policy in effect on `edge`
+    pub fn edge_policy_for(&self, edge: Edge) -> &EdgePolicy {
+        let overrides = &self.edge_overrides;
+        let policy = match edge {
+            Edge::Left => &overrides.left,
+            Edge::Right => &overrides.right,
+            Edge::Top => &overrides.top,
+            Edge::Bottom => &overrides.bottom,
+        };
+        policy.as_ref().unwrap_or(&self.edge_policy)
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// Make `name` the active profile
+    pub fn set_active_profile(&mut self, name: &str) -> Result<()> {
+        if !self.profiles.iter().any(|p| p.name == name) {
+            anyhow::bail!(
+                "Unknown profile '{}' (available: {})",
+                name,
+                self.profile_names().join(", ")
+            );
+        }
+        self.active_profile = name.to_string();
+        Ok(())
+    }
+
+    /// Activate the first `match_monitors` profile whose screens equal `monitors`.
+    ///
+    /// Returns the selected profile name, if any matched.
+    pub fn select_profile_for_monitors(&mut self, monitors: &[LocalScreen]) -> Option<String> {
+        let name = self
+            .profiles
+            .iter()
+            .find(|p| p.match_monitors && p.local_screens == monitors)?
+            .name
+            .clone();
+        self.active_profile = name.clone();
+        Some(name)
+    }
+
+    /// Load the config from the default location.
+    ///
+    /// A missing file yields the default config. A file that fails validation is
+    /// rejected with every diagnostic in the error, rather than silently replaced.
+    pub fn load() -> Result<Self> {
+        let path = config_path();
+        if !path.exists() {
+            println!("Warning: No config file found at {:?}, using default", path);
+            return Ok(Config::default());
+        }
+        Self::load_from(&path)
+    }
+
+    /// Strictly load and validate a config file, printing any warnings.
+    ///
+    /// Files written by an older schema are upgraded in place. The original is kept
+    /// next to it as `config.json.v<old version>.bak`.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let mut contents =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+
+        if let Some((from_version, migrated)) = migrate::migrate_source(&contents)? {
+            let backup = path.with_extension(format!("json.v{}.bak", from_version));
+            std::fs::copy(path, &backup)
+                .with_context(|| format!("Failed to write backup {:?}", backup))?;
+            write_atomic(path, &migrated)?;
+            println!(
+                "Upgraded {} from schema version {} to {} (original kept as {})",
+                path.display(),
+                from_version,
+                CONFIG_VERSION,
+                backup.display()
+            );
+            contents = migrated;
+        }
+
+        let (config, diagnostics) = validate::check_source(&contents);
+
+        for diagnostic in diagnostics
+            .iter()
+            .filter(|d| d.severity == validate::Severity::Warning)
+        {
+            println!("Warning: {}: {}", path.display(), diagnostic);
+        }
+
+        match config {
+            Some(config) if !validate::has_errors(&diagnostics) => Ok(config),
+            _ => {
+                let errors: Vec<String> = diagnostics
+                    .iter()
+                    .filter(|d| d.severity == validate::Severity::Error)
+                    .map(|d| format!("  {}", d))
+                    .collect();
+                anyhow::bail!("Invalid config {}:\n{}", path.display(), errors.join("\n"))
+            }
+        }
+    }
+
+    /// Write the config to the default location
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = config_path();
+        self.save_to(&path)?;
+        Ok(path)
+    }
+
+    /// Atomically write the config to `path`.
+    ///
+    /// The previous file (if any) is kept as `config.json.bak`.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialise config")?;
+
+        if path.exists() {
+            let backup = path.with_extension("json.bak");
+            std::fs::copy(path, &backup)
+                .with_context(|| format!("Failed to write backup {:?}", backup))?;
+        }
+
+        write_atomic(path, &json)
+    }
+}
+
+/// Write `contents` to a temporary file next to `path` and rename it over the target,
+/// so a crash mid-write never leaves a truncated config behind
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create config directory {:?}", dir))?;
+    }
+
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, contents).with_context(|| format!("Failed to write {:?}", tmp))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+/// JSON Schema describing the current config format, for editor validation
+pub fn json_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Config)).expect("schema serialises to JSON")
+}
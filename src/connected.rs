@@ -2,11 +2,14 @@ use crate::net::ScreenInfo;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
 
 #[derive(Clone, Debug)]
 pub struct ConnectedClient {
     pub addr: SocketAddr,
     pub screen_info: ScreenInfo,
+    /// Notified to disconnect this client from the server side
+    pub kick: Arc<Notify>,
 }
 
 pub type ConnectedClients = Arc<Mutex<HashMap<SocketAddr, ConnectedClient>>>;
@@ -14,3 +17,14 @@ pub type ConnectedClients = Arc<Mutex<HashMap<SocketAddr, ConnectedClient>>>;
 pub fn create_connected_clients() -> ConnectedClients {
     Arc::new(Mutex::new(HashMap::new()))
 }
+
+/// Disconnect every client with the given name. Returns how many were kicked.
+pub fn kick_by_name(connected_clients: &ConnectedClients, name: &str) -> usize {
+    let clients = connected_clients.lock().unwrap();
+    let mut kicked = 0;
+    for client in clients.values().filter(|c| c.screen_info.name == name) {
+        client.kick.notify_one();
+        kicked += 1;
+    }
+    kicked
+}
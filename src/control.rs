@@ -0,0 +1,493 @@
+use crate::connected::ConnectedClients;
+use crate::event::ClientEvent;
+use crate::events::ServerEvent;
+use crate::net::PROTOCOL_VERSION;
+use crate::pairing::SharedPairing;
+use crate::topology::{Focus, SharedTopology};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf,
+};
+use tokio::sync::broadcast;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Everything the control API can inspect or change on the running server
+#[derive(Clone)]
+pub struct ControlState {
+    pub port: u16,
+    pub topology: SharedTopology,
+    pub connected_clients: ConnectedClients,
+    pub pairing: SharedPairing,
+    /// Where macros send their events
+    pub tx: broadcast::Sender<ClientEvent>,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl Response {
+    fn ok(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    fn error(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(RpcError {
+                code,
+                message: message.into(),
+            }),
+        }
+    }
+}
+
+fn string_param(params: &Value, key: &str) -> Option<String> {
+    params.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+/// Handle one JSON-RPC request
+fn dispatch(state: &ControlState, request: Request) -> Response {
+    let id = request.id;
+    let params = request.params;
+
+    match request.method.as_str() {
+        "status" => {
+            let topo = state.topology.lock().unwrap();
+            let clients = state.connected_clients.lock().unwrap().len();
+            Response::ok(
+                id,
+                json!({
+                    "protocol_version": PROTOCOL_VERSION,
+                    "port": state.port,
+                    "active_profile": topo.get_config().active().name,
+                    "focus": topo.get_focus().name(),
+                    "focus_locked": topo.is_focus_locked(),
+                    "paused": topo.is_paused(),
+                    "clients": clients,
+                }),
+            )
+        }
+        "clients" => {
+            let clients = state.connected_clients.lock().unwrap();
+            let mut list: Vec<Value> = clients
+                .values()
+                .map(|c| {
+                    json!({
+                        "name": c.screen_info.name,
+                        "addr": c.addr.to_string(),
+                        "x": c.screen_info.x,
+                        "y": c.screen_info.y,
+                        "width": c.screen_info.width,
+                        "height": c.screen_info.height,
+                    })
+                })
+                .collect();
+            list.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+            let pending: Vec<Value> = state
+                .pairing
+                .lock()
+                .unwrap()
+                .pending()
+                .into_iter()
+                .map(|(name, addr)| json!({ "name": name, "addr": addr.to_string() }))
+                .collect();
+
+            Response::ok(id, json!({ "connected": list, "pending": pending }))
+        }
+        "focus" => {
+            let topo = state.topology.lock().unwrap();
+            Response::ok(id, json!({ "focus": topo.get_focus().name() }))
+        }
+        "set_focus" => {
+            let Some(target) = string_param(&params, "target") else {
+                return Response::error(id, INVALID_PARAMS, "missing 'target'");
+            };
+            let focus = if target == "local" {
+                Focus::Local
+            } else if state.topology.lock().unwrap().is_paused() {
+                return Response::error(id, SERVER_ERROR, "sharing is paused");
+            } else {
+                let connected = state
+                    .connected_clients
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .any(|c| c.screen_info.name == target);
+                if !connected {
+                    return Response::error(
+                        id,
+                        SERVER_ERROR,
+                        format!("client '{}' is not connected", target),
+                    );
+                }
+                Focus::Client(target.clone())
+            };
+
+            println!("Control API: switching focus to {:?}", focus);
+            state.topology.lock().unwrap().set_focus(focus);
+            Response::ok(id, json!({ "focus": target }))
+        }
+        "lock_focus" => {
+            let mut topo = state.topology.lock().unwrap();
+            let locked = match params.get("locked") {
+                None | Some(Value::Null) => !topo.is_focus_locked(),
+                Some(Value::Bool(locked)) => *locked,
+                Some(_) => {
+                    return Response::error(id, INVALID_PARAMS, "'locked' must be a boolean");
+                }
+            };
+            topo.set_focus_locked(locked);
+            Response::ok(
+                id,
+                json!({ "focus_locked": locked, "focus": topo.get_focus().name() }),
+            )
+        }
+        "set_paused" => {
+            let mut topo = state.topology.lock().unwrap();
+            let paused = match params.get("paused") {
+                None | Some(Value::Null) => !topo.is_paused(),
+                Some(Value::Bool(paused)) => *paused,
+                Some(_) => {
+                    return Response::error(id, INVALID_PARAMS, "'paused' must be a boolean");
+                }
+            };
+            topo.set_paused(paused);
+            Response::ok(
+                id,
+                json!({ "paused": paused, "focus": topo.get_focus().name() }),
+            )
+        }
+        "switch_profile" => {
+            let Some(name) = string_param(&params, "name") else {
+                return Response::error(id, INVALID_PARAMS, "missing 'name'");
+            };
+            match state.topology.lock().unwrap().switch_profile(&name) {
+                Ok(()) => Response::ok(id, json!({ "active_profile": name })),
+                Err(e) => Response::error(id, SERVER_ERROR, format!("{:#}", e)),
+            }
+        }
+        "kick" => {
+            let Some(name) = string_param(&params, "name") else {
+                return Response::error(id, INVALID_PARAMS, "missing 'name'");
+            };
+            match crate::connected::kick_by_name(&state.connected_clients, &name) {
+                0 => Response::error(
+                    id,
+                    SERVER_ERROR,
+                    format!("client '{}' is not connected", name),
+                ),
+                kicked => Response::ok(id, json!({ "kicked": kicked })),
+            }
+        }
+        "approve_pairing" => {
+            let Some(name) = string_param(&params, "name") else {
+                return Response::error(id, INVALID_PARAMS, "missing 'name'");
+            };
+            if state.pairing.lock().unwrap().approve(&name) {
+                Response::ok(id, json!({ "approved": name }))
+            } else {
+                Response::error(
+                    id,
+                    SERVER_ERROR,
+                    format!("no client named '{}' is waiting for approval", name),
+                )
+            }
+        }
+        "run_macro" => {
+            let Some(name) = string_param(&params, "name") else {
+                return Response::error(id, INVALID_PARAMS, "missing 'name'");
+            };
+            let topo = state.topology.lock().unwrap();
+            match crate::macros::start(&topo, &state.tx, &name, &HashSet::new()) {
+                Ok(client) => Response::ok(id, json!({ "macro": name, "target": client })),
+                Err(e) => Response::error(id, SERVER_ERROR, format!("{:#}", e)),
+            }
+        }
+        other => Response::error(id, METHOD_NOT_FOUND, format!("unknown method '{}'", other)),
+    }
+}
+
+/// Serve newline-delimited JSON-RPC requests on one connection
+pub async fn handle_connection<S>(state: ControlState, stream: S) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (read, mut write) = tokio::io::split(stream);
+    let mut lines = BufReader::new(read).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            // The connection becomes a one-way event stream after subscribing
+            Ok(request) if request.method == "subscribe" => {
+                let events = state.topology.lock().unwrap().subscribe();
+                write_line(
+                    &mut write,
+                    &Response::ok(request.id, json!({ "subscribed": true })),
+                )
+                .await?;
+                return stream_events(events, &mut write).await;
+            }
+            // Loading the file blocks, so it's awaited here rather than dispatched
+            Ok(request) if request.method == "reload" => {
+                match crate::reload::reload_in_background(state.topology.clone()).await {
+                    Ok(()) => Response::ok(request.id, json!({ "reloaded": true })),
+                    Err(e) => Response::error(request.id, SERVER_ERROR, format!("{:#}", e)),
+                }
+            }
+            Ok(request) => dispatch(&state, request),
+            Err(e) => Response::error(Value::Null, PARSE_ERROR, e.to_string()),
+        };
+
+        write_line(&mut write, &response).await?;
+    }
+    Ok(())
+}
+
+async fn write_line<W, T>(write: &mut W, message: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut out = serde_json::to_vec(message)?;
+    out.push(b'\n');
+    write.write_all(&out).await?;
+    write.flush().await?;
+    Ok(())
+}
+
+/// Forward server events as JSON-RPC `event` notifications until the peer goes away
+async fn stream_events<W>(mut events: broadcast::Receiver<ServerEvent>, write: &mut W) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "event",
+                    "params": event,
+                });
+                write_line(write, &notification).await?;
+            }
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                println!("Control API subscriber lagged, {} events dropped", missed);
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// Any stream the control API can run over (Unix socket or named pipe)
+pub trait ControlStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ControlStream for T {}
+
+/// Client side of the control API, used by `aurora_ctl`
+pub struct ControlClient {
+    lines: Lines<BufReader<ReadHalf<Box<dyn ControlStream>>>>,
+    write: WriteHalf<Box<dyn ControlStream>>,
+    next_id: u64,
+}
+
+impl ControlClient {
+    /// Connect to the running server's control endpoint
+    pub async fn connect() -> Result<Self> {
+        #[cfg(unix)]
+        let stream: Box<dyn ControlStream> = {
+            let path = socket_path();
+            let stream = tokio::net::UnixStream::connect(&path)
+                .await
+                .with_context(|| {
+                    format!("Is aurora_server running? Failed to connect to {:?}", path)
+                })?;
+            Box::new(stream)
+        };
+        #[cfg(windows)]
+        let stream: Box<dyn ControlStream> = {
+            let pipe = tokio::net::windows::named_pipe::ClientOptions::new()
+                .open(PIPE_NAME)
+                .with_context(|| {
+                    format!("Is aurora_server running? Failed to open {}", PIPE_NAME)
+                })?;
+            Box::new(pipe)
+        };
+
+        let (read, write) = tokio::io::split(stream);
+        Ok(Self {
+            lines: BufReader::new(read).lines(),
+            write,
+            next_id: 1,
+        })
+    }
+
+    /// Call a method and return its result, turning JSON-RPC errors into `Err`
+    pub async fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        write_line(&mut self.write, &request).await?;
+
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| anyhow!("Server closed the control connection"))?;
+        let mut response: Value = serde_json::from_str(&line)?;
+
+        if let Some(error) = response.get("error") {
+            let message = error["message"].as_str().unwrap_or("unknown error");
+            bail!("{}", message);
+        }
+        Ok(response["result"].take())
+    }
+
+    /// Wait for the next event after a `subscribe` call. `None` once the server goes away.
+    pub async fn next_event(&mut self) -> Result<Option<Value>> {
+        while let Some(line) = self.lines.next_line().await? {
+            let mut notification: Value = serde_json::from_str(&line)?;
+            if notification["method"] == "event" {
+                return Ok(Some(notification["params"].take()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Path of the control socket: `runtime_dir()/aurora_kvm/control.sock`
+#[cfg(unix)]
+pub fn socket_path() -> std::path::PathBuf {
+    let dir = match dirs::runtime_dir() {
+        Some(dir) => dir.join("aurora_kvm"),
+        // No XDG runtime dir (e.g. macOS): fall back to a per-user temp directory
+        None => std::env::temp_dir().join(format!("aurora_kvm-{}", unsafe { libc::getuid() })),
+    };
+    dir.join("control.sock")
+}
+
+/// Listen on the control socket until the process exits.
+///
+/// The socket lives in a 0700 directory, is itself 0600, and every peer's uid is
+/// checked against ours, so only the owning user can connect.
+#[cfg(unix)]
+pub async fn serve(state: ControlState) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::{UnixListener, UnixStream};
+
+    let path = socket_path();
+    let dir = path.parent().expect("socket path has a parent");
+    std::fs::create_dir_all(dir)?;
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+
+    if path.exists() {
+        if UnixStream::connect(&path).await.is_ok() {
+            anyhow::bail!("Another server is already listening on {:?}", path);
+        }
+        // Stale socket from a previous run
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    println!("Control API listening on {:?}", path);
+
+    let uid = unsafe { libc::getuid() };
+    loop {
+        let (stream, _) = listener.accept().await?;
+
+        match stream.peer_cred() {
+            Ok(cred) if cred.uid() == uid => {}
+            Ok(cred) => {
+                println!("Control API: rejected connection from uid {}", cred.uid());
+                continue;
+            }
+            Err(e) => {
+                println!("Control API: could not verify peer: {}", e);
+                continue;
+            }
+        }
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(state, stream).await {
+                println!("Control API connection error: {}", e);
+            }
+        });
+    }
+}
+
+/// Name of the control pipe
+#[cfg(windows)]
+pub const PIPE_NAME: &str = r"\\.\pipe\aurora_kvm-control";
+
+/// Listen on the control named pipe until the process exits.
+///
+/// The pipe's default security descriptor only grants write access to its creator,
+/// SYSTEM and administrators, and remote clients are rejected.
+#[cfg(windows)]
+pub async fn serve(state: ControlState) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(PIPE_NAME)?;
+    println!("Control API listening on {}", PIPE_NAME);
+
+    loop {
+        server.connect().await?;
+        let connected = server;
+        server = ServerOptions::new()
+            .reject_remote_clients(true)
+            .create(PIPE_NAME)?;
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(state, connected).await {
+                println!("Control API connection error: {}", e);
+            }
+        });
+    }
+}
@@ -48,16 +48,14 @@ pub async fn discover_servers(timeout_secs: u64) -> Result<Vec<(ServerAnnounceme
     while start.elapsed().as_secs() < timeout_secs {
         tokio::select! {
             result = socket.recv_from(&mut buf) => {
-                if let Ok((len, addr)) = result {
-                    if let Ok(announcement) = bincode::deserialize::<ServerAnnouncement>(&buf[..len]) {
-                        // Only accept from local network
-                        if is_local_network(addr.ip()) {
-                            // Avoid duplicates
-                            if !discovered.iter().any(|(_, ip)| *ip == addr.ip()) {
-                                discovered.push((announcement, addr.ip()));
-                            }
-                        }
-                    }
+                if let Ok((len, addr)) = result
+                    && let Ok(announcement) = bincode::deserialize::<ServerAnnouncement>(&buf[..len])
+                    // Only accept from local network
+                    && is_local_network(addr.ip())
+                    // Avoid duplicates
+                    && !discovered.iter().any(|(_, ip)| *ip == addr.ip())
+                {
+                    discovered.push((announcement, addr.ip()));
                 }
             }
             _ = sleep(Duration::from_millis(100)) => {
@@ -0,0 +1,99 @@
+//! Edge switching policies: whether pushing the cursor against an edge of the
+//! local screens should move focus to the client behind it.
+//!
+//! `decide` holds all of the logic. It is a pure function of the policy, the
+//! previous state, the current cursor contact and the time, so it can be tested
+//! without a clock or real input.
+
+use crate::config::EdgePolicy;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// A side of a screen
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// The cursor pushing against an edge that has a client behind it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeContact {
+    pub edge: Edge,
+    /// Position along the edge, from its top or left end
+    pub offset: f64,
+    /// Length of the edge
+    pub length: f64,
+}
+
+/// What the policies remember between cursor moves
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EdgeState {
+    contact: Option<Contact>,
+    /// The edge last pushed against and when, for double-tap detection
+    last_tap: Option<(Edge, Instant)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Contact {
+    edge: Edge,
+    since: Instant,
+    /// Whether this push completed a double tap (always true without that policy)
+    armed: bool,
+}
+
+/// Decide whether a cursor move at `now` switches focus.
+///
+/// `contact` is `None` when the cursor is not pushing against an edge, and
+/// `dragging` is whether a mouse button is held. Returns the decision and the
+/// state to pass in with the next move.
+pub fn decide(
+    policy: &EdgePolicy,
+    state: &EdgeState,
+    contact: Option<&EdgeContact>,
+    dragging: bool,
+    now: Instant,
+) -> (bool, EdgeState) {
+    let mut next = *state;
+
+    // A blocked push counts as not touching the edge at all
+    let Some(contact) = contact.filter(|contact| !blocked(policy, contact, dragging)) else {
+        next.contact = None;
+        return (false, next);
+    };
+
+    let current = match state.contact {
+        Some(current) if current.edge == contact.edge => current,
+        _ => {
+            // Arriving at an edge is a tap
+            let armed = policy.double_tap_ms == 0
+                || matches!(state.last_tap, Some((edge, at))
+                    if edge == contact.edge
+                        && now.saturating_duration_since(at)
+                            <= Duration::from_millis(policy.double_tap_ms));
+            next.last_tap = Some((contact.edge, now));
+            Contact {
+                edge: contact.edge,
+                since: now,
+                armed,
+            }
+        }
+    };
+
+    let rested = now.saturating_duration_since(current.since)
+        >= Duration::from_millis(policy.switch_delay_ms);
+    if current.armed && rested {
+        return (true, EdgeState::default());
+    }
+
+    next.contact = Some(current);
+    (false, next)
+}
+
+fn blocked(policy: &EdgePolicy, contact: &EdgeContact, dragging: bool) -> bool {
+    let guard = policy.corner_guard_px as f64;
+    let near_corner = contact.offset < guard || contact.length - contact.offset < guard;
+    near_corner || (policy.block_while_dragging && dragging)
+}
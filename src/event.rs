@@ -1,25 +1,59 @@
+use crate::scancode;
 use rdev::{Button as RdevButton, EventType, Key as RdevKey};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum KvmEvent {
-    KeyPress(Key),
-    KeyRelease(Key),
+    /// `usage` is the HID usage of the physical key, or `NO_USAGE` if unknown.
+    /// Injectors prefer it over `key` so the same key position is pressed
+    /// regardless of OS or layout.
+    KeyPress {
+        key: Key,
+        usage: u32,
+    },
+    KeyRelease {
+        key: Key,
+        usage: u32,
+    },
     ButtonPress(Button),
     ButtonRelease(Button),
-    MouseMove { x: f64, y: f64 },
-    Wheel { delta_x: i64, delta_y: i64 },
+    MouseMove {
+        x: f64,
+        y: f64,
+    },
+    /// Scroll distance in wheel notches; fractions come from high-resolution
+    /// wheels and touchpads (1/120 notch steps). Positive is up and right.
+    Wheel {
+        delta_x: f64,
+        delta_y: f64,
+    },
+    /// Text already resolved with the server's keyboard layout, for clients in
+    /// text mode. Injected as Unicode input rather than key presses.
+    Text(String),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+/// A `KvmEvent` on its way to one client, as passed from the input pipeline to
+/// the client connections
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientEvent {
+    /// Name the client connected with
+    pub client: String,
+    pub event: KvmEvent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Button {
     Left,
     Right,
     Middle,
+    /// Thumb button usually bound to "back"
+    X1,
+    /// Thumb button usually bound to "forward"
+    X2,
     Unknown(u8),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Key {
     Alt,
     AltGr,
@@ -126,32 +160,408 @@ pub enum Key {
     Kp9,
     KpDelete,
     Function,
+    ContextMenu,
+    Power,
+    Sleep,
+    KpEqual,
+    KpComma,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    Help,
+    Again,
+    Undo,
+    Cut,
+    Copy,
+    Paste,
+    Find,
+    IntlRo,
+    IntlYen,
+    KanaMode,
+    Convert,
+    NonConvert,
+    Hangul,
+    Hanja,
+    Katakana,
+    Hiragana,
+    ZenkakuHankaku,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaPlayPause,
+    MediaStop,
+    MediaNextTrack,
+    MediaPrevTrack,
+    Eject,
+    BrightnessUp,
+    BrightnessDown,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    LaunchMail,
+    LaunchMediaSelect,
+    LaunchApp1,
+    LaunchApp2,
     Unknown(u32),
 }
 
+impl Key {
+    /// Every named key, for tests and tooling
+    pub const ALL: &[Key] = &[
+        Key::Alt,
+        Key::AltGr,
+        Key::Backspace,
+        Key::CapsLock,
+        Key::ControlLeft,
+        Key::ControlRight,
+        Key::Delete,
+        Key::DownArrow,
+        Key::End,
+        Key::Escape,
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+        Key::Home,
+        Key::LeftArrow,
+        Key::MetaLeft,
+        Key::MetaRight,
+        Key::PageDown,
+        Key::PageUp,
+        Key::Return,
+        Key::RightArrow,
+        Key::ShiftLeft,
+        Key::ShiftRight,
+        Key::Space,
+        Key::Tab,
+        Key::UpArrow,
+        Key::PrintScreen,
+        Key::ScrollLock,
+        Key::Pause,
+        Key::NumLock,
+        Key::BackQuote,
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+        Key::Num0,
+        Key::Minus,
+        Key::Equal,
+        Key::KeyQ,
+        Key::KeyW,
+        Key::KeyE,
+        Key::KeyR,
+        Key::KeyT,
+        Key::KeyY,
+        Key::KeyU,
+        Key::KeyI,
+        Key::KeyO,
+        Key::KeyP,
+        Key::LeftBracket,
+        Key::RightBracket,
+        Key::KeyA,
+        Key::KeyS,
+        Key::KeyD,
+        Key::KeyF,
+        Key::KeyG,
+        Key::KeyH,
+        Key::KeyJ,
+        Key::KeyK,
+        Key::KeyL,
+        Key::SemiColon,
+        Key::Quote,
+        Key::BackSlash,
+        Key::IntlBackslash,
+        Key::KeyZ,
+        Key::KeyX,
+        Key::KeyC,
+        Key::KeyV,
+        Key::KeyB,
+        Key::KeyN,
+        Key::KeyM,
+        Key::Comma,
+        Key::Dot,
+        Key::Slash,
+        Key::Insert,
+        Key::KpReturn,
+        Key::KpMinus,
+        Key::KpPlus,
+        Key::KpMultiply,
+        Key::KpDivide,
+        Key::Kp0,
+        Key::Kp1,
+        Key::Kp2,
+        Key::Kp3,
+        Key::Kp4,
+        Key::Kp5,
+        Key::Kp6,
+        Key::Kp7,
+        Key::Kp8,
+        Key::Kp9,
+        Key::KpDelete,
+        Key::Function,
+        Key::ContextMenu,
+        Key::Power,
+        Key::Sleep,
+        Key::KpEqual,
+        Key::KpComma,
+        Key::F13,
+        Key::F14,
+        Key::F15,
+        Key::F16,
+        Key::F17,
+        Key::F18,
+        Key::F19,
+        Key::F20,
+        Key::F21,
+        Key::F22,
+        Key::F23,
+        Key::F24,
+        Key::Help,
+        Key::Again,
+        Key::Undo,
+        Key::Cut,
+        Key::Copy,
+        Key::Paste,
+        Key::Find,
+        Key::IntlRo,
+        Key::IntlYen,
+        Key::KanaMode,
+        Key::Convert,
+        Key::NonConvert,
+        Key::Hangul,
+        Key::Hanja,
+        Key::Katakana,
+        Key::Hiragana,
+        Key::ZenkakuHankaku,
+        Key::VolumeMute,
+        Key::VolumeDown,
+        Key::VolumeUp,
+        Key::MediaPlayPause,
+        Key::MediaStop,
+        Key::MediaNextTrack,
+        Key::MediaPrevTrack,
+        Key::Eject,
+        Key::BrightnessUp,
+        Key::BrightnessDown,
+        Key::BrowserBack,
+        Key::BrowserForward,
+        Key::BrowserRefresh,
+        Key::BrowserStop,
+        Key::BrowserSearch,
+        Key::BrowserFavorites,
+        Key::BrowserHome,
+        Key::LaunchMail,
+        Key::LaunchMediaSelect,
+        Key::LaunchApp1,
+        Key::LaunchApp2,
+    ];
+}
+
+impl KvmEvent {
+    /// Key press with the usage looked up from the logical key
+    pub fn key_press(key: Key) -> Self {
+        KvmEvent::KeyPress {
+            key,
+            usage: scancode::usage_for_key(key),
+        }
+    }
+
+    /// Key release with the usage looked up from the logical key
+    pub fn key_release(key: Key) -> Self {
+        KvmEvent::KeyRelease {
+            key,
+            usage: scancode::usage_for_key(key),
+        }
+    }
+}
+
+/// Platform codes for keys rdev has no variant for. rdev reports and simulates
+/// them as `Unknown(code)`: virtual-key codes on Windows, `kVK_*` codes on macOS
+/// and X11 keycodes on Linux, which come from the scancode tables instead.
+///
+/// Each code appears once so it decodes to one key. `VK_KANA` and `VK_HANGUL`
+/// are the same code, read as `KanaMode`; `Hangul` is injected by scancode.
+pub const WINDOWS_VK_CODES: &[(Key, u32)] = &[
+    (Key::ContextMenu, 0x5D),
+    (Key::Sleep, 0x5F),
+    (Key::KpEqual, 0x92),
+    (Key::KpComma, 0xC2),
+    (Key::F13, 0x7C),
+    (Key::F14, 0x7D),
+    (Key::F15, 0x7E),
+    (Key::F16, 0x7F),
+    (Key::F17, 0x80),
+    (Key::F18, 0x81),
+    (Key::F19, 0x82),
+    (Key::F20, 0x83),
+    (Key::F21, 0x84),
+    (Key::F22, 0x85),
+    (Key::F23, 0x86),
+    (Key::F24, 0x87),
+    (Key::Help, 0x2F),
+    (Key::IntlRo, 0xC1),
+    (Key::KanaMode, 0x15),
+    (Key::Hanja, 0x19),
+    (Key::Convert, 0x1C),
+    (Key::NonConvert, 0x1D),
+    (Key::VolumeMute, 0xAD),
+    (Key::VolumeDown, 0xAE),
+    (Key::VolumeUp, 0xAF),
+    (Key::MediaNextTrack, 0xB0),
+    (Key::MediaPrevTrack, 0xB1),
+    (Key::MediaStop, 0xB2),
+    (Key::MediaPlayPause, 0xB3),
+    (Key::LaunchMail, 0xB4),
+    (Key::LaunchMediaSelect, 0xB5),
+    (Key::LaunchApp1, 0xB6),
+    (Key::LaunchApp2, 0xB7),
+    (Key::BrowserBack, 0xA6),
+    (Key::BrowserForward, 0xA7),
+    (Key::BrowserRefresh, 0xA8),
+    (Key::BrowserStop, 0xA9),
+    (Key::BrowserSearch, 0xAA),
+    (Key::BrowserFavorites, 0xAB),
+    (Key::BrowserHome, 0xAC),
+];
+
+/// `kVK_*` codes for keys rdev has no variant for on macOS
+pub const MACOS_VK_CODES: &[(Key, u32)] = &[
+    (Key::ContextMenu, 0x6E),
+    (Key::KpEqual, 0x51),
+    (Key::KpComma, 0x5F),
+    (Key::F13, 0x69),
+    (Key::F14, 0x6B),
+    (Key::F15, 0x71),
+    (Key::F16, 0x6A),
+    (Key::F17, 0x40),
+    (Key::F18, 0x4F),
+    (Key::F19, 0x50),
+    (Key::F20, 0x5A),
+    (Key::Help, 0x72),
+    (Key::IntlRo, 0x5E),
+    (Key::IntlYen, 0x5D),
+    (Key::KanaMode, 0x68),
+    (Key::VolumeUp, 0x48),
+    (Key::VolumeDown, 0x49),
+    (Key::VolumeMute, 0x4A),
+];
+
+#[cfg(windows)]
+const PLATFORM_CODES: &[(Key, u32)] = WINDOWS_VK_CODES;
+#[cfg(target_os = "macos")]
+const PLATFORM_CODES: &[(Key, u32)] = MACOS_VK_CODES;
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+const PLATFORM_CODES: &[(Key, u32)] = &[];
+
+#[cfg(target_os = "linux")]
+fn platform_code(key: Key) -> Option<u32> {
+    scancode::x11_from_usage(scancode::usage_for_key(key))
+}
+
+#[cfg(target_os = "linux")]
+fn key_from_platform_code(code: u32) -> Key {
+    scancode::usage_from_x11(code)
+        .and_then(scancode::key_for_usage)
+        .unwrap_or(Key::Unknown(code))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn platform_code(key: Key) -> Option<u32> {
+    PLATFORM_CODES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, code)| *code)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn key_from_platform_code(code: u32) -> Key {
+    PLATFORM_CODES
+        .iter()
+        .find(|(_, c)| *c == code)
+        .map(|(key, _)| *key)
+        .unwrap_or(Key::Unknown(code))
+}
+
+/// rdev's numbers for the thumb buttons: `XBUTTON1`/`XBUTTON2` on Windows and
+/// buttons 8/9 on X11
+#[cfg(windows)]
+const X_BUTTONS: (u8, u8) = (1, 2);
+#[cfg(not(windows))]
+const X_BUTTONS: (u8, u8) = (8, 9);
+
+/// rdev wheel units per notch: macOS reports and simulates pixels, the other
+/// platforms whole notches
+#[cfg(target_os = "macos")]
+const RDEV_WHEEL_UNITS: f64 = 10.0;
+#[cfg(not(target_os = "macos"))]
+const RDEV_WHEEL_UNITS: f64 = 1.0;
+
 impl From<EventType> for KvmEvent {
     fn from(event: EventType) -> Self {
         match event {
-            EventType::KeyPress(key) => KvmEvent::KeyPress(key.into()),
-            EventType::KeyRelease(key) => KvmEvent::KeyRelease(key.into()),
+            EventType::KeyPress(key) => KvmEvent::KeyPress {
+                key: key.into(),
+                usage: scancode::usage_for_key(key.into()),
+            },
+            EventType::KeyRelease(key) => KvmEvent::KeyRelease {
+                key: key.into(),
+                usage: scancode::usage_for_key(key.into()),
+            },
             EventType::ButtonPress(btn) => KvmEvent::ButtonPress(btn.into()),
             EventType::ButtonRelease(btn) => KvmEvent::ButtonRelease(btn.into()),
             EventType::MouseMove { x, y } => KvmEvent::MouseMove { x, y },
-            EventType::Wheel { delta_x, delta_y } => KvmEvent::Wheel { delta_x, delta_y },
+            EventType::Wheel { delta_x, delta_y } => KvmEvent::Wheel {
+                delta_x: delta_x as f64 / RDEV_WHEEL_UNITS,
+                delta_y: delta_y as f64 / RDEV_WHEEL_UNITS,
+            },
         }
     }
 }
 
-impl From<KvmEvent> for EventType {
-    fn from(event: KvmEvent) -> Self {
-        match event {
-            KvmEvent::KeyPress(key) => EventType::KeyPress(key.into()),
-            KvmEvent::KeyRelease(key) => EventType::KeyRelease(key.into()),
+impl TryFrom<KvmEvent> for EventType {
+    type Error = anyhow::Error;
+
+    fn try_from(event: KvmEvent) -> anyhow::Result<Self> {
+        Ok(match event {
+            KvmEvent::KeyPress { key, .. } => EventType::KeyPress(key.into()),
+            KvmEvent::KeyRelease { key, .. } => EventType::KeyRelease(key.into()),
             KvmEvent::ButtonPress(btn) => EventType::ButtonPress(btn.into()),
             KvmEvent::ButtonRelease(btn) => EventType::ButtonRelease(btn.into()),
             KvmEvent::MouseMove { x, y } => EventType::MouseMove { x, y },
-            KvmEvent::Wheel { delta_x, delta_y } => EventType::Wheel { delta_x, delta_y },
-        }
+            // Rounds away fractions; injectors that can do better handle wheels themselves
+            KvmEvent::Wheel { delta_x, delta_y } => EventType::Wheel {
+                delta_x: (delta_x * RDEV_WHEEL_UNITS).round() as i64,
+                delta_y: (delta_y * RDEV_WHEEL_UNITS).round() as i64,
+            },
+            KvmEvent::Text(_) => anyhow::bail!("Text has no rdev event equivalent"),
+        })
     }
 }
 
@@ -161,6 +571,8 @@ impl From<RdevButton> for Button {
             RdevButton::Left => Button::Left,
             RdevButton::Right => Button::Right,
             RdevButton::Middle => Button::Middle,
+            RdevButton::Unknown(u) if u == X_BUTTONS.0 => Button::X1,
+            RdevButton::Unknown(u) if u == X_BUTTONS.1 => Button::X2,
             RdevButton::Unknown(u) => Button::Unknown(u),
         }
     }
@@ -172,6 +584,8 @@ impl From<Button> for RdevButton {
             Button::Left => RdevButton::Left,
             Button::Right => RdevButton::Right,
             Button::Middle => RdevButton::Middle,
+            Button::X1 => RdevButton::Unknown(X_BUTTONS.0),
+            Button::X2 => RdevButton::Unknown(X_BUTTONS.1),
             Button::Unknown(u) => RdevButton::Unknown(u),
         }
     }
@@ -285,7 +699,7 @@ impl From<RdevKey> for Key {
             RdevKey::Kp9 => Key::Kp9,
             RdevKey::KpDelete => Key::KpDelete,
             RdevKey::Function => Key::Function,
-            RdevKey::Unknown(u) => Key::Unknown(u),
+            RdevKey::Unknown(code) => key_from_platform_code(code),
         }
     }
 }
@@ -399,6 +813,8 @@ impl From<Key> for RdevKey {
             Key::KpDelete => RdevKey::KpDelete,
             Key::Function => RdevKey::Function,
             Key::Unknown(u) => RdevKey::Unknown(u),
+            // Keys rdev cannot name; 0 makes rdev report a simulate error
+            key => RdevKey::Unknown(platform_code(key).unwrap_or(0)),
         }
     }
 }
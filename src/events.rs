@@ -0,0 +1,41 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// How many unread events a slow subscriber may lag behind before missing some
+const EVENT_BUFFER: usize = 64;
+
+/// Server state changes pushed to control API subscribers
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ServerEvent {
+    /// Focus moved; `focus` is `"local"` or a client name
+    FocusChanged {
+        focus: String,
+    },
+    ClientConnected {
+        name: String,
+        addr: String,
+    },
+    ClientDisconnected {
+        name: String,
+        addr: String,
+    },
+    ProfileChanged {
+        profile: String,
+    },
+    /// Edge switching was turned off (`locked`) or back on
+    FocusLockChanged {
+        locked: bool,
+    },
+    /// Sharing input with clients was paused or resumed
+    SharingPaused {
+        paused: bool,
+    },
+    ConfigReloaded,
+}
+
+pub type EventSender = broadcast::Sender<ServerEvent>;
+
+pub fn create_event_channel() -> EventSender {
+    broadcast::channel(EVENT_BUFFER).0
+}
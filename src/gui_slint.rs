@@ -1,9 +1,10 @@
-use crate::config::Config;
+use crate::config::{ClientConfig, Config};
 use crate::connected::ConnectedClients;
+use crate::topology::SharedTopology;
+use crate::tray::{self, TrayAction};
 use slint::Model;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::path::PathBuf;
 use std::rc::Rc;
 
 slint::include_modules!();
@@ -38,9 +39,10 @@ fn build_screen_model(
     client_positions: &Rc<RefCell<HashMap<String, (f32, f32)>>>,
 ) -> Vec<ScreenData> {
     let mut screens: Vec<ScreenData> = Vec::new();
+    let profile = config.active();
 
     // Add local screens (from config)
-    for (i, screen) in config.local_screens.iter().enumerate() {
+    for (i, screen) in profile.local_screens.iter().enumerate() {
         screens.push(ScreenData {
             name: format!("Local {}", i + 1),
             x: screen.x as f32,
@@ -51,8 +53,22 @@ fn build_screen_model(
         });
     }
 
-    // Add configured clients (gray)
-    for client in &config.clients {
+    let connected_names: Vec<String> = connected_clients
+        .as_ref()
+        .and_then(|connected| connected.lock().ok())
+        .map(|clients| {
+            clients
+                .values()
+                .map(|client| client.screen_info.name.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Add configured clients (gray), unless they are shown live below
+    for client in &profile.clients {
+        if connected_names.contains(&client.name) {
+            continue;
+        }
         screens.push(ScreenData {
             name: client.name.clone(),
             x: client.x as f32,
@@ -64,39 +80,45 @@ fn build_screen_model(
     }
 
     // Add live connected clients (green)
-    if let Some(connected) = connected_clients {
-        if let Ok(clients) = connected.lock() {
-            for (i, (_, client)) in clients.iter().enumerate() {
-                let name = format!("{} (Connected)", client.screen_info.name);
-
-                // Get position from persistence map or calculate default
-                let (x, y) = {
-                    let mut positions = client_positions.borrow_mut();
-                    if let Some(&pos) = positions.get(&name) {
-                        pos
-                    } else {
-                        let offset_x = (i as f32) * 1100.0;
-                        let default_x = 1000.0 + offset_x;
-                        let default_y = 750.0;
-                        positions.insert(name.clone(), (default_x, default_y));
-                        (default_x, default_y)
-                    }
-                };
+    if let Some(connected) = connected_clients
+        && let Ok(clients) = connected.lock()
+    {
+        for (i, (_, client)) in clients.iter().enumerate() {
+            let name = format!("{} (Connected)", client.screen_info.name);
+
+            // Get position from persistence map, the saved config, or calculate default
+            let (x, y) = {
+                let mut positions = client_positions.borrow_mut();
+                if let Some(&pos) = positions.get(&name) {
+                    pos
+                } else {
+                    let pos = profile
+                        .clients
+                        .iter()
+                        .find(|c| c.name == client.screen_info.name)
+                        .map(|c| (c.x as f32, c.y as f32))
+                        .unwrap_or_else(|| {
+                            let offset_x = (i as f32) * 1100.0;
+                            (1000.0 + offset_x, 750.0)
+                        });
+                    positions.insert(name.clone(), pos);
+                    pos
+                }
+            };
 
-                screens.push(ScreenData {
-                    name: name.clone(),
-                    x,
-                    y,
-                    width: client.screen_info.width as f32,
-                    height: client.screen_info.height as f32,
-                    connected: true,
-                });
-
-                eprintln!(
-                    "DEBUG: Added connected client '{}' at ({}, {})",
-                    client.screen_info.name, x, y
-                );
-            }
+            screens.push(ScreenData {
+                name: name.clone(),
+                x,
+                y,
+                width: client.screen_info.width as f32,
+                height: client.screen_info.height as f32,
+                connected: true,
+            });
+
+            eprintln!(
+                "DEBUG: Added connected client '{}' at ({}, {})",
+                client.screen_info.name, x, y
+            );
         }
     }
 
@@ -105,34 +127,77 @@ fn build_screen_model(
     screens
 }
 
-pub fn run_gui_slint(
-    connected_clients: Option<ConnectedClients>,
-) -> Result<(), slint::PlatformError> {
-    let ui = MainWindow::new()?;
+/// Build a config from the current canvas layout.
+///
+/// Local screens and configured clients keep their entries; connected clients are
+/// promoted to named `ClientConfig` entries (updating an existing entry of the same name).
+fn collect_config(
+    base: &Config,
+    screens: &[Screen],
+    connected_clients: &Option<ConnectedClients>,
+) -> Config {
+    let mut config = base.clone();
+    let profile = config.active_mut();
+    let local_count = profile.local_screens.len();
+
+    for (i, screen) in screens.iter().enumerate() {
+        let name = screen.name.to_string();
+
+        if i < local_count {
+            profile.local_screens[i].x = screen.x as i32;
+            profile.local_screens[i].y = screen.y as i32;
+            continue;
+        }
 
-    // Load config
-    let config_path = dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("aurora_kvm")
-        .join("config.json");
-
-    let mut config: Config = if config_path.exists() {
-        std::fs::read_to_string(&config_path)
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default()
-    } else {
-        Config::default()
-    };
+        let clean_name = name.replace(" (Connected)", "");
+        let ip = if screen.connected {
+            connected_clients
+                .as_ref()
+                .and_then(|connected| connected.lock().ok())
+                .and_then(|clients| {
+                    clients
+                        .values()
+                        .find(|c| c.screen_info.name == clean_name)
+                        .map(|c| c.addr.ip().to_string())
+                })
+        } else {
+            None
+        };
+
+        if let Some(client) = profile.clients.iter_mut().find(|c| c.name == clean_name) {
+            client.x = screen.x as i32;
+            client.y = screen.y as i32;
+            client.width = screen.width as u32;
+            client.height = screen.height as u32;
+            if let Some(ip) = ip {
+                client.ip = ip;
+            }
+        } else if screen.connected {
+            profile.clients.push(ClientConfig {
+                name: clean_name,
+                ip: ip.unwrap_or_default(),
+                x: screen.x as i32,
+                y: screen.y as i32,
+                width: screen.width as u32,
+                height: screen.height as u32,
+                ..Default::default()
+            });
+        }
+    }
 
-    // Auto-detect monitors if config has no local screens
-    if config.local_screens.is_empty() {
-        config.local_screens = crate::monitor::detect_monitors();
+    config
+}
+
+/// Auto-detect monitors if the active profile has no local screens
+fn detect_missing_screens(config: &mut Config) {
+    let profile = config.active_mut();
+    if profile.local_screens.is_empty() {
+        profile.local_screens = crate::monitor::detect_monitors();
         eprintln!(
             "DEBUG: Auto-detected {} local screens",
-            config.local_screens.len()
+            profile.local_screens.len()
         );
-        for (i, screen) in config.local_screens.iter().enumerate() {
+        for (i, screen) in profile.local_screens.iter().enumerate() {
             eprintln!(
                 "DEBUG:   Local Screen {}: {}x{} at ({}, {})",
                 i + 1,
@@ -144,10 +209,48 @@ pub fn run_gui_slint(
         }
     } else {
         eprintln!(
-            "DEBUG: Using {} local screens from config",
-            config.local_screens.len()
+            "DEBUG: Using {} local screens from profile '{}'",
+            profile.local_screens.len(),
+            profile.name
         );
     }
+}
+
+fn set_profile_list(ui: &MainWindow, config: &Config) {
+    let names: Vec<slint::SharedString> = config
+        .profile_names()
+        .into_iter()
+        .map(|name| name.into())
+        .collect();
+    ui.set_profiles(Rc::new(slint::VecModel::from(names)).into());
+    ui.set_active_profile(config.active().name.clone().into());
+}
+
+/// Run the configuration window until it is closed. With picks from a tray icon
+/// in `tray`, closing it only hides it and the tray's Quit ends the GUI instead,
+/// and `minimized` starts it hidden.
+pub fn run_gui_slint(
+    connected_clients: Option<ConnectedClients>,
+    topology: Option<SharedTopology>,
+    tray: Option<tokio::sync::mpsc::UnboundedReceiver<TrayAction>>,
+    minimized: bool,
+) -> Result<(), slint::PlatformError> {
+    // First, so the overlay can pick how Slint creates windows
+    let _overlay = topology.as_ref().and_then(crate::overlay::attach_local);
+    let ui = MainWindow::new()?;
+
+    // Use the server's config when running alongside it, otherwise load from disk
+    let mut config = match &topology {
+        Some(topology) => topology.lock().unwrap().get_config().clone(),
+        None => Config::load().unwrap_or_else(|e| {
+            eprintln!("{:#}", e);
+            Config::default()
+        }),
+    };
+
+    detect_missing_screens(&mut config);
+
+    set_profile_list(&ui, &config);
 
     // State for persisting client positions during session
     let client_positions = Rc::new(RefCell::new(HashMap::new()));
@@ -158,6 +261,8 @@ pub fn run_gui_slint(
     let model = Rc::new(slint::VecModel::from(screen_model));
     ui.set_screens(model.clone().into());
 
+    let config = Rc::new(RefCell::new(config));
+
     // Setup live update timer (500ms interval)
     let ui_weak_timer = ui.as_weak();
     let connected_clients_timer = connected_clients.clone();
@@ -167,12 +272,33 @@ pub fn run_gui_slint(
 
     let timer = slint::Timer::default();
     let last_signature = Rc::new(RefCell::new(String::new()));
+    let last_signature_timer = last_signature.clone();
+    let topology_timer = topology.clone();
 
     timer.start(
         slint::TimerMode::Repeated,
         std::time::Duration::from_millis(500),
         move || {
             if let Some(ui) = ui_weak_timer.upgrade() {
+                // Follow profile switches made outside the GUI (hotkey, CLI)
+                if let Some(topology) = &topology_timer {
+                    let switched = {
+                        let topo = topology.lock().unwrap();
+                        (topo.get_config().active_profile != config_timer.borrow().active_profile)
+                            .then(|| topo.get_config().clone())
+                    };
+                    if let Some(mut new_config) = switched {
+                        detect_missing_screens(&mut new_config);
+                        set_profile_list(&ui, &new_config);
+                        *config_timer.borrow_mut() = new_config;
+                        client_positions_timer.borrow_mut().clear();
+                        last_signature_timer.borrow_mut().clear();
+                    }
+
+                    // Follow focus lock toggles from the hotkey or control API
+                    ui.set_focus_locked(topology.lock().unwrap().is_focus_locked());
+                }
+
                 // Calculate current signature to check for changes
                 let current_signature = if let Some(connected) = &connected_clients_timer {
                     if let Ok(clients) = connected.lock() {
@@ -189,16 +315,16 @@ pub fn run_gui_slint(
                 };
 
                 // Only rebuild if signature changed
-                if *last_signature.borrow() != current_signature {
+                if *last_signature_timer.borrow() != current_signature {
                     eprintln!(
                         "DEBUG: Clients changed, updating model. Sig: {}",
                         current_signature
                     );
-                    *last_signature.borrow_mut() = current_signature;
+                    *last_signature_timer.borrow_mut() = current_signature;
 
                     // Rebuild screen model
                     let screens = build_screen_model(
-                        &config_timer,
+                        &config_timer.borrow(),
                         &connected_clients_timer,
                         &client_positions_timer,
                     );
@@ -214,9 +340,104 @@ pub fn run_gui_slint(
 
     // Setup callbacks
     let ui_weak = ui.as_weak();
+    let model_save = model.clone();
+    let config_save = config.clone();
+    let connected_clients_save = connected_clients.clone();
+    let topology_save = topology.clone();
+    let last_signature_save = last_signature.clone();
     ui.on_save_config(move || {
         if let Some(ui) = ui_weak.upgrade() {
-            ui.set_status_text("Config saved!".into());
+            let screens: Vec<Screen> = model_save.iter().collect();
+            // Start from the running config, which reloads and profile switches may
+            // have changed since the window opened
+            let base = match &topology_save {
+                Some(topology) => {
+                    let mut config = topology.lock().unwrap().get_config().clone();
+                    detect_missing_screens(&mut config);
+                    config
+                }
+                None => config_save.borrow().clone(),
+            };
+            let new_config = collect_config(&base, &screens, &connected_clients_save);
+
+            match new_config.save() {
+                Ok(path) => {
+                    println!("Config saved to {:?}", path);
+
+                    // Apply the new layout to the running server
+                    if let Some(topology) = &topology_save
+                        && let Ok(mut topo) = topology.lock()
+                    {
+                        topo.update_config(new_config.clone());
+                    }
+
+                    *config_save.borrow_mut() = new_config;
+                    // Force the timer to rebuild the model from the saved config
+                    last_signature_save.borrow_mut().clear();
+
+                    ui.set_status_text(format!("Config saved to {}", path.display()).into());
+                }
+                Err(e) => {
+                    eprintln!("Failed to save config: {:#}", e);
+                    ui.set_status_text(format!("Save failed: {:#}", e).into());
+                }
+            }
+        }
+    });
+
+    let ui_weak = ui.as_weak();
+    let config_profile = config.clone();
+    let client_positions_profile = client_positions.clone();
+    let last_signature_profile = last_signature.clone();
+    let topology_profile = topology.clone();
+    ui.on_switch_profile(move |name| {
+        if let Some(ui) = ui_weak.upgrade() {
+            let name = name.to_string();
+            let result = match &topology_profile {
+                Some(topology) => topology.lock().unwrap().switch_profile(&name),
+                None => {
+                    let mut config = config_profile.borrow().clone();
+                    config
+                        .set_active_profile(&name)
+                        .and_then(|_| config.save().map(|_| ()))
+                }
+            };
+
+            match result {
+                Ok(()) => {
+                    let mut config = config_profile.borrow_mut();
+                    let _ = config.set_active_profile(&name);
+                    detect_missing_screens(&mut config);
+                    client_positions_profile.borrow_mut().clear();
+                    last_signature_profile.borrow_mut().clear();
+                    ui.set_status_text(format!("Switched to profile '{}'", name).into());
+                }
+                Err(e) => {
+                    ui.set_active_profile(config_profile.borrow().active().name.clone().into());
+                    ui.set_status_text(format!("Profile switch failed: {:#}", e).into());
+                }
+            }
+        }
+    });
+
+    let ui_weak = ui.as_weak();
+    let topology_lock = topology.clone();
+    ui.on_toggle_focus_lock(move || {
+        if let Some(ui) = ui_weak.upgrade() {
+            let Some(topology) = &topology_lock else {
+                ui.set_status_text("Focus lock needs a running server".into());
+                return;
+            };
+            let mut topo = topology.lock().unwrap();
+            let locked = !topo.is_focus_locked();
+            topo.set_focus_locked(locked);
+            ui.set_focus_locked(locked);
+            let status = if locked {
+                format!("Focus pinned to {}", topo.get_focus().name())
+            } else {
+                "Focus unpinned".to_string()
+            };
+            ui.set_status_text(status.into());
         }
     });
 
@@ -249,21 +470,21 @@ pub fn run_gui_slint(
                     .insert(name.clone(), (new_x, new_y));
 
                 // Update shared state for server routing
-                if let Some(connected) = &connected_clients_moved {
-                    if let Ok(mut clients) = connected.lock() {
-                        // The name in the model is "Name (Connected)", but in clients it's just "Name"
-                        // We need to match correctly.
-                        let clean_name = name.replace(" (Connected)", "");
-
-                        for (_, client) in clients.iter_mut() {
-                            if client.screen_info.name == clean_name {
-                                client.screen_info.x = new_x as i32;
-                                client.screen_info.y = new_y as i32;
-                                eprintln!(
-                                    "DEBUG: Updated server routing for '{}' to ({}, {})",
-                                    clean_name, new_x, new_y
-                                );
-                            }
+                if let Some(connected) = &connected_clients_moved
+                    && let Ok(mut clients) = connected.lock()
+                {
+                    // The name in the model is "Name (Connected)", but in clients it's just "Name"
+                    // We need to match correctly.
+                    let clean_name = name.replace(" (Connected)", "");
+
+                    for (_, client) in clients.iter_mut() {
+                        if client.screen_info.name == clean_name {
+                            client.screen_info.x = new_x as i32;
+                            client.screen_info.y = new_y as i32;
+                            eprintln!(
+                                "DEBUG: Updated server routing for '{}' to ({}, {})",
+                                clean_name, new_x, new_y
+                            );
                         }
                     }
                 }
@@ -275,5 +496,41 @@ pub fn run_gui_slint(
     // This ensures it lives for the duration of the UI
     Box::leak(Box::new(timer));
 
-    ui.run()
+    let Some(mut actions) = tray else {
+        return ui.run();
+    };
+
+    let ui_weak_tray = ui.as_weak();
+    let tray_timer = slint::Timer::default();
+    tray_timer.start(
+        slint::TimerMode::Repeated,
+        std::time::Duration::from_millis(100),
+        move || {
+            while let Ok(action) = actions.try_recv() {
+                let action = match &topology {
+                    Some(topology) => tray::apply(topology, action),
+                    None => Some(action),
+                };
+                match action {
+                    Some(TrayAction::OpenConfiguration) => {
+                        if let Some(ui) = ui_weak_tray.upgrade()
+                            && let Err(e) = ui.show()
+                        {
+                            eprintln!("Failed to open the configuration: {}", e);
+                        }
+                    }
+                    Some(TrayAction::Quit) => {
+                        let _ = slint::quit_event_loop();
+                    }
+                    // Server picks without a server to apply them to
+                    _ => {}
+                }
+            }
+        },
+    );
+
+    if !minimized {
+        ui.show()?;
+    }
+    slint::run_event_loop_until_quit()
 }
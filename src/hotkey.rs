@@ -0,0 +1,93 @@
+use crate::event::Key;
+use anyhow::{Result, anyhow};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// A key chord such as `ControlLeft+Alt+KeyK`, written as `+`-separated key names.
+///
+/// Names are the `event::Key` variant names. The common aliases `Ctrl`, `Shift`,
+/// `Alt`, `Meta`, `Super`, `Win` and `Cmd` resolve to the left-hand modifier,
+/// and a single letter or digit such as `C` or `1` to that key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotkey {
+    pub modifiers: Vec<Key>,
+    pub key: Key,
+}
+
+/// Resolve a single key name to a `Key`
+pub fn parse_key_name(name: &str) -> Option<Key> {
+    let name = name.trim();
+    let alias = match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => Some(Key::ControlLeft),
+        "shift" => Some(Key::ShiftLeft),
+        "alt" => Some(Key::Alt),
+        "meta" | "super" | "win" | "cmd" => Some(Key::MetaLeft),
+        _ => None,
+    };
+    if alias.is_some() {
+        return alias;
+    }
+
+    let variant = match name.as_bytes() {
+        [letter] if letter.is_ascii_alphabetic() => format!("Key{}", name.to_ascii_uppercase()),
+        [digit] if digit.is_ascii_digit() => format!("Num{}", name),
+        _ => name.to_string(),
+    };
+
+    // Unit variants deserialise from their name; `Unknown(u32)` never matches a bare string
+    serde_json::from_value(serde_json::Value::String(variant)).ok()
+}
+
+/// Fold left/right variants of a modifier into one, so `Ctrl` also matches `ControlRight`
+pub fn side_insensitive(key: Key) -> Key {
+    match key {
+        Key::ControlRight => Key::ControlLeft,
+        Key::ShiftRight => Key::ShiftLeft,
+        Key::MetaRight => Key::MetaLeft,
+        other => other,
+    }
+}
+
+impl Hotkey {
+    /// Whether pressing `key` while `pressed` are held completes this chord
+    pub fn matches(&self, pressed: &HashSet<Key>, key: Key) -> bool {
+        side_insensitive(key) == side_insensitive(self.key)
+            && self.modifiers.iter().all(|modifier| {
+                pressed
+                    .iter()
+                    .any(|held| side_insensitive(*held) == side_insensitive(*modifier))
+            })
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut keys = Vec::new();
+        for part in s.split('+') {
+            if part.trim().is_empty() {
+                return Err(anyhow!("Empty key name in '{}'", s));
+            }
+            let key = parse_key_name(part)
+                .ok_or_else(|| anyhow!("Unknown key name '{}'", part.trim()))?;
+            keys.push(key);
+        }
+
+        let key = keys.pop().ok_or_else(|| anyhow!("Empty hotkey"))?;
+        Ok(Hotkey {
+            modifiers: keys,
+            key,
+        })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{:?}+", modifier)?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
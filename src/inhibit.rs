@@ -0,0 +1,170 @@
+//! Keeping a client awake while it has focus.
+//!
+//! Some desktops do not count injected input as activity, so a client could
+//! blank its screen or suspend while the user types into it from the server.
+//! The client holds an idle inhibitor from `FocusGained` until `FocusLost`.
+
+use anyhow::Result;
+use tokio::sync::mpsc;
+
+/// Stops this machine from blanking or suspending for idleness
+pub trait IdleInhibitor: Send {
+    fn inhibit(&mut self) -> Result<()>;
+
+    /// Let the machine go idle again
+    fn release(&mut self);
+}
+
+/// Holds an inhibitor while this client has focus, and releases it when
+/// dropped, e.g. because the connection was lost
+pub struct KeepAwake {
+    inhibitor: Box<dyn IdleInhibitor>,
+    held: bool,
+}
+
+impl KeepAwake {
+    pub fn new(inhibitor: Box<dyn IdleInhibitor>) -> Self {
+        Self {
+            inhibitor,
+            held: false,
+        }
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        if focused == self.held {
+            return;
+        }
+        if !focused {
+            self.inhibitor.release();
+            self.held = false;
+            return;
+        }
+        match self.inhibitor.inhibit() {
+            Ok(()) => self.held = true,
+            Err(e) => println!("Cannot keep this machine awake: {:#}", e),
+        }
+    }
+}
+
+impl Drop for KeepAwake {
+    fn drop(&mut self) {
+        self.set_focused(false);
+    }
+}
+
+/// Run a `KeepAwake` on a thread of its own, since inhibitors block on D-Bus,
+/// and return the channel that tells it whether this client has focus. The
+/// inhibitor comes from `create`, called on that thread; closing the channel
+/// releases it.
+pub fn keep_awake_in_background<F>(create: F) -> mpsc::UnboundedSender<bool>
+where
+    F: FnOnce() -> Option<Box<dyn IdleInhibitor>> + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let mut awake = create().map(KeepAwake::new);
+        while let Some(focused) = rx.blocking_recv() {
+            if let Some(awake) = &mut awake {
+                awake.set_focused(focused);
+            }
+        }
+    });
+    tx
+}
+
+/// The idle inhibitor for this platform, or `None` where injected input already
+/// counts as activity (Windows and macOS) or there is no D-Bus to ask
+pub fn create_inhibitor() -> Option<Box<dyn IdleInhibitor>> {
+    #[cfg(target_os = "linux")]
+    match dbus::DbusInhibitor::new() {
+        Ok(inhibitor) => return Some(Box::new(inhibitor)),
+        Err(e) => println!("Cannot keep this machine awake: {:#}", e),
+    }
+    None
+}
+
+#[cfg(target_os = "linux")]
+mod dbus {
+    use super::IdleInhibitor;
+    use anyhow::{Result, bail};
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedFd;
+
+    const WHO: &str = "AuroraKVM";
+    const WHY: &str = "Receiving input from the AuroraKVM server";
+
+    /// A logind idle inhibitor against suspend, like `systemd-inhibit`, and a
+    /// freedesktop ScreenSaver inhibit against blanking. The bus connections are
+    /// opened once and kept, since a screensaver inhibit also ends with the
+    /// connection that took it.
+    pub struct DbusInhibitor {
+        logind: Option<Proxy<'static>>,
+        screensaver: Option<Proxy<'static>>,
+        /// Held open for as long as the logind inhibitor should last
+        lock: Option<OwnedFd>,
+        cookie: Option<u32>,
+    }
+
+    impl DbusInhibitor {
+        pub fn new() -> Result<Self> {
+            let logind = Connection::system().and_then(|bus| {
+                Proxy::new_owned(
+                    bus,
+                    "org.freedesktop.login1",
+                    "/org/freedesktop/login1",
+                    "org.freedesktop.login1.Manager",
+                )
+            });
+            let screensaver = Connection::session().and_then(|bus| {
+                Proxy::new_owned(
+                    bus,
+                    "org.freedesktop.ScreenSaver",
+                    "/org/freedesktop/ScreenSaver",
+                    "org.freedesktop.ScreenSaver",
+                )
+            });
+            if let (Err(logind), Err(screensaver)) = (&logind, &screensaver) {
+                bail!("logind: {}; ScreenSaver: {}", logind, screensaver);
+            }
+            Ok(Self {
+                logind: logind.ok(),
+                screensaver: screensaver.ok(),
+                lock: None,
+                cookie: None,
+            })
+        }
+    }
+
+    impl IdleInhibitor for DbusInhibitor {
+        fn inhibit(&mut self) -> Result<()> {
+            let lock = self.logind.as_ref().map(|manager| {
+                manager.call::<_, _, OwnedFd>("Inhibit", &("idle", WHO, WHY, "block"))
+            });
+            let cookie = self
+                .screensaver
+                .as_ref()
+                .map(|screensaver| screensaver.call::<_, _, u32>("Inhibit", &(WHO, WHY)));
+
+            match (lock, cookie) {
+                (Some(Err(logind)), Some(Err(screensaver))) => {
+                    bail!("logind: {}; ScreenSaver: {}", logind, screensaver)
+                }
+                (Some(Err(e)), None) | (None, Some(Err(e))) => bail!(e),
+                (lock, cookie) => {
+                    self.lock = lock.and_then(Result::ok);
+                    self.cookie = cookie.and_then(Result::ok);
+                    Ok(())
+                }
+            }
+        }
+
+        fn release(&mut self) {
+            self.lock = None;
+            if let (Some(screensaver), Some(cookie)) = (&self.screensaver, self.cookie.take())
+                && let Err(e) = screensaver.call::<_, _, ()>("UnInhibit", &cookie)
+            {
+                println!("Failed to release the screensaver inhibit: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use super::evdev_map::{self, BUTTON_CODES, EvdevEncoder, EvdevTranslator, RawEvent};
+use super::{CaptureHandler, Disposition, InputCapture, InputInjector};
+use crate::event::KvmEvent;
+use anyhow::{Context, Result, bail};
+use evdev::uinput::VirtualDevice;
+use evdev::{
+    AbsInfo, AbsoluteAxisCode, AttributeSet, Device, InputEvent, KeyCode, RelativeAxisCode,
+    UinputAbsSetup,
+};
+use std::os::fd::AsRawFd;
+
+/// Prefix of the uinput devices we create, so capture never reads them back
+const VIRTUAL_DEVICE_PREFIX: &str = "AuroraKVM";
+
+/// Reads keyboards and mice straight from `/dev/input/event*`.
+///
+/// Works under Wayland, where rdev sees nothing. Devices are grabbed exclusively
+/// while the router swallows events (focus is on a client) and released when it
+/// passes them again. Needs read access to the device nodes, usually via the
+/// `input` group. Devices plugged in after startup are not picked up.
+pub struct EvdevCapture;
+
+impl InputCapture for EvdevCapture {
+    fn run(self: Box<Self>, mut handler: CaptureHandler) -> Result<()> {
+        let mut devices = open_input_devices()?;
+
+        let mut translator = EvdevTranslator::new();
+        let (x, y, width, height) = desktop_bounds();
+        translator.set_cursor(
+            x as f64 + width as f64 / 2.0,
+            y as f64 + height as f64 / 2.0,
+        );
+
+        let mut grabbed = false;
+
+        while !devices.is_empty() {
+            let mut fds: Vec<libc::pollfd> = devices
+                .iter()
+                .map(|device| libc::pollfd {
+                    fd: device.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                })
+                .collect();
+
+            // SAFETY: `fds` is a valid, exclusively borrowed array of `fds.len()` pollfds
+            let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+            if ready < 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error).context("poll on input devices failed");
+            }
+
+            let mut want_grab = grabbed;
+            let mut unplugged = Vec::new();
+
+            for (index, (device, fd)) in devices.iter_mut().zip(&fds).enumerate() {
+                if fd.revents & (libc::POLLERR | libc::POLLHUP) != 0 {
+                    unplugged.push(index);
+                    continue;
+                }
+                if fd.revents & libc::POLLIN == 0 {
+                    continue;
+                }
+
+                let raw_events: Vec<RawEvent> = match device.fetch_events() {
+                    Ok(events) => events
+                        .map(|e| RawEvent::new(e.event_type().0, e.code(), e.value()))
+                        .collect(),
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+                    Err(_) => {
+                        unplugged.push(index);
+                        continue;
+                    }
+                };
+
+                for raw in raw_events {
+                    for event in translator.feed(raw) {
+                        want_grab = handler(event) == Disposition::Swallow;
+                    }
+                }
+            }
+
+            for index in unplugged.into_iter().rev() {
+                let device = devices.remove(index);
+                println!(
+                    "Input device removed: {}",
+                    device.name().unwrap_or("unnamed")
+                );
+            }
+
+            if want_grab != grabbed {
+                for device in &mut devices {
+                    let result = if want_grab {
+                        device.grab()
+                    } else {
+                        device.ungrab()
+                    };
+                    if let Err(e) = result {
+                        println!(
+                            "Failed to change grab on {}: {}",
+                            device.name().unwrap_or("unnamed"),
+                            e
+                        );
+                    }
+                }
+                grabbed = want_grab;
+            }
+        }
+
+        bail!("All input devices were removed")
+    }
+}
+
+/// Open every keyboard and mouse we can read, in non-blocking mode
+fn open_input_devices() -> Result<Vec<Device>> {
+    let mut devices = Vec::new();
+
+    for (path, device) in evdev::enumerate() {
+        let name = device.name().unwrap_or("");
+        if name.starts_with(VIRTUAL_DEVICE_PREFIX) {
+            continue;
+        }
+
+        let is_keyboard = device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(KeyCode::KEY_A));
+        let is_mouse = device
+            .supported_relative_axes()
+            .is_some_and(|axes| axes.contains(RelativeAxisCode::REL_X));
+        if !is_keyboard && !is_mouse {
+            continue;
+        }
+
+        device
+            .set_nonblocking(true)
+            .with_context(|| format!("Failed to configure {}", path.display()))?;
+        println!("Capturing input from {} ({})", name, path.display());
+        devices.push(device);
+    }
+
+    if devices.is_empty() {
+        bail!("No readable keyboards or mice in /dev/input. Is this user in the `input` group?");
+    }
+    Ok(devices)
+}
+
+/// Replays events through two uinput devices: a keyboard, and an absolute pointer
+/// spanning the whole desktop.
+///
+/// Needs write access to `/dev/uinput`.
+pub struct EvdevInjector {
+    keyboard: VirtualDevice,
+    pointer: VirtualDevice,
+    encoder: EvdevEncoder,
+    /// Last position we moved the pointer to. uinput cannot read the cursor
+    /// back, so motion from the client's own mouse is not included.
+    position: Option<(f64, f64)>,
+}
+
+impl EvdevInjector {
+    pub fn new() -> Result<Self> {
+        let keys: AttributeSet<KeyCode> = evdev_map::all_key_codes().map(KeyCode::new).collect();
+        let keyboard = VirtualDevice::builder()
+            .context("Failed to open /dev/uinput")?
+            .name("AuroraKVM Keyboard")
+            .with_keys(&keys)?
+            .build()
+            .context("Failed to create uinput keyboard")?;
+
+        let buttons: AttributeSet<KeyCode> = BUTTON_CODES
+            .iter()
+            .map(|(_, code)| KeyCode::new(*code))
+            .collect();
+        let wheels: AttributeSet<RelativeAxisCode> = [
+            RelativeAxisCode::REL_WHEEL,
+            RelativeAxisCode::REL_HWHEEL,
+            RelativeAxisCode::REL_WHEEL_HI_RES,
+            RelativeAxisCode::REL_HWHEEL_HI_RES,
+        ]
+        .into_iter()
+        .collect();
+        let (_, _, width, height) = desktop_bounds();
+        let axis = |code, size: u32| {
+            UinputAbsSetup::new(code, AbsInfo::new(0, 0, size as i32 - 1, 0, 0, 1))
+        };
+
+        let pointer = VirtualDevice::builder()
+            .context("Failed to open /dev/uinput")?
+            .name("AuroraKVM Pointer")
+            .with_keys(&buttons)?
+            .with_relative_axes(&wheels)?
+            .with_absolute_axis(&axis(AbsoluteAxisCode::ABS_X, width))?
+            .with_absolute_axis(&axis(AbsoluteAxisCode::ABS_Y, height))?
+            .build()
+            .context("Failed to create uinput pointer")?;
+
+        Ok(Self {
+            keyboard,
+            pointer,
+            encoder: EvdevEncoder::new(),
+            position: None,
+        })
+    }
+}
+
+impl InputInjector for EvdevInjector {
+    fn inject(&mut self, event: &KvmEvent) -> Result<()> {
+        if let KvmEvent::Text(_) = event {
+            bail!("Text input is not supported by the evdev backend; use keyboard_mode \"keys\"");
+        }
+        if let KvmEvent::MouseMove { x, y } = *event {
+            self.position = Some((x, y));
+        }
+
+        let raw = self.encoder.encode(event);
+        if raw.is_empty() {
+            return Ok(());
+        }
+
+        let events: Vec<InputEvent> = raw
+            .iter()
+            .map(|r| InputEvent::new(r.kind, r.code, r.value))
+            .collect();
+        let device = match event {
+            KvmEvent::KeyPress { .. } | KvmEvent::KeyRelease { .. } => &mut self.keyboard,
+            _ => &mut self.pointer,
+        };
+        device
+            .emit(&events)
+            .context("Failed to write to uinput device")
+    }
+
+    fn cursor_position(&mut self) -> Option<(f64, f64)> {
+        self.position
+    }
+}
+
+/// Bounding box of all displays as (x, y, width, height)
+fn desktop_bounds() -> (i32, i32, u32, u32) {
+    let displays = display_info::DisplayInfo::all().unwrap_or_default();
+    if displays.is_empty() {
+        return (0, 0, 1920, 1080);
+    }
+
+    let min_x = displays.iter().map(|d| d.x).min().unwrap_or(0);
+    let min_y = displays.iter().map(|d| d.y).min().unwrap_or(0);
+    let max_x = displays
+        .iter()
+        .map(|d| d.x + d.width as i32)
+        .max()
+        .unwrap_or(1920);
+    let max_y = displays
+        .iter()
+        .map(|d| d.y + d.height as i32)
+        .max()
+        .unwrap_or(1080);
+
+    (min_x, min_y, (max_x - min_x) as u32, (max_y - min_y) as u32)
+}
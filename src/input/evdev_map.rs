@@ -0,0 +1,274 @@
+//! Translation between Linux evdev events and `KvmEvent`s.
+//!
+//! Kept free of any device I/O so it builds on every platform and can be tested
+//! with hand-written event sequences. Codes are the values from
+//! `linux/input-event-codes.h`.
+
+use super::scroll::{HI_RES_PER_NOTCH, ScrollAccumulator};
+use crate::event::{Button, Key, KvmEvent};
+use crate::scancode::{self, NO_USAGE, PHYSICAL_KEYS};
+
+pub const EV_SYN: u16 = 0x00;
+pub const EV_KEY: u16 = 0x01;
+pub const EV_REL: u16 = 0x02;
+pub const EV_ABS: u16 = 0x03;
+
+pub const SYN_REPORT: u16 = 0;
+pub const SYN_DROPPED: u16 = 3;
+
+pub const REL_X: u16 = 0x00;
+pub const REL_Y: u16 = 0x01;
+pub const REL_HWHEEL: u16 = 0x06;
+pub const REL_WHEEL: u16 = 0x08;
+pub const REL_WHEEL_HI_RES: u16 = 0x0b;
+pub const REL_HWHEEL_HI_RES: u16 = 0x0c;
+
+pub const ABS_X: u16 = 0x00;
+pub const ABS_Y: u16 = 0x01;
+
+/// Codes in this range are buttons (mouse, joystick, touch); the rest are keys
+pub const BUTTON_RANGE: std::ops::Range<u16> = 0x100..0x160;
+
+/// A single `input_event`, minus the timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawEvent {
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl RawEvent {
+    pub fn new(kind: u16, code: u16, value: i32) -> Self {
+        Self { kind, code, value }
+    }
+}
+
+/// `KEY_FN`; it has no HID keyboard usage, so it is mapped by name
+const KEY_FN: u16 = 0x1d0;
+
+/// evdev code for every mouse button we forward. Mice report the thumb buttons
+/// as either `BTN_SIDE`/`BTN_EXTRA` or `BTN_BACK`/`BTN_FORWARD`; the first entry
+/// for a button is the one we send.
+pub const BUTTON_CODES: &[(Button, u16)] = &[
+    (Button::Left, 0x110),
+    (Button::Right, 0x111),
+    (Button::Middle, 0x112),
+    (Button::X1, 0x113),
+    (Button::X2, 0x114),
+    (Button::X2, 0x115),
+    (Button::X1, 0x116),
+];
+
+/// evdev code for a key event, preferring the physical position from `usage`
+pub fn key_code(key: Key, usage: u32) -> Option<u16> {
+    scancode::evdev_from_usage(usage).or(match key {
+        Key::Function => Some(KEY_FN),
+        key => scancode::evdev_from_usage(scancode::usage_for_key(key)),
+    })
+}
+
+/// Every key code a uinput keyboard needs to be able to send
+pub fn all_key_codes() -> impl Iterator<Item = u16> {
+    PHYSICAL_KEYS
+        .iter()
+        .map(|k| k.evdev)
+        .chain(std::iter::once(KEY_FN))
+}
+
+/// Logical key and HID usage for an evdev code outside `BUTTON_RANGE`.
+/// Codes without a named key become `Key::Unknown(code)`.
+pub fn key_from_code(code: u16) -> (Key, u32) {
+    if code == KEY_FN {
+        return (Key::Function, NO_USAGE);
+    }
+
+    let usage = scancode::usage_from_evdev(code).unwrap_or(NO_USAGE);
+    let key = scancode::key_for_usage(usage).unwrap_or(Key::Unknown(code as u32));
+    (key, usage)
+}
+
+pub fn button_code(button: Button) -> Option<u16> {
+    BUTTON_CODES
+        .iter()
+        .find(|(b, _)| *b == button)
+        .map(|(_, code)| *code)
+}
+
+pub fn button_from_code(code: u16) -> Option<Button> {
+    BUTTON_CODES
+        .iter()
+        .find(|(_, c)| *c == code)
+        .map(|(button, _)| *button)
+}
+
+/// Turns a stream of evdev events from keyboards and mice into `KvmEvent`s.
+///
+/// Keys and buttons are reported immediately. Pointer motion and wheel deltas are
+/// collected until the `SYN_REPORT` that closes the frame, then reported as one
+/// `MouseMove` (an absolute position accumulated from relative motion) and one
+/// `Wheel`. Wheels with high-resolution axes send those alongside the classic
+/// ones, so an axis's hi-res value wins whenever the frame has one. Absolute
+/// axes, e.g. touchpads in absolute mode, are ignored.
+#[derive(Debug, Default)]
+pub struct EvdevTranslator {
+    cursor: (f64, f64),
+    motion: (i32, i32),
+    wheel: (i32, i32),
+    wheel_hi_res: (i32, i32),
+}
+
+impl EvdevTranslator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cursor(&self) -> (f64, f64) {
+        self.cursor
+    }
+
+    /// Move the tracked cursor, e.g. to the centre of the screen on startup
+    pub fn set_cursor(&mut self, x: f64, y: f64) {
+        self.cursor = (x, y);
+    }
+
+    pub fn feed(&mut self, raw: RawEvent) -> Vec<KvmEvent> {
+        match (raw.kind, raw.code) {
+            (EV_KEY, code) if !BUTTON_RANGE.contains(&code) => {
+                let (key, usage) = key_from_code(code);
+                match raw.value {
+                    0 => vec![KvmEvent::KeyRelease { key, usage }],
+                    // 2 is autorepeat, which the OS reports as repeated presses
+                    _ => vec![KvmEvent::KeyPress { key, usage }],
+                }
+            }
+            (EV_KEY, code) => match (button_from_code(code), raw.value) {
+                (Some(button), 0) => vec![KvmEvent::ButtonRelease(button)],
+                (Some(button), 1) => vec![KvmEvent::ButtonPress(button)],
+                _ => vec![],
+            },
+            (EV_REL, REL_X) => {
+                self.motion.0 += raw.value;
+                vec![]
+            }
+            (EV_REL, REL_Y) => {
+                self.motion.1 += raw.value;
+                vec![]
+            }
+            (EV_REL, REL_HWHEEL) => {
+                self.wheel.0 += raw.value;
+                vec![]
+            }
+            (EV_REL, REL_WHEEL) => {
+                self.wheel.1 += raw.value;
+                vec![]
+            }
+            (EV_REL, REL_HWHEEL_HI_RES) => {
+                self.wheel_hi_res.0 += raw.value;
+                vec![]
+            }
+            (EV_REL, REL_WHEEL_HI_RES) => {
+                self.wheel_hi_res.1 += raw.value;
+                vec![]
+            }
+            (EV_SYN, SYN_REPORT) => self.flush(),
+            (EV_SYN, SYN_DROPPED) => {
+                // The kernel dropped part of this frame; discard what we have of it
+                self.motion = (0, 0);
+                self.wheel = (0, 0);
+                self.wheel_hi_res = (0, 0);
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn flush(&mut self) -> Vec<KvmEvent> {
+        let mut events = Vec::new();
+
+        if self.motion != (0, 0) {
+            self.cursor.0 += self.motion.0 as f64;
+            self.cursor.1 += self.motion.1 as f64;
+            self.motion = (0, 0);
+            events.push(KvmEvent::MouseMove {
+                x: self.cursor.0,
+                y: self.cursor.1,
+            });
+        }
+
+        let axis = |notches: i32, hi_res: i32| {
+            if hi_res != 0 {
+                hi_res as f64 / HI_RES_PER_NOTCH
+            } else {
+                notches as f64
+            }
+        };
+        let delta_x = axis(self.wheel.0, self.wheel_hi_res.0);
+        let delta_y = axis(self.wheel.1, self.wheel_hi_res.1);
+        self.wheel = (0, 0);
+        self.wheel_hi_res = (0, 0);
+        if delta_x != 0.0 || delta_y != 0.0 {
+            events.push(KvmEvent::Wheel { delta_x, delta_y });
+        }
+
+        events
+    }
+}
+
+/// Turns `KvmEvent`s into the evdev events that replay them on uinput devices.
+///
+/// Pointer positions are written to `ABS_X`/`ABS_Y`. Wheels are written to both
+/// the hi-res axes and, once whole notches add up, the classic ones, the way
+/// the kernel reports a hi-res mouse.
+#[derive(Debug, Default)]
+pub struct EvdevEncoder {
+    scroll: ScrollAccumulator,
+}
+
+impl EvdevEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events for `event`, without the closing `SYN_REPORT`
+    pub fn encode(&mut self, event: &KvmEvent) -> Vec<RawEvent> {
+        match *event {
+            KvmEvent::KeyPress { key, usage } => key_code(key, usage)
+                .map(|code| vec![RawEvent::new(EV_KEY, code, 1)])
+                .unwrap_or_default(),
+            KvmEvent::KeyRelease { key, usage } => key_code(key, usage)
+                .map(|code| vec![RawEvent::new(EV_KEY, code, 0)])
+                .unwrap_or_default(),
+            KvmEvent::ButtonPress(button) => button_code(button)
+                .map(|code| vec![RawEvent::new(EV_KEY, code, 1)])
+                .unwrap_or_default(),
+            KvmEvent::ButtonRelease(button) => button_code(button)
+                .map(|code| vec![RawEvent::new(EV_KEY, code, 0)])
+                .unwrap_or_default(),
+            KvmEvent::MouseMove { x, y } => vec![
+                RawEvent::new(EV_ABS, ABS_X, x.round() as i32),
+                RawEvent::new(EV_ABS, ABS_Y, y.round() as i32),
+            ],
+            KvmEvent::Wheel { delta_x, delta_y } => {
+                let (notches_x, notches_y) = self.scroll.add(delta_x, delta_y);
+                let mut events = Vec::new();
+                if delta_x != 0.0 {
+                    let hi_res = (delta_x * HI_RES_PER_NOTCH).round() as i32;
+                    events.push(RawEvent::new(EV_REL, REL_HWHEEL_HI_RES, hi_res));
+                }
+                if notches_x != 0 {
+                    events.push(RawEvent::new(EV_REL, REL_HWHEEL, notches_x as i32));
+                }
+                if delta_y != 0.0 {
+                    let hi_res = (delta_y * HI_RES_PER_NOTCH).round() as i32;
+                    events.push(RawEvent::new(EV_REL, REL_WHEEL_HI_RES, hi_res));
+                }
+                if notches_y != 0 {
+                    events.push(RawEvent::new(EV_REL, REL_WHEEL, notches_y as i32));
+                }
+                events
+            }
+            // Text needs a layout to turn into key codes; the injector rejects it
+            KvmEvent::Text(_) => vec![],
+        }
+    }
+}
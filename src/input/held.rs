@@ -0,0 +1,50 @@
+//! Keys and buttons held down on a client.
+//!
+//! When focus leaves a client while something is held, the release goes to the
+//! next machine instead, so the client releases everything itself on `FocusLost`.
+
+use crate::event::{Button, Key, KvmEvent};
+use std::collections::{HashMap, HashSet};
+
+/// Keys and buttons injected as pressed and not released yet
+#[derive(Debug, Default)]
+pub struct HeldInput {
+    /// Held keys with the usage they were pressed with
+    keys: HashMap<Key, u32>,
+    buttons: HashSet<Button>,
+}
+
+impl HeldInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note an event that was just injected
+    pub fn track(&mut self, event: &KvmEvent) {
+        match *event {
+            KvmEvent::KeyPress { key, usage } => {
+                self.keys.insert(key, usage);
+            }
+            KvmEvent::KeyRelease { key, .. } => {
+                self.keys.remove(&key);
+            }
+            KvmEvent::ButtonPress(button) => {
+                self.buttons.insert(button);
+            }
+            KvmEvent::ButtonRelease(button) => {
+                self.buttons.remove(&button);
+            }
+            _ => {}
+        }
+    }
+
+    /// Events that release everything still held, which is then forgotten
+    pub fn release_all(&mut self) -> Vec<KvmEvent> {
+        let buttons = self.buttons.drain().map(KvmEvent::ButtonRelease);
+        let keys = self
+            .keys
+            .drain()
+            .map(|(key, usage)| KvmEvent::KeyRelease { key, usage });
+        buttons.chain(keys).collect()
+    }
+}
@@ -0,0 +1,95 @@
+//! Scripted capture and recording injection, for driving the router in tests.
+
+use super::{CaptureHandler, CursorWarp, Disposition, InputCapture, InputInjector};
+use crate::event::KvmEvent;
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+
+/// Replays a fixed list of events and records what the handler decided for each
+pub struct MockCapture {
+    events: Vec<KvmEvent>,
+    dispositions: Arc<Mutex<Vec<Disposition>>>,
+}
+
+impl MockCapture {
+    pub fn new(events: Vec<KvmEvent>) -> Self {
+        Self {
+            events,
+            dispositions: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Handle to the recorded dispositions, readable after `run` returns
+    pub fn dispositions(&self) -> Arc<Mutex<Vec<Disposition>>> {
+        self.dispositions.clone()
+    }
+}
+
+impl InputCapture for MockCapture {
+    fn run(self: Box<Self>, mut handler: CaptureHandler) -> Result<()> {
+        for event in self.events {
+            let disposition = handler(event);
+            self.dispositions.lock().unwrap().push(disposition);
+        }
+        Ok(())
+    }
+}
+
+/// Records every injected event instead of touching the OS
+#[derive(Default, Clone)]
+pub struct RecordingInjector {
+    events: Arc<Mutex<Vec<KvmEvent>>>,
+}
+
+impl RecordingInjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<KvmEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl InputInjector for RecordingInjector {
+    fn inject(&mut self, event: &KvmEvent) -> Result<()> {
+        self.events.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+
+    /// The last position injected
+    fn cursor_position(&mut self) -> Option<(f64, f64)> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find_map(|event| match *event {
+                KvmEvent::MouseMove { x, y } => Some((x, y)),
+                _ => None,
+            })
+    }
+}
+
+/// Records cursor warps instead of moving the cursor
+#[derive(Default, Clone)]
+pub struct RecordingWarp {
+    warps: Arc<Mutex<Vec<(f64, f64)>>>,
+}
+
+impl RecordingWarp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn warps(&self) -> Vec<(f64, f64)> {
+        self.warps.lock().unwrap().clone()
+    }
+}
+
+impl CursorWarp for RecordingWarp {
+    fn warp(&mut self, x: f64, y: f64) -> Result<()> {
+        self.warps.lock().unwrap().push((x, y));
+        Ok(())
+    }
+}
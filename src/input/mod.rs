@@ -0,0 +1,110 @@
+//! Input backends: capturing local input on the server and injecting it on clients.
+//!
+//! The rest of the crate only sees `KvmEvent`s, so backends can be swapped without
+//! touching the routing logic in `router`.
+
+use crate::event::KvmEvent;
+use anyhow::Result;
+
+#[cfg(target_os = "linux")]
+pub mod evdev_backend;
+pub mod evdev_map;
+pub mod held;
+pub mod mock;
+pub mod pointer;
+pub mod rdev_backend;
+pub mod remap;
+pub mod router;
+pub mod scroll;
+pub mod text;
+pub mod unicode;
+
+/// What the capture backend should do with an event after routing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposition {
+    /// Let the local OS see the event
+    Pass,
+    /// Hide the event from the local OS (it was forwarded or consumed)
+    Swallow,
+}
+
+/// Called by a capture backend for every input event
+pub type CaptureHandler = Box<dyn FnMut(KvmEvent) -> Disposition + Send>;
+
+/// Source of local input on the server
+pub trait InputCapture: Send {
+    /// Run the capture loop, calling `handler` for every event.
+    ///
+    /// Blocks until capture stops, so it is normally run on a dedicated thread.
+    fn run(self: Box<Self>, handler: CaptureHandler) -> Result<()>;
+}
+
+/// Moves the local cursor. While a client is focused the router parks the cursor
+/// in the middle of the screen with it, so the OS never clamps motion at an edge.
+pub trait CursorWarp: Send {
+    fn warp(&mut self, x: f64, y: f64) -> Result<()>;
+}
+
+/// Sink that replays received events on a client
+pub trait InputInjector: Send {
+    fn inject(&mut self, event: &KvmEvent) -> Result<()>;
+
+    /// Where the cursor is on this machine, or `None` if the backend cannot tell
+    fn cursor_position(&mut self) -> Option<(f64, f64)> {
+        None
+    }
+}
+
+/// Which OS interface to capture and inject input with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InputBackend {
+    /// rdev: X11 on Linux, native hooks on Windows and macOS
+    Rdev,
+    /// evdev and uinput (Linux only, works under Wayland)
+    Evdev,
+}
+
+impl InputBackend {
+    /// evdev on Wayland sessions, rdev everywhere else
+    pub fn detect() -> Self {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+            || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland");
+
+        if cfg!(target_os = "linux") && wayland {
+            InputBackend::Evdev
+        } else {
+            InputBackend::Rdev
+        }
+    }
+}
+
+pub fn create_capture(backend: InputBackend) -> Result<Box<dyn InputCapture>> {
+    match backend {
+        InputBackend::Rdev => Ok(Box::new(rdev_backend::RdevCapture)),
+        #[cfg(target_os = "linux")]
+        InputBackend::Evdev => Ok(Box::new(evdev_backend::EvdevCapture)),
+        #[cfg(not(target_os = "linux"))]
+        InputBackend::Evdev => anyhow::bail!("The evdev backend is only available on Linux"),
+    }
+}
+
+/// A cursor warp for backends that report absolute positions the OS clamps to
+/// the screen. `None` for backends that report raw relative motion (evdev, and
+/// rdev on Linux, which reads evdev too).
+pub fn create_cursor_warp(backend: InputBackend) -> Option<Box<dyn CursorWarp>> {
+    match backend {
+        #[cfg(not(target_os = "linux"))]
+        InputBackend::Rdev => Some(Box::new(rdev_backend::RdevWarp)),
+        _ => None,
+    }
+}
+
+pub fn create_injector(backend: InputBackend) -> Result<Box<dyn InputInjector>> {
+    match backend {
+        InputBackend::Rdev => Ok(Box::new(rdev_backend::RdevInjector::default())),
+        #[cfg(target_os = "linux")]
+        InputBackend::Evdev => Ok(Box::new(evdev_backend::EvdevInjector::new()?)),
+        #[cfg(not(target_os = "linux"))]
+        InputBackend::Evdev => anyhow::bail!("The evdev backend is only available on Linux"),
+    }
+}
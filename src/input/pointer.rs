@@ -0,0 +1,66 @@
+//! Pointer speed for clients.
+//!
+//! Motion forwarded to a client is multiplied by its `pointer_speed`, by its
+//! acceleration curve at the current mouse speed, and by its display scale
+//! factor relative to the server's. The last one makes the cursor cover the same
+//! physical distance on a HiDPI panel as on a regular one.
+
+use crate::config::ClientConfig;
+use std::time::{Duration, Instant};
+
+/// Moves closer together than this are measured as this far apart, so events
+/// delivered in a burst do not read as very fast motion
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Moves further apart than this start a new motion from rest
+const MAX_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Multiplier for motion sent to `client` while the mouse moves at `speed` pixels
+/// per second. `scale` is the client's display scale factor over the server's.
+pub fn gain(client: &ClientConfig, scale: f64, speed: f64) -> f64 {
+    client.pointer_speed * scale * acceleration(&client.pointer_acceleration, speed)
+}
+
+/// Multiplier from a curve of `[speed, multiplier]` points, interpolated between
+/// them and flat beyond either end. 1.0 for an empty curve.
+pub fn acceleration(curve: &[[f64; 2]], speed: f64) -> f64 {
+    let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+        return 1.0;
+    };
+    if speed <= first[0] {
+        return first[1];
+    }
+
+    for pair in curve.windows(2) {
+        let ([low_speed, low], [high_speed, high]) = (pair[0], pair[1]);
+        if speed <= high_speed {
+            if high_speed <= low_speed {
+                return high;
+            }
+            return low + (high - low) * (speed - low_speed) / (high_speed - low_speed);
+        }
+    }
+    last[1]
+}
+
+/// Measures how fast the mouse moves from the time between motion events
+#[derive(Debug, Default)]
+pub struct SpeedMeter {
+    last: Option<Instant>,
+}
+
+impl SpeedMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Speed in pixels per second of a move of `distance` pixels at `now`
+    pub fn measure(&mut self, distance: f64, now: Instant) -> f64 {
+        let interval = self
+            .last
+            .replace(now)
+            .map_or(MAX_INTERVAL, |last| now.saturating_duration_since(last))
+            .clamp(MIN_INTERVAL, MAX_INTERVAL);
+        distance / interval.as_secs_f64()
+    }
+}
@@ -0,0 +1,259 @@
+#[cfg(not(windows))]
+use super::scroll::ScrollAccumulator;
+use super::unicode::UnicodeTyper;
+use super::{CaptureHandler, Disposition, InputCapture, InputInjector};
+use crate::event::KvmEvent;
+use crate::scancode;
+use anyhow::{Result, anyhow};
+use rdev::{Event, EventType, grab, simulate};
+use std::sync::Mutex;
+
+/// Captures input with `rdev::grab` (X11 on Linux, low-level hooks on Windows/macOS)
+pub struct RdevCapture;
+
+impl InputCapture for RdevCapture {
+    fn run(self: Box<Self>, handler: CaptureHandler) -> Result<()> {
+        // rdev wants an `Fn` callback; the handler needs `&mut self`
+        let handler = Mutex::new(handler);
+
+        grab(move |event: Event| -> Option<Event> {
+            let mut handler = handler.lock().unwrap();
+            match handler(KvmEvent::from(event.event_type)) {
+                Disposition::Pass => Some(event),
+                Disposition::Swallow => None,
+            }
+        })
+        .map_err(|e| anyhow!("rdev grab failed: {:?}", e))
+    }
+}
+
+/// Moves the cursor with `rdev::simulate`. The capture hook sees the move like
+/// any other, at exactly the warp target.
+#[cfg(not(target_os = "linux"))]
+pub struct RdevWarp;
+
+#[cfg(not(target_os = "linux"))]
+impl super::CursorWarp for RdevWarp {
+    fn warp(&mut self, x: f64, y: f64) -> Result<()> {
+        simulate(&EventType::MouseMove { x, y })
+            .map_err(|e| anyhow!("Failed to move cursor: {:?}", e))
+    }
+}
+
+/// Injects input with `rdev::simulate` (XTest on Linux, SendInput on Windows).
+///
+/// Keys with a known HID usage are sent by physical position: as X11 keycodes on
+/// Linux and as scancodes on Windows. Other keys, and all keys on macOS, are sent
+/// by logical key. `KvmEvent::Text` is typed through the platform's Unicode input.
+/// Windows scrolls in 1/120 notch steps; elsewhere rdev only scrolls whole notches.
+#[derive(Default)]
+pub struct RdevInjector {
+    /// Created on the first text event, so clients in key mode never need it
+    text: Option<UnicodeTyper>,
+    /// Fractional notches not scrolled yet
+    #[cfg(not(windows))]
+    scroll: ScrollAccumulator,
+}
+
+impl InputInjector for RdevInjector {
+    fn inject(&mut self, event: &KvmEvent) -> Result<()> {
+        if let KvmEvent::Text(text) = event {
+            let typer = match &mut self.text {
+                Some(typer) => typer,
+                None => self.text.insert(UnicodeTyper::new()?),
+            };
+            return typer.type_text(text);
+        }
+        if let KvmEvent::Wheel { delta_x, delta_y } = *event {
+            return self.inject_wheel(delta_x, delta_y);
+        }
+
+        let physical = match *event {
+            KvmEvent::KeyPress { usage, .. } => inject_physical(usage, true),
+            KvmEvent::KeyRelease { usage, .. } => inject_physical(usage, false),
+            _ => None,
+        };
+        if let Some(result) = physical {
+            return result;
+        }
+
+        let event_type = EventType::try_from(event.clone())?;
+        simulate(&event_type).map_err(|e| anyhow!("Failed to simulate event: {:?}", e))
+    }
+
+    fn cursor_position(&mut self) -> Option<(f64, f64)> {
+        cursor_position()
+    }
+}
+
+impl RdevInjector {
+    #[cfg(not(windows))]
+    fn inject_wheel(&mut self, delta_x: f64, delta_y: f64) -> Result<()> {
+        let (notches_x, notches_y) = self.scroll.add(delta_x, delta_y);
+        if (notches_x, notches_y) == (0, 0) {
+            return Ok(());
+        }
+
+        let event_type = EventType::try_from(KvmEvent::Wheel {
+            delta_x: notches_x as f64,
+            delta_y: notches_y as f64,
+        })?;
+        simulate(&event_type).map_err(|e| anyhow!("Failed to simulate event: {:?}", e))
+    }
+
+    #[cfg(windows)]
+    fn inject_wheel(&mut self, delta_x: f64, delta_y: f64) -> Result<()> {
+        use super::scroll::HI_RES_PER_NOTCH;
+        use winapi::um::winuser::{
+            INPUT, INPUT_MOUSE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_WHEEL, MOUSEINPUT, SendInput,
+        };
+
+        let mut inputs: Vec<INPUT> = Vec::new();
+        for (delta, flags) in [(delta_y, MOUSEEVENTF_WHEEL), (delta_x, MOUSEEVENTF_HWHEEL)] {
+            let amount = (delta * HI_RES_PER_NOTCH).round() as i32;
+            if amount == 0 {
+                continue;
+            }
+            // SAFETY: INPUT is plain data, and we only write the mouse variant of its union
+            let mut input: INPUT = unsafe { std::mem::zeroed() };
+            input.type_ = INPUT_MOUSE;
+            unsafe {
+                *input.u.mi_mut() = MOUSEINPUT {
+                    dx: 0,
+                    dy: 0,
+                    mouseData: amount as u32,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                };
+            }
+            inputs.push(input);
+        }
+        if inputs.is_empty() {
+            return Ok(());
+        }
+
+        // SAFETY: `inputs` is a valid array of INPUTs and the size matches their type
+        let sent = unsafe {
+            SendInput(
+                inputs.len() as u32,
+                inputs.as_mut_ptr(),
+                std::mem::size_of::<INPUT>() as i32,
+            )
+        };
+        if sent as usize != inputs.len() {
+            return Err(anyhow!("SendInput rejected wheel input"));
+        }
+        Ok(())
+    }
+}
+
+/// Press or release the key at a HID usage, or `None` if this platform cannot
+/// address it by position
+#[cfg(target_os = "linux")]
+fn inject_physical(usage: u32, pressed: bool) -> Option<Result<()>> {
+    // rdev passes unknown keys straight through as X11 keycodes
+    let key = rdev::Key::Unknown(scancode::x11_from_usage(usage)?);
+    let event_type = if pressed {
+        EventType::KeyPress(key)
+    } else {
+        EventType::KeyRelease(key)
+    };
+    Some(simulate(&event_type).map_err(|e| anyhow!("Failed to simulate event: {:?}", e)))
+}
+
+#[cfg(windows)]
+fn inject_physical(usage: u32, pressed: bool) -> Option<Result<()>> {
+    use winapi::um::winuser::{
+        INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP,
+        KEYEVENTF_SCANCODE, SendInput,
+    };
+
+    // Pause is a multi-byte sequence that SendInput cannot express as one scancode
+    if scancode::key_for_usage(usage) == Some(crate::event::Key::Pause) {
+        return None;
+    }
+
+    let scancode = scancode::scancode_from_usage(usage)?;
+    let mut flags = KEYEVENTF_SCANCODE;
+    if scancode & 0xFF00 == 0xE000 {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    if !pressed {
+        flags |= KEYEVENTF_KEYUP;
+    }
+
+    // SAFETY: INPUT is plain data, and we only write the keyboard variant of its union
+    let mut input: INPUT = unsafe { std::mem::zeroed() };
+    input.type_ = INPUT_KEYBOARD;
+    unsafe {
+        *input.u.ki_mut() = KEYBDINPUT {
+            wVk: 0,
+            wScan: scancode & 0xFF,
+            dwFlags: flags,
+            time: 0,
+            dwExtraInfo: 0,
+        };
+    }
+
+    // SAFETY: `input` is a valid INPUT and the size matches its type
+    let sent = unsafe { SendInput(1, &mut input, std::mem::size_of::<INPUT>() as i32) };
+    Some(if sent == 1 {
+        Ok(())
+    } else {
+        Err(anyhow!("SendInput rejected scancode {:#06x}", scancode))
+    })
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn inject_physical(_usage: u32, _pressed: bool) -> Option<Result<()>> {
+    None
+}
+
+/// The pointer position on the default X screen
+#[cfg(target_os = "linux")]
+fn cursor_position() -> Option<(f64, f64)> {
+    use x11::xlib;
+
+    // SAFETY: plain Xlib calls on a display we open and close here; every out
+    // parameter points at a local
+    unsafe {
+        let display = xlib::XOpenDisplay(std::ptr::null());
+        if display.is_null() {
+            return None;
+        }
+        let root = xlib::XDefaultRootWindow(display);
+        let (mut root_return, mut child) = (0, 0);
+        let (mut x, mut y, mut win_x, mut win_y) = (0, 0, 0, 0);
+        let mut mask = 0;
+        let found = xlib::XQueryPointer(
+            display,
+            root,
+            &mut root_return,
+            &mut child,
+            &mut x,
+            &mut y,
+            &mut win_x,
+            &mut win_y,
+            &mut mask,
+        );
+        xlib::XCloseDisplay(display);
+        (found != 0).then_some((x as f64, y as f64))
+    }
+}
+
+#[cfg(windows)]
+fn cursor_position() -> Option<(f64, f64)> {
+    use winapi::shared::windef::POINT;
+    use winapi::um::winuser::GetCursorPos;
+
+    let mut point = POINT { x: 0, y: 0 };
+    // SAFETY: `point` is a valid POINT for the call to fill in
+    let found = unsafe { GetCursorPos(&mut point) };
+    (found != 0).then_some((point.x as f64, point.y as f64))
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn cursor_position() -> Option<(f64, f64)> {
+    None
+}
@@ -0,0 +1,236 @@
+//! Per-client key remapping, applied on the server before events are sent.
+//!
+//! A rule maps a key or chord to another key or chord, written like hotkeys:
+//! `MetaLeft -> ControlLeft`, `CapsLock -> Escape`, `Ctrl+C -> Meta+C`. Single
+//! keys are substituted as they go down and up. For chords, the client sees the
+//! source modifiers lifted and the target modifiers pressed around the key, and
+//! the original modifiers restored afterwards if they are still held.
+
+use crate::event::{Key, KvmEvent};
+use crate::hotkey::{Hotkey, side_insensitive};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Keys that can be part of a chord without being its final key
+pub const MODIFIERS: &[Key] = &[
+    Key::ControlLeft,
+    Key::ControlRight,
+    Key::ShiftLeft,
+    Key::ShiftRight,
+    Key::Alt,
+    Key::AltGr,
+    Key::MetaLeft,
+    Key::MetaRight,
+];
+
+fn is_modifier(key: Key) -> bool {
+    MODIFIERS.contains(&key)
+}
+
+/// One `from -> to` rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemapRule {
+    pub from: Hotkey,
+    pub to: Hotkey,
+}
+
+impl RemapRule {
+    /// Parse a rule from its two config strings
+    pub fn parse(from: &str, to: &str) -> Result<Self> {
+        Ok(Self {
+            from: from.parse().with_context(|| format!("in '{}'", from))?,
+            to: to.parse().with_context(|| format!("in '{}'", to))?,
+        })
+    }
+}
+
+/// A client's remap rules
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemapTable {
+    rules: Vec<RemapRule>,
+}
+
+impl RemapTable {
+    pub fn new(mut rules: Vec<RemapRule>) -> Self {
+        // Most specific first, so `Ctrl+C` wins over a plain `C` rule
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.from.modifiers.len()));
+        Self { rules }
+    }
+
+    /// Build a table from a client's `remap` setting, skipping entries that do not
+    /// parse (the config validator reports those)
+    pub fn from_config(entries: &BTreeMap<String, String>) -> Self {
+        Self::new(
+            entries
+                .iter()
+                .filter_map(|(from, to)| RemapRule::parse(from, to).ok())
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn lookup(&self, held: &HashSet<Key>, key: Key) -> Option<&RemapRule> {
+        self.rules.iter().find(|rule| rule.from.matches(held, key))
+    }
+}
+
+/// A rule whose key is down, keyed by the physical key that triggered it
+#[derive(Debug)]
+struct ActiveRule {
+    /// Key pressed on the client in its place
+    key: Key,
+    /// Target modifiers we pressed on the client for this rule
+    pressed: Vec<Key>,
+    /// Held modifiers we lifted on the client, as (physical, client) pairs
+    lifted: Vec<(Key, Key)>,
+}
+
+/// Applies a `RemapTable` to the key events for one client.
+///
+/// Tracks which physical keys are held and what the client has been told, so the
+/// client never ends up with a key stuck down.
+#[derive(Debug, Default)]
+pub struct Remapper {
+    /// Physical keys held on the server
+    held: HashSet<Key>,
+    /// Modifiers down on the client, by the physical key that put them there
+    client_modifiers: HashMap<Key, Key>,
+    active: HashMap<Key, ActiveRule>,
+}
+
+impl Remapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, table: &RemapTable, event: KvmEvent) -> Vec<KvmEvent> {
+        match event {
+            KvmEvent::KeyPress { key, .. } => self.press(table, key, event),
+            KvmEvent::KeyRelease { key, .. } => self.release(key, event),
+            event => vec![event],
+        }
+    }
+
+    /// Forget all state, e.g. when focus moves to another machine
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn press(&mut self, table: &RemapTable, key: Key, event: KvmEvent) -> Vec<KvmEvent> {
+        // Autorepeat of a key that is already remapped
+        if let Some(active) = self.active.get(&key) {
+            return vec![KvmEvent::key_press(active.key)];
+        }
+
+        let rule = table.lookup(&self.held, key).cloned();
+        self.held.insert(key);
+
+        let Some(rule) = rule else {
+            if is_modifier(key) {
+                self.client_modifiers.insert(key, key);
+            }
+            return vec![event];
+        };
+
+        let mut events = Vec::new();
+
+        // Lift the chord's own modifiers unless the target wants them too
+        let mut lifted = Vec::new();
+        for from_modifier in &rule.from.modifiers {
+            let matching: Vec<(Key, Key)> = self
+                .client_modifiers
+                .iter()
+                .filter(|(physical, _)| {
+                    side_insensitive(**physical) == side_insensitive(*from_modifier)
+                })
+                .map(|(physical, client)| (*physical, *client))
+                .collect();
+            for (physical, client) in matching {
+                let wanted = rule
+                    .to
+                    .modifiers
+                    .iter()
+                    .any(|m| side_insensitive(*m) == side_insensitive(client));
+                if !wanted {
+                    self.client_modifiers.remove(&physical);
+                    events.push(KvmEvent::key_release(client));
+                    lifted.push((physical, client));
+                }
+            }
+        }
+
+        let mut pressed = Vec::new();
+        for to_modifier in &rule.to.modifiers {
+            let already_down = self
+                .client_modifiers
+                .values()
+                .any(|client| side_insensitive(*client) == side_insensitive(*to_modifier));
+            if !already_down {
+                events.push(KvmEvent::key_press(*to_modifier));
+                pressed.push(*to_modifier);
+            }
+        }
+
+        events.push(KvmEvent::key_press(rule.to.key));
+        if is_modifier(key) && is_modifier(rule.to.key) && rule.to.modifiers.is_empty() {
+            // A plain modifier swap: the client holds the substitute like any modifier
+            self.client_modifiers.insert(key, rule.to.key);
+        } else {
+            self.active.insert(
+                key,
+                ActiveRule {
+                    key: rule.to.key,
+                    pressed,
+                    lifted,
+                },
+            );
+        }
+        events
+    }
+
+    fn release(&mut self, key: Key, event: KvmEvent) -> Vec<KvmEvent> {
+        self.held.remove(&key);
+
+        if let Some(active) = self.active.remove(&key) {
+            let mut events = vec![KvmEvent::key_release(active.key)];
+            events.extend(
+                active
+                    .pressed
+                    .iter()
+                    .rev()
+                    .map(|m| KvmEvent::key_release(*m)),
+            );
+            // Restore the modifiers the user is still holding
+            for (physical, client) in active.lifted {
+                if self.held.contains(&physical) {
+                    self.client_modifiers.insert(physical, client);
+                    events.push(KvmEvent::key_press(client));
+                }
+            }
+            return events;
+        }
+
+        if let Some(client) = self.client_modifiers.remove(&key) {
+            if client == key {
+                return vec![event];
+            }
+            return vec![KvmEvent::key_release(client)];
+        }
+
+        // Lifted by a chord that is still down; the client already saw it go up
+        let mut was_lifted = false;
+        for active in self.active.values_mut() {
+            let before = active.lifted.len();
+            active.lifted.retain(|(physical, _)| *physical != key);
+            was_lifted |= active.lifted.len() != before;
+        }
+        if was_lifted {
+            return vec![];
+        }
+
+        vec![event]
+    }
+}
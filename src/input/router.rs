@@ -0,0 +1,433 @@
+use super::pointer::{self, SpeedMeter};
+use super::remap::Remapper;
+use super::scroll;
+use super::text::{TextResolver, TextTranslator};
+use super::{CursorWarp, Disposition};
+use crate::config::{ClientConfig, KeyboardMode};
+use crate::event::{Button, ClientEvent, Key, KvmEvent};
+use crate::macros;
+use crate::topology::{Focus, SharedTopology, Topology, clamp_to};
+use std::collections::HashSet;
+use std::time::Instant;
+use tokio::sync::broadcast;
+
+/// How far the backend's cursor may stray from its parking spot while a client is
+/// focused before it is warped back
+const REPARK_DISTANCE: f64 = 200.0;
+
+/// The server's focus/edge state machine.
+///
+/// Every captured event goes through `handle`, which tracks held keys and the
+/// virtual cursor, switches focus at screen edges, and forwards events to the
+/// focused client.
+///
+/// While a client is focused the virtual cursor moves by the difference between
+/// consecutive backend positions and stays inside the client's rectangle, and
+/// the client is sent positions relative to its own origin.
+pub struct InputRouter {
+    topology: SharedTopology,
+    tx: broadcast::Sender<ClientEvent>,
+    /// Last known cursor position in the shared coordinate space
+    virtual_cursor: (f64, f64),
+    /// Last position the capture backend reported, or where we warped the cursor
+    raw_cursor: Option<(f64, f64)>,
+    /// Shared-space position minus backend position while local. Non-zero after
+    /// a client had focus on a backend that cannot warp the cursor.
+    local_offset: (f64, f64),
+    /// Parks the local cursor while a client is focused; `None` for backends
+    /// that report raw relative motion
+    warp: Option<Box<dyn CursorWarp>>,
+    /// Display scale factor of the server's screens
+    local_scale: f64,
+    /// Mouse speed, for client acceleration curves
+    speed: SpeedMeter,
+    /// Keys currently held, for hotkey detection
+    pressed: HashSet<Key>,
+    /// Mouse buttons currently held, for the drag edge policy
+    buttons: HashSet<Button>,
+    /// Macro, focus and focus lock hotkeys still held; their autorepeat and release
+    /// are swallowed
+    hotkey_keys: HashSet<Key>,
+    /// Applies the focused client's key remaps
+    remapper: Remapper,
+    /// Rewrites typing for clients in text mode; `None` if no layout resolver
+    text: Option<TextTranslator>,
+    /// Client the remapper and text translator state belongs to, cleared whenever
+    /// it loses focus
+    focused_client: Option<String>,
+    warned_no_text: bool,
+}
+
+impl InputRouter {
+    pub fn new(topology: SharedTopology, tx: broadcast::Sender<ClientEvent>) -> Self {
+        Self {
+            topology,
+            tx,
+            virtual_cursor: (0.0, 0.0),
+            raw_cursor: None,
+            local_offset: (0.0, 0.0),
+            warp: None,
+            local_scale: 1.0,
+            speed: SpeedMeter::new(),
+            pressed: HashSet::new(),
+            hotkey_keys: HashSet::new(),
+            buttons: HashSet::new(),
+            remapper: Remapper::new(),
+            text: None,
+            focused_client: None,
+            warned_no_text: false,
+        }
+    }
+
+    /// Enable `KeyboardMode::Text` for clients that ask for it. Without a
+    /// resolver those clients get key events.
+    pub fn set_text_resolver(&mut self, resolver: Box<dyn TextResolver>) {
+        self.text = Some(TextTranslator::new(resolver));
+    }
+
+    /// Park the local cursor with `warp` while a client is focused, for backends
+    /// whose positions the OS clamps to the screen
+    pub fn set_cursor_warp(&mut self, warp: Box<dyn CursorWarp>) {
+        self.warp = Some(warp);
+    }
+
+    /// Scale motion for clients by their display scale factor relative to
+    /// `scale`, the server's own
+    pub fn set_local_scale(&mut self, scale: f64) {
+        self.local_scale = scale;
+    }
+
+    pub fn virtual_cursor(&self) -> (f64, f64) {
+        self.virtual_cursor
+    }
+
+    pub fn handle(&mut self, event: KvmEvent) -> Disposition {
+        let topology = self.topology.clone();
+        let mut topo = topology.lock().unwrap();
+
+        match event {
+            KvmEvent::KeyPress { key, .. } => {
+                if self.hotkey_keys.contains(&key) {
+                    return Disposition::Swallow;
+                }
+                if let Some(name) = topo.profile_for_hotkey(&self.pressed, key) {
+                    // Profile hotkeys are swallowed on every machine
+                    let name = name.to_string();
+                    if let Err(e) = topo.switch_profile(&name) {
+                        println!("Profile switch failed: {}", e);
+                    }
+                    self.hotkey_keys.insert(key);
+                    return Disposition::Swallow;
+                }
+                if topo.is_focus_lock_hotkey(&self.pressed, key) {
+                    let locked = !topo.is_focus_locked();
+                    topo.set_focus_locked(locked);
+                    self.hotkey_keys.insert(key);
+                    return Disposition::Swallow;
+                }
+                if let Some(focus) = topo.focus_for_hotkey(&self.pressed, key) {
+                    self.switch_focus(&mut topo, focus);
+                    self.hotkey_keys.insert(key);
+                    return Disposition::Swallow;
+                }
+                if let Some(name) = topo.macro_for_hotkey(&self.pressed, key) {
+                    // Like profile hotkeys, never forwarded
+                    match macros::start(&topo, &self.tx, name, &self.pressed) {
+                        Ok(client) => println!("Running macro '{}' on {}", name, client),
+                        Err(e) => println!("Macro failed: {:#}", e),
+                    }
+                    self.hotkey_keys.insert(key);
+                    return Disposition::Swallow;
+                }
+                self.pressed.insert(key);
+            }
+            KvmEvent::KeyRelease { key, .. } => {
+                self.pressed.remove(&key);
+                if self.hotkey_keys.remove(&key) {
+                    return Disposition::Swallow;
+                }
+            }
+            KvmEvent::ButtonPress(button) => {
+                self.buttons.insert(button);
+            }
+            KvmEvent::ButtonRelease(button) => {
+                self.buttons.remove(&button);
+            }
+            _ => {}
+        }
+
+        match topo.get_focus() {
+            Focus::Local => {
+                if let Some(client) = self.focused_client.take() {
+                    let warped = self.resume_local(&mut topo, &client);
+                    if warped && matches!(event, KvmEvent::MouseMove { .. }) {
+                        // Reported before the warp
+                        return Disposition::Swallow;
+                    }
+                }
+
+                // Pass through events to local OS
+                // Check for edge switching
+                if let KvmEvent::MouseMove { x, y } = event {
+                    self.raw_cursor = Some((x, y));
+                    let (x, y) = (x + self.local_offset.0, y + self.local_offset.1);
+                    let previous = self.virtual_cursor;
+                    self.virtual_cursor = (x, y);
+
+                    let dragging = !self.buttons.is_empty();
+                    if let Some(new_focus) = topo.check_edge(x, y, dragging, Instant::now()) {
+                        println!("Switching focus to {:?}", new_focus);
+                        topo.remember_cursor(Focus::Local.name(), previous);
+                        topo.set_focus_at(new_focus, (x, y));
+                        // Keep the cursor away from the edge so the OS does not clamp
+                        // the motion we turn into movement on the client
+                        if self.park_cursor(&topo) {
+                            return Disposition::Swallow;
+                        }
+                    }
+                }
+                Disposition::Pass
+            }
+            Focus::Client(name) => {
+                let name = name.clone();
+                if self.focused_client.as_ref() != Some(&name) {
+                    self.enter_client(&mut topo, &name);
+                }
+
+                let unconfigured = ClientConfig::default();
+                let bounds = topo.client_bounds(&name);
+                let mut event = event;
+                if let KvmEvent::MouseMove { x, y } = event {
+                    // The local cursor is frozen (or parked) while a client has
+                    // focus, so only the motion since the last event means anything
+                    let (last_x, last_y) = self.raw_cursor.unwrap_or((x, y));
+                    self.raw_cursor = Some((x, y));
+                    let (dx, dy) = (x - last_x, y - last_y);
+                    if dx == 0.0 && dy == 0.0 {
+                        // Includes the echo of our own warp
+                        return Disposition::Swallow;
+                    }
+                    let speed = self.speed.measure(dx.hypot(dy), Instant::now());
+                    let scale = topo.client_scale(&name) / self.local_scale;
+                    let gain =
+                        pointer::gain(client_config(&topo, &name, &unconfigured), scale, speed);
+                    let (dx, dy) = (dx * gain, dy * gain);
+                    let target = (self.virtual_cursor.0 + dx, self.virtual_cursor.1 + dy);
+
+                    // Check if we are back inside any local screen
+                    if !topo.is_focus_locked() && topo.is_inside_local(target.0, target.1) {
+                        println!("Returning focus to Local");
+                        topo.remember_cursor(&name, self.virtual_cursor);
+                        topo.set_focus_at(Focus::Local, target);
+                        self.focused_client = None;
+                        self.virtual_cursor = target;
+                        if let Some(warp) = &mut self.warp
+                            && warp.warp(target.0, target.1).is_ok()
+                        {
+                            self.raw_cursor = Some(target);
+                            self.local_offset = (0.0, 0.0);
+                            return Disposition::Swallow;
+                        }
+                        // The cursor resumes from where it froze, so the backend's
+                        // coordinates are now off by however far we moved meanwhile.
+                        // We don't swallow this event so the cursor actually moves back in.
+                        self.local_offset = (target.0 - x, target.1 - y);
+                        return Disposition::Pass;
+                    }
+
+                    self.virtual_cursor = match bounds {
+                        Some(bounds) => clamp_to(bounds, target),
+                        None => target,
+                    };
+                    let (home_x, home_y) = topo.parking_spot();
+                    if (x - home_x).hypot(y - home_y) > REPARK_DISTANCE {
+                        self.park_cursor(&topo);
+                    }
+
+                    // Clients place the cursor in their own coordinates
+                    let (origin_x, origin_y) = bounds.map_or((0.0, 0.0), |b| (b.0, b.1));
+                    event = KvmEvent::MouseMove {
+                        x: self.virtual_cursor.0 - origin_x,
+                        y: self.virtual_cursor.1 - origin_y,
+                    };
+                }
+
+                let client = client_config(&topo, &name, &unconfigured);
+
+                let event = match event {
+                    KvmEvent::Wheel { delta_x, delta_y } => {
+                        let (delta_x, delta_y) =
+                            scroll::adjust_for_client(delta_x, delta_y, client);
+                        KvmEvent::Wheel { delta_x, delta_y }
+                    }
+                    event => event,
+                };
+                let mut events = match topo.remap_for(&name) {
+                    Some(table) => self.remapper.apply(table, event),
+                    None => vec![event],
+                };
+                if client.keyboard_mode == KeyboardMode::Text {
+                    events = events
+                        .into_iter()
+                        .flat_map(|event| self.translate_text(&name, event))
+                        .collect();
+                }
+
+                // Forward to clients
+                for event in events {
+                    let _ = self.tx.send(ClientEvent {
+                        client: name.clone(),
+                        event,
+                    });
+                }
+                Disposition::Swallow
+            }
+        }
+    }
+
+    /// Move focus for a focus hotkey, wherever the cursor is
+    fn switch_focus(&mut self, topo: &mut Topology, focus: Focus) {
+        if *topo.get_focus() == focus {
+            return;
+        }
+        if let Focus::Client(name) = topo.get_focus() {
+            // The hotkey's modifiers would stay down there otherwise
+            for key in &self.pressed {
+                let _ = self.tx.send(ClientEvent {
+                    client: name.clone(),
+                    event: KvmEvent::key_release(*key),
+                });
+            }
+        }
+
+        println!("Switching focus to {:?}", focus);
+        let from_local = *topo.get_focus() == Focus::Local;
+        topo.set_focus(focus.clone());
+        match focus {
+            Focus::Local => {
+                if let Some(client) = self.focused_client.take() {
+                    self.resume_local(topo, &client);
+                }
+            }
+            Focus::Client(name) => {
+                self.enter_client(topo, &name);
+                if from_local {
+                    self.park_cursor(topo);
+                }
+            }
+        }
+    }
+
+    /// Start routing to a newly focused client. If focus got there without the
+    /// cursor crossing an edge, the virtual cursor is placed on the client: where
+    /// it last was in jump mode, otherwise in the middle.
+    fn enter_client(&mut self, topo: &mut Topology, name: &str) {
+        self.remapper.reset();
+        if let Some(text) = &mut self.text {
+            text.reset();
+        }
+        let previous = self.focused_client.replace(name.to_string());
+
+        let Some(bounds) = topo.client_bounds(name) else {
+            return;
+        };
+        if clamp_to(bounds, self.virtual_cursor) == self.virtual_cursor {
+            return;
+        }
+
+        // The cursor is still on the machine focus left
+        let left = previous.unwrap_or_else(|| Focus::Local.name().to_string());
+        topo.remember_cursor(&left, self.virtual_cursor);
+
+        let Some(landing) = topo.landing_point(name) else {
+            return;
+        };
+        self.virtual_cursor = landing;
+        if topo.get_config().jump_mode && topo.last_cursor(name).is_some() {
+            let _ = self.tx.send(ClientEvent {
+                client: name.to_string(),
+                event: KvmEvent::MouseMove {
+                    x: landing.0 - bounds.0,
+                    y: landing.1 - bounds.1,
+                },
+            });
+        }
+    }
+
+    /// Pick the local cursor up again after focus came back from `client`
+    /// without crossing an edge. Returns whether the cursor was warped.
+    fn resume_local(&mut self, topo: &mut Topology, client: &str) -> bool {
+        topo.remember_cursor(client, self.virtual_cursor);
+        let Some(last) = topo.last_cursor(Focus::Local.name()) else {
+            return false;
+        };
+
+        match &mut self.warp {
+            // Parked while the client had focus; jump mode puts it back
+            Some(warp) => {
+                if !topo.get_config().jump_mode || warp.warp(last.0, last.1).is_err() {
+                    return false;
+                }
+                self.raw_cursor = Some(last);
+                self.local_offset = (0.0, 0.0);
+                self.virtual_cursor = last;
+                true
+            }
+            // Still frozen where focus left it, wherever the backend's
+            // coordinates went meanwhile
+            None => {
+                if let Some((x, y)) = self.raw_cursor {
+                    self.local_offset = (last.0 - x, last.1 - y);
+                }
+                self.virtual_cursor = last;
+                false
+            }
+        }
+    }
+
+    /// Warp the local cursor to its parking spot. Returns whether it moved.
+    fn park_cursor(&mut self, topo: &Topology) -> bool {
+        let Some(warp) = &mut self.warp else {
+            return false;
+        };
+        let spot = topo.parking_spot();
+        match warp.warp(spot.0, spot.1) {
+            Ok(()) => {
+                self.raw_cursor = Some(spot);
+                true
+            }
+            Err(e) => {
+                println!("Cursor warp failed: {:#}", e);
+                false
+            }
+        }
+    }
+
+    fn translate_text(&mut self, client: &str, event: KvmEvent) -> Vec<KvmEvent> {
+        let Some(text) = self.text.as_mut() else {
+            if !self.warned_no_text {
+                println!(
+                    "No keyboard layout available for text mode, sending keys to {}",
+                    client
+                );
+                self.warned_no_text = true;
+            }
+            return vec![event];
+        };
+        text.translate(event)
+    }
+}
+
+/// A client's settings in the active profile, or `unconfigured` if it has none
+fn client_config<'a>(
+    topo: &'a Topology,
+    name: &str,
+    unconfigured: &'a ClientConfig,
+) -> &'a ClientConfig {
+    topo.get_config()
+        .active()
+        .clients
+        .iter()
+        .find(|c| c.name == name)
+        .unwrap_or(unconfigured)
+}
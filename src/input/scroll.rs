@@ -0,0 +1,54 @@
+//! Scroll wheel units and per-client adjustments.
+//!
+//! `KvmEvent::Wheel` carries notches as `f64`. High-resolution sources report
+//! fractions of a notch in 1/120 steps, the unit Windows (`WHEEL_DELTA`) and the
+//! evdev `REL_*_HI_RES` axes share.
+
+use crate::config::ClientConfig;
+
+/// High-resolution wheel units in one notch
+pub const HI_RES_PER_NOTCH: f64 = 120.0;
+
+/// Apply a client's scroll direction and speed settings to a wheel delta
+pub fn adjust_for_client(delta_x: f64, delta_y: f64, client: &ClientConfig) -> (f64, f64) {
+    let factor = if client.invert_scroll {
+        -client.scroll_speed
+    } else {
+        client.scroll_speed
+    };
+    (delta_x * factor, delta_y * factor)
+}
+
+/// Collects fractional scroll deltas for outputs that only take whole notches,
+/// carrying the remainder over to the next event
+#[derive(Debug, Default)]
+pub struct ScrollAccumulator {
+    pending: (f64, f64),
+}
+
+impl ScrollAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a delta and return the whole notches that are now due
+    pub fn add(&mut self, delta_x: f64, delta_y: f64) -> (i64, i64) {
+        (
+            take_notches(&mut self.pending.0, delta_x),
+            take_notches(&mut self.pending.1, delta_y),
+        )
+    }
+}
+
+fn take_notches(pending: &mut f64, delta: f64) -> i64 {
+    // A leftover from scrolling the other way must not eat into the new direction
+    if *pending * delta < 0.0 {
+        *pending = 0.0;
+    }
+
+    // Snap to hi-res steps so float error cannot leave 0.999... notches behind
+    *pending = ((*pending + delta) * HI_RES_PER_NOTCH).round() / HI_RES_PER_NOTCH;
+    let notches = pending.trunc();
+    *pending -= notches;
+    notches as i64
+}
@@ -0,0 +1,135 @@
+//! Text mode: turning captured keystrokes into Unicode text on the server.
+//!
+//! Clients in `KeyboardMode::Text` receive `KvmEvent::Text` for ordinary typing,
+//! resolved with the server's layout, so a client with a different layout still
+//! types the characters printed on the server's keyboard. Anything that is not
+//! plain typing (shortcuts, modifier chords, Enter, arrows, ...) still goes out
+//! as key events.
+
+use crate::event::{Key, KvmEvent};
+use std::collections::HashSet;
+
+/// Resolves key events to the text they type with the local keyboard layout
+pub trait TextResolver: Send {
+    /// Update modifier state with `event` and return the text a key press types.
+    /// Called for every key event, presses and releases, in order.
+    fn resolve(&mut self, event: &KvmEvent) -> Option<String>;
+
+    /// Forget held modifiers, e.g. after focus moves to another machine
+    fn reset(&mut self);
+}
+
+/// Resolver using the server's active layout, or `None` if it is unavailable
+/// (no X display on Linux, for example)
+pub fn create_text_resolver() -> Option<Box<dyn TextResolver>> {
+    rdev::Keyboard::new().map(|keyboard| Box::new(RdevResolver(keyboard)) as Box<dyn TextResolver>)
+}
+
+/// Wraps `rdev::Keyboard`, which tracks Shift and Caps Lock itself.
+///
+/// rdev does not resolve AltGr levels, so AltGr is treated as a chord modifier
+/// by `TextTranslator` and those keys go out as keys.
+struct RdevResolver(rdev::Keyboard);
+
+// SAFETY: on Linux `rdev::Keyboard` holds an Xlib display and input context, which
+// are not thread-safe but may move between threads. The resolver is owned by the
+// router and only ever used from the capture thread, one call at a time.
+unsafe impl Send for RdevResolver {}
+
+impl TextResolver for RdevResolver {
+    fn resolve(&mut self, event: &KvmEvent) -> Option<String> {
+        use rdev::KeyboardState;
+
+        let event_type = rdev::EventType::try_from(event.clone()).ok()?;
+        self.0.add(&event_type)
+    }
+
+    fn reset(&mut self) {
+        use rdev::KeyboardState;
+
+        self.0.reset();
+    }
+}
+
+/// Modifiers that turn a keystroke into a shortcut rather than typing
+const CHORD_MODIFIERS: &[Key] = &[
+    Key::ControlLeft,
+    Key::ControlRight,
+    Key::Alt,
+    Key::AltGr,
+    Key::MetaLeft,
+    Key::MetaRight,
+];
+
+/// Rewrites a client's event stream for text mode.
+///
+/// A key press with no chord modifier held that types printable text becomes
+/// `KvmEvent::Text`, and its release is dropped. Caps Lock is applied by the
+/// resolver, so it is not forwarded. Everything else passes through unchanged,
+/// so the client sees modifiers, shortcuts and editing keys as usual.
+pub struct TextTranslator {
+    resolver: Box<dyn TextResolver>,
+    /// Chord modifiers currently held
+    chord: HashSet<Key>,
+    /// Keys whose press was sent as text, so their release must not be sent
+    converted: HashSet<Key>,
+}
+
+impl TextTranslator {
+    pub fn new(resolver: Box<dyn TextResolver>) -> Self {
+        Self {
+            resolver,
+            chord: HashSet::new(),
+            converted: HashSet::new(),
+        }
+    }
+
+    pub fn translate(&mut self, event: KvmEvent) -> Vec<KvmEvent> {
+        match event {
+            KvmEvent::KeyPress { key, .. } => {
+                let text = self.resolver.resolve(&event);
+
+                if key == Key::CapsLock {
+                    return vec![];
+                }
+                if CHORD_MODIFIERS.contains(&key) {
+                    self.chord.insert(key);
+                } else if self.chord.is_empty()
+                    && let Some(text) = text.filter(|t| is_typed_text(t))
+                {
+                    self.converted.insert(key);
+                    return vec![KvmEvent::Text(text)];
+                }
+
+                // A key that was typing text and now repeats inside a chord is a
+                // real key press again, so let its release through
+                self.converted.remove(&key);
+                vec![event]
+            }
+            KvmEvent::KeyRelease { key, .. } => {
+                self.resolver.resolve(&event);
+                self.chord.remove(&key);
+
+                if key == Key::CapsLock || self.converted.remove(&key) {
+                    vec![]
+                } else {
+                    vec![event]
+                }
+            }
+            event => vec![event],
+        }
+    }
+
+    /// Start over, e.g. when focus moves. Converted keys were never pressed on
+    /// the client, so nothing needs releasing.
+    pub fn reset(&mut self) {
+        self.resolver.reset();
+        self.chord.clear();
+        self.converted.clear();
+    }
+}
+
+/// Printable text; control characters (Enter, Tab, Backspace, Escape) stay keys
+fn is_typed_text(text: &str) -> bool {
+    !text.is_empty() && !text.chars().any(char::is_control)
+}
@@ -0,0 +1,193 @@
+//! Typing arbitrary Unicode text on the client, independent of its keyboard layout.
+//!
+//! Used by the rdev injector for `KvmEvent::Text`.
+
+#[cfg(target_os = "linux")]
+pub use x11_typer::UnicodeTyper;
+
+#[cfg(target_os = "linux")]
+mod x11_typer {
+    use anyhow::{Result, bail};
+    use x11::{xlib, xtest};
+
+    /// Spare keycodes to rotate through. Applications look keysyms up when they
+    /// process an event, which can be after we remap the keycode for the next
+    /// character, so a few are kept in flight.
+    const MAX_SPARE_KEYCODES: usize = 8;
+
+    /// Types characters by temporarily mapping their keysym onto an unused
+    /// keycode and pressing it with XTest, like `xdotool type` does
+    pub struct UnicodeTyper {
+        display: *mut xlib::Display,
+        spare: Vec<xlib::KeyCode>,
+        next: usize,
+    }
+
+    // SAFETY: the display connection is owned by this typer and only used through
+    // `&mut self`, so it is never touched by two threads at once
+    unsafe impl Send for UnicodeTyper {}
+
+    impl UnicodeTyper {
+        pub fn new() -> Result<Self> {
+            // SAFETY: plain Xlib calls on a display we opened; the keysym array is
+            // read within the bounds Xlib reported and freed once
+            unsafe {
+                let display = xlib::XOpenDisplay(std::ptr::null());
+                if display.is_null() {
+                    bail!("Cannot open the X display for text input");
+                }
+
+                let (mut min, mut max) = (0, 0);
+                xlib::XDisplayKeycodes(display, &mut min, &mut max);
+                let count = max - min + 1;
+                let mut per_code = 0;
+                let keysyms = xlib::XGetKeyboardMapping(display, min as u8, count, &mut per_code);
+                if keysyms.is_null() {
+                    xlib::XCloseDisplay(display);
+                    bail!("Cannot read the X keyboard mapping");
+                }
+
+                let mapping = std::slice::from_raw_parts(keysyms, (count * per_code) as usize);
+                let spare: Vec<xlib::KeyCode> = mapping
+                    .chunks(per_code as usize)
+                    .enumerate()
+                    .rev()
+                    .filter(|(_, syms)| syms.iter().all(|&sym| sym == 0))
+                    .map(|(index, _)| (min + index as i32) as xlib::KeyCode)
+                    .take(MAX_SPARE_KEYCODES)
+                    .collect();
+                xlib::XFree(keysyms.cast());
+
+                if spare.is_empty() {
+                    xlib::XCloseDisplay(display);
+                    bail!("No unused keycode to type text with");
+                }
+
+                Ok(Self {
+                    display,
+                    spare,
+                    next: 0,
+                })
+            }
+        }
+
+        pub fn type_text(&mut self, text: &str) -> Result<()> {
+            for c in text.chars() {
+                let keycode = self.spare[self.next];
+                self.next = (self.next + 1) % self.spare.len();
+
+                // Same keysym on both levels so a Shift held on the client does not matter
+                let mut keysyms = [keysym_for_char(c); 2];
+                // SAFETY: `keysyms` holds exactly one keycode's worth of entries
+                unsafe {
+                    xlib::XChangeKeyboardMapping(
+                        self.display,
+                        keycode as i32,
+                        2,
+                        keysyms.as_mut_ptr(),
+                        1,
+                    );
+                    xlib::XSync(self.display, xlib::False);
+                    xtest::XTestFakeKeyEvent(self.display, keycode as u32, xlib::True, 0);
+                    xtest::XTestFakeKeyEvent(self.display, keycode as u32, xlib::False, 0);
+                    xlib::XSync(self.display, xlib::False);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for UnicodeTyper {
+        fn drop(&mut self) {
+            // SAFETY: the display is still open; it is closed last
+            unsafe {
+                for &keycode in &self.spare {
+                    let mut no_symbol: xlib::KeySym = 0;
+                    xlib::XChangeKeyboardMapping(
+                        self.display,
+                        keycode as i32,
+                        1,
+                        &mut no_symbol,
+                        1,
+                    );
+                }
+                xlib::XSync(self.display, xlib::False);
+                xlib::XCloseDisplay(self.display);
+            }
+        }
+    }
+
+    /// Latin-1 keysyms equal their code point; everything else uses the
+    /// `0x01000000 + code point` Unicode keysym range
+    fn keysym_for_char(c: char) -> xlib::KeySym {
+        let code = c as u32;
+        if (0x20..=0x7e).contains(&code) || (0xa0..=0xff).contains(&code) {
+            code as xlib::KeySym
+        } else {
+            (0x0100_0000 | code) as xlib::KeySym
+        }
+    }
+}
+
+/// Types text with `SendInput` and `KEYEVENTF_UNICODE`, one UTF-16 unit per event
+#[cfg(windows)]
+pub struct UnicodeTyper;
+
+#[cfg(windows)]
+impl UnicodeTyper {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    pub fn type_text(&mut self, text: &str) -> anyhow::Result<()> {
+        use winapi::um::winuser::{
+            INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, SendInput,
+        };
+
+        let mut inputs: Vec<INPUT> = Vec::new();
+        for unit in text.encode_utf16() {
+            for flags in [KEYEVENTF_UNICODE, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP] {
+                // SAFETY: INPUT is plain data, and we only write the keyboard variant of its union
+                let mut input: INPUT = unsafe { std::mem::zeroed() };
+                input.type_ = INPUT_KEYBOARD;
+                unsafe {
+                    *input.u.ki_mut() = KEYBDINPUT {
+                        wVk: 0,
+                        wScan: unit,
+                        dwFlags: flags,
+                        time: 0,
+                        dwExtraInfo: 0,
+                    };
+                }
+                inputs.push(input);
+            }
+        }
+
+        // SAFETY: `inputs` is a valid array of INPUTs and the size matches their type
+        let sent = unsafe {
+            SendInput(
+                inputs.len() as u32,
+                inputs.as_mut_ptr(),
+                std::mem::size_of::<INPUT>() as i32,
+            )
+        };
+        if sent as usize != inputs.len() {
+            anyhow::bail!("SendInput rejected text input");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub struct UnicodeTyper;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl UnicodeTyper {
+    pub fn new() -> anyhow::Result<Self> {
+        anyhow::bail!("Text input is not supported on this platform; use keyboard_mode \"keys\"")
+    }
+
+    pub fn type_text(&mut self, _text: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -1,11 +1,26 @@
 pub mod client;
 pub mod config;
 pub mod connected;
+pub mod control;
 pub mod discovery;
+pub mod edge;
 pub mod event;
+pub mod events;
 // pub mod gui; // Old egui GUI - replaced by gui_slint
 pub mod gui_slint;
+pub mod hotkey;
+pub mod inhibit;
+pub mod input;
+pub mod macros;
+pub mod migrate;
 pub mod monitor;
 pub mod net;
+pub mod overlay;
+pub mod pairing;
+pub mod reload;
+pub mod scancode;
 pub mod server;
+pub mod session;
 pub mod topology;
+pub mod tray;
+pub mod validate;
@@ -0,0 +1,133 @@
+//! Server-side macros: named sequences of key events and text sent to one client.
+//!
+//! A macro is started by its hotkey in the input router or by the `run_macro`
+//! control method. Its events bypass the focused client's remaps and text mode,
+//! so they arrive exactly as written.
+//!
+//! Events go out one `SEND_INTERVAL` apart, so a long macro, or several at once,
+//! can't overrun the channel feeding the client connections.
+
+use crate::config::{MacroConfig, MacroStep};
+use crate::event::{ClientEvent, Key, KvmEvent};
+use crate::hotkey::Hotkey;
+use crate::topology::{Focus, Topology};
+use anyhow::{Context, Result, anyhow, bail};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Gap between the events a macro sends
+pub const SEND_INTERVAL: Duration = Duration::from_millis(1);
+
+/// What a macro does next
+#[derive(Debug, Clone, PartialEq)]
+pub enum MacroAction {
+    Send(KvmEvent),
+    Wait(Duration),
+}
+
+/// Turn one step into actions. Chords are pressed modifiers first and released
+/// in reverse.
+pub fn expand_step(step: &MacroStep) -> Result<Vec<MacroAction>> {
+    let chord =
+        |keys: &str| -> Result<Hotkey> { keys.parse().with_context(|| format!("in '{}'", keys)) };
+    let press = |hotkey: &Hotkey| {
+        hotkey
+            .modifiers
+            .iter()
+            .chain([&hotkey.key])
+            .map(|key| MacroAction::Send(KvmEvent::key_press(*key)))
+            .collect::<Vec<_>>()
+    };
+    let release = |hotkey: &Hotkey| {
+        [&hotkey.key]
+            .into_iter()
+            .chain(hotkey.modifiers.iter().rev())
+            .map(|key| MacroAction::Send(KvmEvent::key_release(*key)))
+            .collect::<Vec<_>>()
+    };
+
+    Ok(match step {
+        MacroStep::Tap(keys) => {
+            let hotkey = chord(keys)?;
+            let mut actions = press(&hotkey);
+            actions.extend(release(&hotkey));
+            actions
+        }
+        MacroStep::Press(keys) => press(&chord(keys)?),
+        MacroStep::Release(keys) => release(&chord(keys)?),
+        MacroStep::Text(text) => vec![MacroAction::Send(KvmEvent::Text(text.clone()))],
+        MacroStep::DelayMs(ms) => vec![MacroAction::Wait(Duration::from_millis(*ms))],
+    })
+}
+
+/// Turn a macro's steps into the actions to run
+pub fn expand(steps: &[MacroStep]) -> Result<Vec<MacroAction>> {
+    let mut actions = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        actions.extend(expand_step(step).with_context(|| format!("step {}", i + 1))?);
+    }
+    Ok(actions)
+}
+
+/// Start the macro called `name` and return the client it is sent to.
+///
+/// `held` are the keys the user is holding, usually the hotkey's modifiers. If
+/// the macro goes to the focused client they are released there first, so they
+/// do not combine with the macro's own keys. The events are sent from a thread
+/// of their own, since delays would otherwise stall the caller.
+pub fn start(
+    topology: &Topology,
+    tx: &broadcast::Sender<ClientEvent>,
+    name: &str,
+    held: &HashSet<Key>,
+) -> Result<String> {
+    if topology.is_paused() {
+        bail!("Sharing is paused");
+    }
+    let config = topology
+        .get_config()
+        .macros
+        .iter()
+        .find(|m| m.name == name)
+        .ok_or_else(|| anyhow!("Unknown macro '{}'", name))?;
+    let client = target(topology.get_focus(), config)?;
+    let mut actions = expand(&config.steps).with_context(|| format!("in macro '{}'", name))?;
+
+    if *topology.get_focus() == Focus::Client(client.clone()) {
+        let releases = held
+            .iter()
+            .map(|key| MacroAction::Send(KvmEvent::key_release(*key)));
+        actions.splice(0..0, releases);
+    }
+
+    let tx = tx.clone();
+    let target = client.clone();
+    std::thread::spawn(move || {
+        for action in actions {
+            match action {
+                MacroAction::Send(event) => {
+                    let _ = tx.send(ClientEvent {
+                        client: target.clone(),
+                        event,
+                    });
+                    std::thread::sleep(SEND_INTERVAL);
+                }
+                MacroAction::Wait(duration) => std::thread::sleep(duration),
+            }
+        }
+    });
+
+    Ok(client)
+}
+
+fn target(focus: &Focus, config: &MacroConfig) -> Result<String> {
+    match (&config.target, focus) {
+        (Some(target), _) => Ok(target.clone()),
+        (None, Focus::Client(name)) => Ok(name.clone()),
+        (None, Focus::Local) => Err(anyhow!(
+            "Macro '{}' has no target and no client is focused",
+            config.name
+        )),
+    }
+}
@@ -0,0 +1,92 @@
+use crate::config::{CONFIG_VERSION, DEFAULT_PROFILE};
+use anyhow::{Result, anyhow, bail};
+use serde_json::Value;
+
+/// Upgrades a config document from version `n` to `n + 1`
+type Migration = fn(&mut serde_json::Map<String, Value>) -> Result<()>;
+
+/// `MIGRATIONS[n]` upgrades version `n` to `n + 1`; its length is always `CONFIG_VERSION`
+const MIGRATIONS: &[Migration] = &[v0_to_v1, v1_to_v2];
+
+/// Read the schema version of a config document. Files without one predate versioning.
+pub fn schema_version(value: &Value) -> Result<u32> {
+    match value.get("version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow!("'version' must be a non-negative integer, got {}", version)),
+    }
+}
+
+/// Upgrade a config document to `CONFIG_VERSION`, returning the version it started at
+pub fn migrate(value: &mut Value) -> Result<u32> {
+    debug_assert_eq!(MIGRATIONS.len(), CONFIG_VERSION as usize);
+
+    let from_version = schema_version(value)?;
+    if from_version > CONFIG_VERSION {
+        bail!(
+            "Config schema version {} is newer than this build supports ({})",
+            from_version,
+            CONFIG_VERSION
+        );
+    }
+
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Config must be a JSON object"))?;
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from_version as usize) {
+        migration(object).map_err(|e| {
+            anyhow!(
+                "Migration from version {} to {} failed: {}",
+                version,
+                version + 1,
+                e
+            )
+        })?;
+        object.insert("version".to_string(), Value::from(version as u32 + 1));
+    }
+
+    Ok(from_version)
+}
+
+/// Migrate config source text if it is out of date.
+///
+/// Returns `None` when the text is already current, or is not valid JSON (the
+/// validator reports parse errors with line numbers).
+pub fn migrate_source(source: &str) -> Result<Option<(u32, String)>> {
+    let Ok(mut value) = serde_json::from_str::<Value>(source) else {
+        return Ok(None);
+    };
+
+    if schema_version(&value)? == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    let from_version = migrate(&mut value)?;
+    Ok(Some((from_version, serde_json::to_string_pretty(&value)?)))
+}
+
+/// Version 0 (unversioned) files only need the version field added
+fn v0_to_v1(_config: &mut serde_json::Map<String, Value>) -> Result<()> {
+    Ok(())
+}
+
+/// Version 2 moves the single layout into a profile named "default"
+fn v1_to_v2(config: &mut serde_json::Map<String, Value>) -> Result<()> {
+    let mut profile = serde_json::Map::new();
+    profile.insert("name".to_string(), Value::from(DEFAULT_PROFILE));
+    for key in ["local_screens", "clients"] {
+        if let Some(value) = config.remove(key) {
+            profile.insert(key.to_string(), value);
+        }
+    }
+
+    config.insert("active_profile".to_string(), Value::from(DEFAULT_PROFILE));
+    config.insert(
+        "profiles".to_string(),
+        Value::Array(vec![Value::Object(profile)]),
+    );
+    Ok(())
+}
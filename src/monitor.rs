@@ -36,3 +36,30 @@ pub fn detect_monitors() -> Vec<LocalScreen> {
         }
     }
 }
+
+/// The primary monitor, or the first one if none is marked primary
+pub fn detect_primary_screen() -> Option<LocalScreen> {
+    let displays = DisplayInfo::all().unwrap_or_default();
+    displays
+        .iter()
+        .find(|display| display.is_primary)
+        .or(displays.first())
+        .map(|display| LocalScreen {
+            x: display.x,
+            y: display.y,
+            width: display.width,
+            height: display.height,
+        })
+}
+
+/// Display scale factor of the primary monitor, 1.0 if unknown
+pub fn detect_scale_factor() -> f64 {
+    let displays = DisplayInfo::all().unwrap_or_default();
+    displays
+        .iter()
+        .find(|display| display.is_primary)
+        .or(displays.first())
+        .map(|display| display.scale_factor as f64)
+        .filter(|scale| *scale > 0.0)
+        .unwrap_or(1.0)
+}
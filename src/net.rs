@@ -1,4 +1,6 @@
+use crate::edge::Edge;
 use crate::event::KvmEvent;
+use crate::session::SessionEvent;
 use anyhow::{Result, anyhow};
 use bincode;
 use serde::{Deserialize, Serialize};
@@ -6,7 +8,7 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
 const MAX_FRAME_SIZE: u32 = 1024 * 1024; // 1MB
-pub const PROTOCOL_VERSION: u32 = 1;
+pub const PROTOCOL_VERSION: u32 = 11;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ScreenInfo {
@@ -15,6 +17,8 @@ pub struct ScreenInfo {
     pub x: i32,
     pub y: i32,
     pub name: String,
+    /// OS display scale factor, e.g. 2.0 on a HiDPI panel
+    pub scale: f64,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -26,6 +30,24 @@ pub enum Packet {
     },
     Event(KvmEvent),
     Heartbeat,
+    /// Server to client: input goes to this client from now on, starting with
+    /// the cursor at `entry_point` in the client's own coordinates. `entry_edge`
+    /// is the side the cursor crossed, if it came in over an edge.
+    FocusGained {
+        entry_point: (f64, f64),
+        entry_edge: Option<Edge>,
+    },
+    /// Server to client: focus moved to another machine
+    FocusLost,
+    /// Client to server, in reply to `FocusLost`: where the client's cursor is,
+    /// in its own coordinates
+    CursorPosition {
+        x: f64,
+        y: f64,
+    },
+    /// Either way: the sender's screen was locked or unlocked, or its
+    /// screensaver started or stopped
+    Session(SessionEvent),
 }
 
 pub struct KvmStream {
@@ -0,0 +1,230 @@
+//! On-screen focus indicator.
+//!
+//! When a machine gains focus it flashes its name and an arrow pointing the way
+//! the cursor came in. The flash is a Slint component, so it lives on the thread
+//! running the Slint event loop: the configuration GUI's on the server, or one
+//! the binary runs on its main thread otherwise. It shows up while the user is
+//! typing or clicking, so it must neither take focus nor catch clicks.
+
+use crate::config::{FocusOverlay, LocalScreen};
+use crate::edge::Edge;
+use crate::events::ServerEvent;
+use crate::gui_slint::FocusFlash;
+use crate::topology::{Focus, SharedTopology};
+use slint::winit_030::{WinitWindowAccessor, winit};
+use slint::{ComponentHandle, PhysicalPosition, PlatformError, Timer, TimerMode};
+use std::cell::Cell;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+thread_local! {
+    /// Set while the flash window is created, for the hook `select_backend` installs
+    static CREATING_FLASH: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A focus change on this machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusNotice {
+    /// Focus arrived, across `edge` if the cursor crossed one
+    Gained {
+        edge: Option<Edge>,
+    },
+    Lost,
+}
+
+/// Arrow pointing the way the cursor moved to come in across `edge`
+pub fn arrow(edge: Option<Edge>) -> &'static str {
+    match edge {
+        Some(Edge::Left) => "→",
+        Some(Edge::Right) => "←",
+        Some(Edge::Top) => "↓",
+        Some(Edge::Bottom) => "↑",
+        None => "",
+    }
+}
+
+/// Select Slint's backend with a hook that keeps the flash from being focused.
+/// Only works before the first Slint window is created; without it the flash
+/// still shows, but the window manager may focus it.
+fn select_backend() {
+    let result = slint::BackendSelector::new()
+        .with_winit_window_attributes_hook(|attributes| {
+            if CREATING_FLASH.get() {
+                flash_attributes(attributes)
+            } else {
+                attributes
+            }
+        })
+        .select();
+    if let Err(e) = result {
+        println!("Focus overlay may take focus: {}", e);
+    }
+}
+
+fn flash_attributes(
+    attributes: winit::window::WindowAttributes,
+) -> winit::window::WindowAttributes {
+    let attributes = attributes.with_active(false);
+    // X11 ignores `active`; unmanaged windows are never focused by the window
+    // manager, and are placed where they ask to be
+    #[cfg(target_os = "linux")]
+    let attributes = {
+        use winit::platform::x11::{WindowAttributesExtX11, WindowType};
+        attributes
+            .with_override_redirect(true)
+            .with_x11_window_type(vec![WindowType::Notification])
+    };
+    attributes
+}
+
+/// Name to flash on this machine
+pub fn this_machine() -> String {
+    hostname::get()
+        .ok()
+        .and_then(|h| h.into_string().ok())
+        .unwrap_or_else(|| "This machine".to_string())
+}
+
+/// The indicator windows for one screen
+pub struct Overlay {
+    settings: FocusOverlay,
+    screen: LocalScreen,
+    flash: FocusFlash,
+    hide_flash: Timer,
+}
+
+impl Overlay {
+    pub fn new(
+        settings: FocusOverlay,
+        machine: &str,
+        screen: LocalScreen,
+    ) -> Result<Self, PlatformError> {
+        CREATING_FLASH.set(true);
+        let flash = FocusFlash::new();
+        CREATING_FLASH.set(false);
+        let flash = flash?;
+        flash.set_machine(machine.into());
+        Ok(Self {
+            settings,
+            screen,
+            flash,
+            hide_flash: Timer::default(),
+        })
+    }
+
+    pub fn show(&self, notice: FocusNotice) {
+        let result = match notice {
+            FocusNotice::Gained { edge } => self.gained(edge),
+            FocusNotice::Lost => self.lost(),
+        };
+        if let Err(e) = result {
+            println!("Focus overlay failed: {}", e);
+        }
+    }
+
+    fn gained(&self, edge: Option<Edge>) -> Result<(), PlatformError> {
+        self.flash.set_arrow(arrow(edge).into());
+        self.flash.show()?;
+        // Let clicks through to whatever is underneath
+        self.flash.window().with_winit_window(|window| {
+            if let Err(e) = window.set_cursor_hittest(false) {
+                println!("Focus overlay will catch clicks: {}", e);
+            }
+        });
+        let size = self.flash.window().size();
+        self.flash.window().set_position(PhysicalPosition::new(
+            self.screen.x + (self.screen.width as i32 - size.width as i32) / 2,
+            self.screen.y + (self.screen.height as i32 - size.height as i32) / 2,
+        ));
+        let flash = self.flash.as_weak();
+        self.hide_flash.start(
+            TimerMode::SingleShot,
+            Duration::from_millis(self.settings.flash_ms),
+            move || {
+                if let Some(flash) = flash.upgrade() {
+                    let _ = flash.hide();
+                }
+            },
+        );
+        Ok(())
+    }
+
+    fn lost(&self) -> Result<(), PlatformError> {
+        self.hide_flash.stop();
+        self.flash.hide()
+    }
+}
+
+/// Show the notices from `notices` on this machine's primary screen. Call on the
+/// Slint event loop thread, before any other Slint window is created; the
+/// overlay lasts as long as the returned timer.
+pub fn attach(
+    settings: FocusOverlay,
+    machine: &str,
+    mut notices: mpsc::UnboundedReceiver<FocusNotice>,
+) -> Result<Timer, PlatformError> {
+    select_backend();
+    let screen = crate::monitor::detect_primary_screen()
+        .ok_or_else(|| PlatformError::Other("No screen to show it on".to_string()))?;
+    let overlay = Overlay::new(settings, machine, screen)?;
+
+    let timer = Timer::default();
+    timer.start(TimerMode::Repeated, Duration::from_millis(50), move || {
+        while let Ok(notice) = notices.try_recv() {
+            overlay.show(notice);
+        }
+    });
+    Ok(timer)
+}
+
+/// The overlay for the server's own screen, if the config enables it
+pub fn attach_local(topology: &SharedTopology) -> Option<Timer> {
+    let settings = topology.lock().unwrap().get_config().focus_overlay.clone();
+    if !settings.enabled {
+        return None;
+    }
+    attach(settings, &this_machine(), local_notices(topology))
+        .inspect_err(|e| println!("Focus overlay unavailable: {}", e))
+        .ok()
+}
+
+/// Focus notices for the server's own screen. Focus starting out here is not a
+/// change, so nothing flashes until it moves.
+pub fn local_notices(topology: &SharedTopology) -> mpsc::UnboundedReceiver<FocusNotice> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (mut events, mut focused) = {
+        let topo = topology.lock().unwrap();
+        (topo.subscribe(), *topo.get_focus() == Focus::Local)
+    };
+
+    // Weak, so the channel closes once everyone else drops the topology
+    let topology = std::sync::Arc::downgrade(topology);
+    std::thread::spawn(move || {
+        loop {
+            match events.blocking_recv() {
+                Ok(ServerEvent::FocusChanged { focus }) => {
+                    let local = focus == Focus::Local.name();
+                    if local == focused {
+                        continue;
+                    }
+                    focused = local;
+                    let notice = if local {
+                        let Some(topology) = topology.upgrade() else {
+                            break;
+                        };
+                        let edge = topology.lock().unwrap().entry_edge(&focus);
+                        FocusNotice::Gained { edge }
+                    } else {
+                        FocusNotice::Lost
+                    };
+                    if tx.send(notice).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+    rx
+}
@@ -0,0 +1,76 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Clients waiting for approval and those approved during this session.
+///
+/// Only consulted when `Config::require_pairing` is set; clients named in the active
+/// profile are always accepted. Approval covers a name from one IP, so another host
+/// reporting the same name has to be approved separately.
+#[derive(Default)]
+pub struct Pairing {
+    pending: HashMap<String, PendingClient>,
+    approved: HashSet<(String, IpAddr)>,
+}
+
+struct PendingClient {
+    addr: SocketAddr,
+    approve: oneshot::Sender<()>,
+}
+
+pub type SharedPairing = Arc<Mutex<Pairing>>;
+
+pub fn create_pairing() -> SharedPairing {
+    Arc::new(Mutex::new(Pairing::default()))
+}
+
+impl Pairing {
+    pub fn is_approved(&self, name: &str, addr: SocketAddr) -> bool {
+        self.approved.contains(&(name.to_string(), addr.ip()))
+    }
+
+    /// Queue a client for approval. The receiver resolves once it is approved.
+    ///
+    /// Returns None if a client of that name is already waiting, so one can't
+    /// take over another's request.
+    pub fn request(&mut self, name: &str, addr: SocketAddr) -> Option<oneshot::Receiver<()>> {
+        if self.pending.contains_key(name) {
+            return None;
+        }
+        let (approve, approved) = oneshot::channel();
+        self.pending
+            .insert(name.to_string(), PendingClient { addr, approve });
+        Some(approved)
+    }
+
+    /// Approve a pending client. Returns false if no client of that name is waiting.
+    pub fn approve(&mut self, name: &str) -> bool {
+        match self.pending.remove(name) {
+            Some(client) => {
+                self.approved.insert((name.to_string(), client.addr.ip()));
+                let _ = client.approve.send(());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop the request `addr` made for `name`, e.g. after it timed out
+    pub fn cancel(&mut self, name: &str, addr: SocketAddr) {
+        if self
+            .pending
+            .get(name)
+            .is_some_and(|client| client.addr == addr)
+        {
+            self.pending.remove(name);
+        }
+    }
+
+    pub fn pending(&self) -> Vec<(String, SocketAddr)> {
+        self.pending
+            .iter()
+            .map(|(name, client)| (name.clone(), client.addr))
+            .collect()
+    }
+}
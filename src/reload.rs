@@ -0,0 +1,131 @@
+use crate::config::{Config, config_path};
+use crate::events::ServerEvent;
+use crate::topology::SharedTopology;
+use anyhow::Result;
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, sleep};
+
+const POLL_INTERVAL_MS: u64 = 1000;
+
+/// Parse the config file and swap it into the running topology.
+///
+/// On error the previous config stays in effect. Client sessions are untouched since
+/// only the routing layout changes.
+pub fn reload_config(topology: &SharedTopology) -> Result<()> {
+    let path = config_path();
+    let config = Config::load_from(&path)?;
+
+    let mut topo = topology.lock().unwrap();
+    if topo.get_config().port != config.port {
+        println!(
+            "Warning: port changed from {} to {}; restart the server to apply it",
+            topo.get_config().port,
+            config.port
+        );
+    }
+    topo.update_config(config);
+    topo.publish(ServerEvent::ConfigReloaded);
+    println!("Config reloaded from {:?}", path);
+    Ok(())
+}
+
+/// `reload_config` on the blocking pool, since loading reads and may rewrite the file
+pub async fn reload_in_background(topology: SharedTopology) -> Result<()> {
+    tokio::task::spawn_blocking(move || reload_config(&topology)).await?
+}
+
+/// Load the config at startup, selecting a profile that matches the detected monitors.
+///
+/// Reloads keep the file's `active_profile` so manual switches aren't overridden.
+pub fn load_server_config() -> Result<Config> {
+    let mut config = Config::load()?;
+
+    if config.profiles.iter().any(|p| p.match_monitors)
+        && let Some(name) = config.select_profile_for_monitors(&crate::monitor::detect_monitors())
+    {
+        println!("Selected profile '{}' for the detected monitors", name);
+    }
+
+    Ok(config)
+}
+
+/// Record `profile` as the active profile in the config file at `path`.
+///
+/// Only `active_profile` changes: the rest comes from the file, so edits that
+/// haven't been reloaded yet are kept rather than overwritten.
+pub fn persist_active_profile(path: &Path, profile: &str) -> Result<()> {
+    let mut config = Config::load_from(path)?;
+    if config.active_profile == profile {
+        return Ok(());
+    }
+    config.set_active_profile(profile)?;
+    config.save_to(path)
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Watch the config file and reload it whenever its modification time changes.
+///
+/// Profile switches are written back to the file from here.
+pub async fn watch_config(topology: SharedTopology) {
+    let path = config_path();
+    let mut last_modified = modified_time(&path);
+    let mut events = topology.lock().unwrap().subscribe();
+
+    loop {
+        tokio::select! {
+            _ = sleep(Duration::from_millis(POLL_INTERVAL_MS)) => {}
+            event = events.recv() => {
+                if let Err(broadcast::error::RecvError::Closed) = event {
+                    break;
+                }
+                if let Ok(ServerEvent::ProfileChanged { profile }) = event {
+                    // Our own write isn't a reload, unless the file had unreloaded
+                    // edits too
+                    let unchanged = modified_time(&path) == last_modified;
+                    let save_path = path.clone();
+                    let saved = tokio::task::spawn_blocking(move || {
+                        persist_active_profile(&save_path, &profile)
+                    })
+                    .await;
+                    match saved {
+                        Ok(Ok(())) if unchanged => last_modified = modified_time(&path),
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => eprintln!("Failed to persist active profile: {:#}", e),
+                        Err(e) => eprintln!("Failed to persist active profile: {}", e),
+                    }
+                }
+                continue;
+            }
+        }
+
+        let modified = modified_time(&path);
+        if modified.is_none() || modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        if let Err(e) = reload_in_background(topology.clone()).await {
+            eprintln!("Config reload rejected, keeping previous config: {:#}", e);
+        }
+    }
+}
+
+/// Reload the config whenever the process receives SIGHUP
+#[cfg(unix)]
+pub async fn reload_on_sighup(topology: SharedTopology) -> Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        println!("SIGHUP received, reloading config");
+        if let Err(e) = reload_in_background(topology.clone()).await {
+            eprintln!("Config reload rejected, keeping previous config: {:#}", e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,263 @@
+//! Physical key codes.
+//!
+//! Keys travel between machines as USB HID usages, which name a physical key
+//! position independently of OS and keyboard layout. Usages are "extended"
+//! (`page << 16 | id`) so keys from the keyboard page (0x07), consumer page (0x0C,
+//! media and browser keys) and generic desktop page (0x01, system sleep) can share
+//! one field. This module maps them to and from the codes each platform uses:
+//! Linux evdev codes, X11 keycodes and Windows set-1 scancodes (`0xE0xx` for
+//! extended keys).
+
+use crate::event::Key;
+
+/// HID usage value meaning "no physical key known"
+pub const NO_USAGE: u32 = 0;
+
+pub const PAGE_GENERIC_DESKTOP: u32 = 0x01;
+pub const PAGE_KEYBOARD: u32 = 0x07;
+pub const PAGE_CONSUMER: u32 = 0x0C;
+
+/// X11 keycodes are evdev codes shifted by 8 under the evdev/libinput drivers
+const X11_EVDEV_OFFSET: u32 = 8;
+
+/// One physical key and its code on every platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicalKey {
+    pub usage: u32,
+    pub evdev: u16,
+    /// Windows has no scancode for some consumer keys
+    pub scancode: Option<u16>,
+    pub key: Key,
+}
+
+const fn keyboard(id: u32, evdev: u16, scancode: Option<u16>, key: Key) -> PhysicalKey {
+    PhysicalKey {
+        usage: PAGE_KEYBOARD << 16 | id,
+        evdev,
+        scancode,
+        key,
+    }
+}
+
+const fn consumer(id: u32, evdev: u16, scancode: Option<u16>, key: Key) -> PhysicalKey {
+    PhysicalKey {
+        usage: PAGE_CONSUMER << 16 | id,
+        evdev,
+        scancode,
+        key,
+    }
+}
+
+const fn desktop(id: u32, evdev: u16, scancode: Option<u16>, key: Key) -> PhysicalKey {
+    PhysicalKey {
+        usage: PAGE_GENERIC_DESKTOP << 16 | id,
+        evdev,
+        scancode,
+        key,
+    }
+}
+
+/// Every physical key we can translate, ordered by HID page and usage
+pub const PHYSICAL_KEYS: &[PhysicalKey] = &[
+    keyboard(0x04, 30, Some(0x1E), Key::KeyA),
+    keyboard(0x05, 48, Some(0x30), Key::KeyB),
+    keyboard(0x06, 46, Some(0x2E), Key::KeyC),
+    keyboard(0x07, 32, Some(0x20), Key::KeyD),
+    keyboard(0x08, 18, Some(0x12), Key::KeyE),
+    keyboard(0x09, 33, Some(0x21), Key::KeyF),
+    keyboard(0x0A, 34, Some(0x22), Key::KeyG),
+    keyboard(0x0B, 35, Some(0x23), Key::KeyH),
+    keyboard(0x0C, 23, Some(0x17), Key::KeyI),
+    keyboard(0x0D, 36, Some(0x24), Key::KeyJ),
+    keyboard(0x0E, 37, Some(0x25), Key::KeyK),
+    keyboard(0x0F, 38, Some(0x26), Key::KeyL),
+    keyboard(0x10, 50, Some(0x32), Key::KeyM),
+    keyboard(0x11, 49, Some(0x31), Key::KeyN),
+    keyboard(0x12, 24, Some(0x18), Key::KeyO),
+    keyboard(0x13, 25, Some(0x19), Key::KeyP),
+    keyboard(0x14, 16, Some(0x10), Key::KeyQ),
+    keyboard(0x15, 19, Some(0x13), Key::KeyR),
+    keyboard(0x16, 31, Some(0x1F), Key::KeyS),
+    keyboard(0x17, 20, Some(0x14), Key::KeyT),
+    keyboard(0x18, 22, Some(0x16), Key::KeyU),
+    keyboard(0x19, 47, Some(0x2F), Key::KeyV),
+    keyboard(0x1A, 17, Some(0x11), Key::KeyW),
+    keyboard(0x1B, 45, Some(0x2D), Key::KeyX),
+    keyboard(0x1C, 21, Some(0x15), Key::KeyY),
+    keyboard(0x1D, 44, Some(0x2C), Key::KeyZ),
+    keyboard(0x1E, 2, Some(0x02), Key::Num1),
+    keyboard(0x1F, 3, Some(0x03), Key::Num2),
+    keyboard(0x20, 4, Some(0x04), Key::Num3),
+    keyboard(0x21, 5, Some(0x05), Key::Num4),
+    keyboard(0x22, 6, Some(0x06), Key::Num5),
+    keyboard(0x23, 7, Some(0x07), Key::Num6),
+    keyboard(0x24, 8, Some(0x08), Key::Num7),
+    keyboard(0x25, 9, Some(0x09), Key::Num8),
+    keyboard(0x26, 10, Some(0x0A), Key::Num9),
+    keyboard(0x27, 11, Some(0x0B), Key::Num0),
+    keyboard(0x28, 28, Some(0x1C), Key::Return),
+    keyboard(0x29, 1, Some(0x01), Key::Escape),
+    keyboard(0x2A, 14, Some(0x0E), Key::Backspace),
+    keyboard(0x2B, 15, Some(0x0F), Key::Tab),
+    keyboard(0x2C, 57, Some(0x39), Key::Space),
+    keyboard(0x2D, 12, Some(0x0C), Key::Minus),
+    keyboard(0x2E, 13, Some(0x0D), Key::Equal),
+    keyboard(0x2F, 26, Some(0x1A), Key::LeftBracket),
+    keyboard(0x30, 27, Some(0x1B), Key::RightBracket),
+    keyboard(0x31, 43, Some(0x2B), Key::BackSlash),
+    keyboard(0x33, 39, Some(0x27), Key::SemiColon),
+    keyboard(0x34, 40, Some(0x28), Key::Quote),
+    keyboard(0x35, 41, Some(0x29), Key::BackQuote),
+    keyboard(0x36, 51, Some(0x33), Key::Comma),
+    keyboard(0x37, 52, Some(0x34), Key::Dot),
+    keyboard(0x38, 53, Some(0x35), Key::Slash),
+    keyboard(0x39, 58, Some(0x3A), Key::CapsLock),
+    keyboard(0x3A, 59, Some(0x3B), Key::F1),
+    keyboard(0x3B, 60, Some(0x3C), Key::F2),
+    keyboard(0x3C, 61, Some(0x3D), Key::F3),
+    keyboard(0x3D, 62, Some(0x3E), Key::F4),
+    keyboard(0x3E, 63, Some(0x3F), Key::F5),
+    keyboard(0x3F, 64, Some(0x40), Key::F6),
+    keyboard(0x40, 65, Some(0x41), Key::F7),
+    keyboard(0x41, 66, Some(0x42), Key::F8),
+    keyboard(0x42, 67, Some(0x43), Key::F9),
+    keyboard(0x43, 68, Some(0x44), Key::F10),
+    keyboard(0x44, 87, Some(0x57), Key::F11),
+    keyboard(0x45, 88, Some(0x58), Key::F12),
+    keyboard(0x46, 99, Some(0xE037), Key::PrintScreen),
+    keyboard(0x47, 70, Some(0x46), Key::ScrollLock),
+    keyboard(0x48, 119, Some(0x45), Key::Pause),
+    keyboard(0x49, 110, Some(0xE052), Key::Insert),
+    keyboard(0x4A, 102, Some(0xE047), Key::Home),
+    keyboard(0x4B, 104, Some(0xE049), Key::PageUp),
+    keyboard(0x4C, 111, Some(0xE053), Key::Delete),
+    keyboard(0x4D, 107, Some(0xE04F), Key::End),
+    keyboard(0x4E, 109, Some(0xE051), Key::PageDown),
+    keyboard(0x4F, 106, Some(0xE04D), Key::RightArrow),
+    keyboard(0x50, 105, Some(0xE04B), Key::LeftArrow),
+    keyboard(0x51, 108, Some(0xE050), Key::DownArrow),
+    keyboard(0x52, 103, Some(0xE048), Key::UpArrow),
+    keyboard(0x53, 69, Some(0xE045), Key::NumLock),
+    keyboard(0x54, 98, Some(0xE035), Key::KpDivide),
+    keyboard(0x55, 55, Some(0x37), Key::KpMultiply),
+    keyboard(0x56, 74, Some(0x4A), Key::KpMinus),
+    keyboard(0x57, 78, Some(0x4E), Key::KpPlus),
+    keyboard(0x58, 96, Some(0xE01C), Key::KpReturn),
+    keyboard(0x59, 79, Some(0x4F), Key::Kp1),
+    keyboard(0x5A, 80, Some(0x50), Key::Kp2),
+    keyboard(0x5B, 81, Some(0x51), Key::Kp3),
+    keyboard(0x5C, 75, Some(0x4B), Key::Kp4),
+    keyboard(0x5D, 76, Some(0x4C), Key::Kp5),
+    keyboard(0x5E, 77, Some(0x4D), Key::Kp6),
+    keyboard(0x5F, 71, Some(0x47), Key::Kp7),
+    keyboard(0x60, 72, Some(0x48), Key::Kp8),
+    keyboard(0x61, 73, Some(0x49), Key::Kp9),
+    keyboard(0x62, 82, Some(0x52), Key::Kp0),
+    keyboard(0x63, 83, Some(0x53), Key::KpDelete),
+    keyboard(0x64, 86, Some(0x56), Key::IntlBackslash),
+    keyboard(0x65, 127, Some(0xE05D), Key::ContextMenu),
+    keyboard(0x66, 116, Some(0xE05E), Key::Power),
+    keyboard(0x67, 117, Some(0x59), Key::KpEqual),
+    keyboard(0x68, 183, Some(0x64), Key::F13),
+    keyboard(0x69, 184, Some(0x65), Key::F14),
+    keyboard(0x6A, 185, Some(0x66), Key::F15),
+    keyboard(0x6B, 186, Some(0x67), Key::F16),
+    keyboard(0x6C, 187, Some(0x68), Key::F17),
+    keyboard(0x6D, 188, Some(0x69), Key::F18),
+    keyboard(0x6E, 189, Some(0x6A), Key::F19),
+    keyboard(0x6F, 190, Some(0x6B), Key::F20),
+    keyboard(0x70, 191, Some(0x6C), Key::F21),
+    keyboard(0x71, 192, Some(0x6D), Key::F22),
+    keyboard(0x72, 193, Some(0x6E), Key::F23),
+    keyboard(0x73, 194, Some(0x76), Key::F24),
+    keyboard(0x75, 138, Some(0xE03B), Key::Help),
+    keyboard(0x79, 129, None, Key::Again),
+    keyboard(0x7A, 131, Some(0xE008), Key::Undo),
+    keyboard(0x7B, 137, Some(0xE017), Key::Cut),
+    keyboard(0x7C, 133, Some(0xE018), Key::Copy),
+    keyboard(0x7D, 135, Some(0xE00A), Key::Paste),
+    keyboard(0x7E, 136, None, Key::Find),
+    keyboard(0x85, 121, Some(0x7E), Key::KpComma),
+    keyboard(0x87, 89, Some(0x73), Key::IntlRo),
+    keyboard(0x88, 93, Some(0x70), Key::KanaMode),
+    keyboard(0x89, 124, Some(0x7D), Key::IntlYen),
+    keyboard(0x8A, 92, Some(0x79), Key::Convert),
+    keyboard(0x8B, 94, Some(0x7B), Key::NonConvert),
+    keyboard(0x90, 122, Some(0x72), Key::Hangul),
+    keyboard(0x91, 123, Some(0x71), Key::Hanja),
+    keyboard(0x92, 90, Some(0x78), Key::Katakana),
+    keyboard(0x93, 91, Some(0x77), Key::Hiragana),
+    keyboard(0x94, 85, None, Key::ZenkakuHankaku),
+    keyboard(0xE0, 29, Some(0x1D), Key::ControlLeft),
+    keyboard(0xE1, 42, Some(0x2A), Key::ShiftLeft),
+    keyboard(0xE2, 56, Some(0x38), Key::Alt),
+    keyboard(0xE3, 125, Some(0xE05B), Key::MetaLeft),
+    keyboard(0xE4, 97, Some(0xE01D), Key::ControlRight),
+    keyboard(0xE5, 54, Some(0x36), Key::ShiftRight),
+    keyboard(0xE6, 100, Some(0xE038), Key::AltGr),
+    keyboard(0xE7, 126, Some(0xE05C), Key::MetaRight),
+    desktop(0x82, 142, Some(0xE05F), Key::Sleep),
+    consumer(0x6F, 225, None, Key::BrightnessUp),
+    consumer(0x70, 224, None, Key::BrightnessDown),
+    consumer(0xB5, 163, Some(0xE019), Key::MediaNextTrack),
+    consumer(0xB6, 165, Some(0xE010), Key::MediaPrevTrack),
+    consumer(0xB7, 166, Some(0xE024), Key::MediaStop),
+    consumer(0xB8, 161, None, Key::Eject),
+    consumer(0xCD, 164, Some(0xE022), Key::MediaPlayPause),
+    consumer(0xE2, 113, Some(0xE020), Key::VolumeMute),
+    consumer(0xE9, 115, Some(0xE030), Key::VolumeUp),
+    consumer(0xEA, 114, Some(0xE02E), Key::VolumeDown),
+    consumer(0x183, 171, Some(0xE06D), Key::LaunchMediaSelect),
+    consumer(0x18A, 155, Some(0xE06C), Key::LaunchMail),
+    consumer(0x192, 140, Some(0xE021), Key::LaunchApp2),
+    consumer(0x194, 157, Some(0xE06B), Key::LaunchApp1),
+    consumer(0x221, 217, Some(0xE065), Key::BrowserSearch),
+    consumer(0x223, 172, Some(0xE032), Key::BrowserHome),
+    consumer(0x224, 158, Some(0xE06A), Key::BrowserBack),
+    consumer(0x225, 159, Some(0xE069), Key::BrowserForward),
+    consumer(0x226, 128, Some(0xE068), Key::BrowserStop),
+    consumer(0x227, 173, Some(0xE067), Key::BrowserRefresh),
+    consumer(0x22A, 156, Some(0xE066), Key::BrowserFavorites),
+];
+
+fn find(predicate: impl Fn(&PhysicalKey) -> bool) -> Option<&'static PhysicalKey> {
+    PHYSICAL_KEYS.iter().find(|k| predicate(k))
+}
+
+pub fn physical_key(usage: u32) -> Option<&'static PhysicalKey> {
+    find(|k| k.usage == usage)
+}
+
+/// HID usage of a logical key, or `NO_USAGE` if it has no fixed position
+pub fn usage_for_key(key: Key) -> u32 {
+    find(|k| k.key == key).map(|k| k.usage).unwrap_or(NO_USAGE)
+}
+
+pub fn key_for_usage(usage: u32) -> Option<Key> {
+    physical_key(usage).map(|k| k.key)
+}
+
+pub fn usage_from_evdev(code: u16) -> Option<u32> {
+    find(|k| k.evdev == code).map(|k| k.usage)
+}
+
+pub fn evdev_from_usage(usage: u32) -> Option<u16> {
+    physical_key(usage).map(|k| k.evdev)
+}
+
+pub fn usage_from_x11(keycode: u32) -> Option<u32> {
+    let evdev = keycode.checked_sub(X11_EVDEV_OFFSET)?;
+    usage_from_evdev(u16::try_from(evdev).ok()?)
+}
+
+pub fn x11_from_usage(usage: u32) -> Option<u32> {
+    evdev_from_usage(usage).map(|code| code as u32 + X11_EVDEV_OFFSET)
+}
+
+pub fn usage_from_scancode(scancode: u16) -> Option<u32> {
+    find(|k| k.scancode == Some(scancode)).map(|k| k.usage)
+}
+
+pub fn scancode_from_usage(usage: u32) -> Option<u16> {
+    physical_key(usage).and_then(|k| k.scancode)
+}
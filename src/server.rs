@@ -1,163 +1,53 @@
-use crate::config::Config;
-use crate::event::KvmEvent;
+use crate::event::ClientEvent;
+use crate::events::ServerEvent;
+use crate::input::router::InputRouter;
+use crate::input::text::create_text_resolver;
+use crate::input::{InputBackend, create_capture, create_cursor_warp};
 use crate::net::{KvmStream, PROTOCOL_VERSION, Packet};
-use crate::topology::{Focus, Topology};
+use crate::session::SessionChange;
+use crate::topology::{Focus, SharedTopology, create_shared_topology};
 use anyhow::Result;
-use dirs;
-use rdev::{Event, grab};
-use serde_json;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use tokio::net::TcpListener;
-
-pub async fn run(port: u16) -> Result<()> {
-    run_with_state(port, crate::connected::create_connected_clients()).await
+use tokio::sync::{Notify, broadcast};
+use tokio::time::Duration;
+
+/// How long an unknown client waits for approval when pairing is required
+const PAIRING_TIMEOUT_SECS: u64 = 120;
+
+pub async fn run(port: u16, backend: InputBackend) -> Result<()> {
+    let connected_clients = crate::connected::create_connected_clients();
+    let topology = create_shared_topology(
+        crate::reload::load_server_config()?,
+        connected_clients.clone(),
+    );
+    run_with_state(port, backend, connected_clients, topology).await
 }
 
 pub async fn run_with_state(
     port: u16,
+    backend: InputBackend,
     connected_clients: crate::connected::ConnectedClients,
+    topology: SharedTopology,
 ) -> Result<()> {
-    // Load config from file
-    let config_path = dirs::config_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("aurora_kvm")
-        .join("config.json");
-
-    let config = if config_path.exists() {
-        std::fs::read_to_string(&config_path)
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_else(|| {
-                println!("Warning: Failed to parse config, using default");
-                Config::default()
-            })
-    } else {
-        println!(
-            "Warning: No config file found at {:?}, using default",
-            config_path
-        );
-        Config::default()
-    };
-
-    let topology = Arc::new(Mutex::new(Topology::new(config, connected_clients.clone())));
-
-    // Channel for broadcasting events to clients
-    let (tx, _rx) = tokio::sync::broadcast::channel::<KvmEvent>(100);
+    // Channel for broadcasting events to clients; each connection picks out its own
+    let (tx, _rx) = tokio::sync::broadcast::channel::<ClientEvent>(100);
 
     // Start Input Capture Thread
-    let topology_clone = topology.clone();
-    let tx_clone = tx.clone();
-
-    // Virtual cursor state
-    let virtual_cursor = Arc::new(Mutex::new((0.0, 0.0)));
-    let virtual_cursor_clone = virtual_cursor.clone();
+    let capture = create_capture(backend)?;
+    println!("Capturing input with the {:?} backend", backend);
+    let mut router = InputRouter::new(topology.clone(), tx.clone());
+    if let Some(warp) = create_cursor_warp(backend) {
+        router.set_cursor_warp(warp);
+    }
+    router.set_local_scale(crate::monitor::detect_scale_factor());
+    match create_text_resolver() {
+        Some(resolver) => router.set_text_resolver(resolver),
+        None => println!("Keyboard layout unavailable, text mode clients will get keys"),
+    }
 
     std::thread::spawn(move || {
-        use rdev::{Event, EventType, grab};
-
-        if let Err(error) = grab(move |event: Event| -> Option<Event> {
-            let mut topo = topology_clone.lock().unwrap();
-            let mut v_cursor = virtual_cursor_clone.lock().unwrap();
-
-            match topo.get_focus() {
-                Focus::Local => {
-                    // Pass through events to local OS
-                    // Check for edge switching
-                    if let EventType::MouseMove { x, y } = event.event_type {
-                        // Update virtual cursor to match real cursor while local
-                        *v_cursor = (x, y);
-
-                        if let Some(new_focus) = topo.check_edge(x, y) {
-                            println!("Switching focus to {:?}", new_focus);
-                            topo.set_focus(new_focus);
-                            // When switching to client, we might want to center the mouse
-                            // or just leave it at the edge. Leaving it is safer for now.
-                        }
-                    }
-                    Some(event)
-                }
-                Focus::Client(_) => {
-                    // Swallow events (return None) so local OS doesn't see them
-
-                    // Update virtual cursor for MouseMove
-                    if let EventType::MouseMove { x, y } = event.event_type {
-                        // In grab mode, (x,y) are still absolute screen coordinates.
-                        // But since we swallow events, the OS cursor won't move.
-                        // Wait, if we swallow, the OS cursor STAYS put.
-                        // So subsequent MouseMove events might report the SAME (x,y) or
-                        // relative deltas depending on OS.
-                        // rdev usually reports absolute. If OS cursor is frozen, x/y might be static.
-                        // Actually, rdev on Linux often uses XInput2 or similar.
-                        // If we swallow, X11 might not move the cursor.
-                        // We need relative movement.
-                        // rdev doesn't give relative movement easily in MouseMove.
-                        // BUT, for now let's assume we can just track the "would be" position
-                        // if we were letting it move, OR we rely on the fact that we need to
-                        // calculate deltas if the OS cursor is locked.
-
-                        // simpler approach for first iteration:
-                        // We can't easily get deltas from absolute rdev events if the cursor is frozen.
-                        // However, we can try to let the cursor move BUT confine it?
-                        // No, user wants "fully detach".
-
-                        // Let's try to infer delta from the event if possible,
-                        // OR just use the event's x/y if rdev reports raw input before OS clamping.
-                        // On Linux `grab` usually hooks deep.
-
-                        // Let's assume for a moment we just forward the event to the client
-                        // and swallow it locally.
-                        // But we need to check if we come back to local.
-
-                        // We need to track the virtual position manually.
-                        // If rdev gives us the new position even if we return None, we are good.
-                        // If rdev gives us the OLD position because we returned None previously, we are stuck.
-
-                        // EXPERIMENTAL: Let's assume rdev `grab` on Linux sees the physical move
-                        // even if we block propagation.
-                        *v_cursor = (x, y);
-
-                        // Check if we returned to local
-                        // We use the virtual coordinates for this check
-                        // But wait, if we are "Client", we want to check if we hit the "return" edge.
-                        // The `check_edge` function currently only checks if we are inside local.
-                        // We need a reverse check.
-
-                        // Actually, `check_edge` in Topology handles "if not inside local -> find client".
-                        // We need "if inside local -> switch to local".
-
-                        // Let's do a manual check here for return
-                        let (vx, vy) = *v_cursor;
-
-                        // Check if we are back inside any local screen
-                        let mut inside_local = false;
-                        for screen in &topo.get_config().local_screens {
-                            let sx = screen.x as f64;
-                            let sy = screen.y as f64;
-                            let sw = screen.width as f64;
-                            let sh = screen.height as f64;
-
-                            if vx >= sx && vx < sx + sw && vy >= sy && vy < sy + sh {
-                                inside_local = true;
-                                break;
-                            }
-                        }
-
-                        if inside_local {
-                            println!("Returning focus to Local");
-                            topo.set_focus(Focus::Local);
-                            // We don't swallow this event so the cursor actually moves back in
-                            return Some(event);
-                        }
-                    }
-
-                    // Forward to clients
-                    let kvm_event = KvmEvent::from(event.event_type);
-                    let _ = tx_clone.send(kvm_event);
-
-                    None // Swallow event
-                }
-            }
-        }) {
+        if let Err(error) = capture.run(Box::new(move |event| router.handle(event))) {
             println!("Error: {:?}", error);
         }
     });
@@ -165,6 +55,19 @@ pub async fn run_with_state(
     let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
     println!("Server listening on port {}", port);
 
+    // Hot-reload config.json into the running topology
+    tokio::spawn(crate::reload::watch_config(topology.clone()));
+
+    #[cfg(unix)]
+    {
+        let topology_sighup = topology.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::reload::reload_on_sighup(topology_sighup).await {
+                eprintln!("SIGHUP handler error: {}", e);
+            }
+        });
+    }
+
     // Start service discovery broadcast
     let discovery_port = port;
     tokio::spawn(async move {
@@ -179,19 +82,50 @@ pub async fn run_with_state(
         }
     });
 
+    // Local control API
+    let pairing = crate::pairing::create_pairing();
+    let control_state = crate::control::ControlState {
+        port,
+        topology: topology.clone(),
+        connected_clients: connected_clients.clone(),
+        pairing: pairing.clone(),
+        tx: tx.clone(),
+    };
+    tokio::spawn(async move {
+        if let Err(e) = crate::control::serve(control_state).await {
+            eprintln!("Control API error: {:#}", e);
+        }
+    });
+
+    // Screen lock and screensaver sync; client changes arrive on `session_tx`
+    // and go out to the connections on `session_out`
+    let (session_tx, session_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (session_out, _) = broadcast::channel::<SessionChange>(16);
+    if let Some(backend) = crate::session::watch_local(session_tx.clone()).await {
+        tokio::spawn(crate::session::run_server(
+            topology.clone(),
+            backend,
+            session_rx,
+            session_out.clone(),
+        ));
+    }
+
     loop {
         let (stream, addr) = listener.accept().await?;
         println!("Client connected: {}", addr);
         let mut rx = tx.subscribe();
-        let _topology_client = topology.clone();
+        let topology_client = topology.clone();
         let connected_clients_clone = connected_clients.clone();
+        let pairing_client = pairing.clone();
+        let session_tx = session_tx.clone();
+        let mut session_changes = session_out.subscribe();
 
         tokio::spawn(async move {
             let kvm_stream = KvmStream::new(stream);
             let (mut reader, mut writer) = kvm_stream.split();
 
             // Handshake
-            match reader.receive().await {
+            let screen_info = match reader.receive().await {
                 Ok(Packet::Handshake {
                     version,
                     screen_info,
@@ -201,23 +135,7 @@ pub async fn run_with_state(
                         println!("Client {} version mismatch: {}", addr, version);
                         return;
                     }
-
-                    // Register connected client
-                    if let Some(info) = screen_info {
-                        connected_clients_clone.lock().unwrap().insert(
-                            addr,
-                            crate::connected::ConnectedClient {
-                                addr,
-                                screen_info: info.clone(),
-                            },
-                        );
-                        println!(
-                            "Client {} connected: {} ({}x{})",
-                            addr, info.name, info.width, info.height
-                        );
-                    } else {
-                        println!("Client {} connected (no screen info)", addr);
-                    }
+                    screen_info
                 }
                 Ok(_) => {
                     println!("Client {} sent unexpected packet during handshake", addr);
@@ -227,23 +145,203 @@ pub async fn run_with_state(
                     println!("Client {} handshake error: {}", addr, e);
                     return;
                 }
+            };
+
+            let name = screen_info
+                .as_ref()
+                .map(|info| info.name.clone())
+                .unwrap_or_else(|| addr.to_string());
+            if !await_pairing(&topology_client, &pairing_client, &name, addr).await {
+                return;
+            }
+
+            // Register connected client
+            let kick = Arc::new(Notify::new());
+            topology_client
+                .lock()
+                .unwrap()
+                .publish(ServerEvent::ClientConnected {
+                    name: name.clone(),
+                    addr: addr.to_string(),
+                });
+            if let Some(info) = screen_info {
+                connected_clients_clone.lock().unwrap().insert(
+                    addr,
+                    crate::connected::ConnectedClient {
+                        addr,
+                        screen_info: info.clone(),
+                        kick: kick.clone(),
+                    },
+                );
+                println!(
+                    "Client {} connected: {} ({}x{})",
+                    addr, info.name, info.width, info.height
+                );
+            } else {
+                println!("Client {} connected (no screen info)", addr);
             }
 
+            // Read on a task of its own: a read cut short by select! would lose
+            // part of a frame
+            let (packets_tx, mut packets) = tokio::sync::mpsc::channel(16);
+            tokio::spawn(async move {
+                while let Ok(packet) = reader.receive().await {
+                    if packets_tx.send(packet).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let mut server_events = topology_client.lock().unwrap().subscribe();
+            let mut focused = false;
+
             loop {
-                match rx.recv().await {
-                    Ok(event) => {
-                        let packet = Packet::Event(event);
+                tokio::select! {
+                    _ = kick.notified() => {
+                        println!("Client {} ({}) kicked", addr, name);
+                        break;
+                    }
+                    packet = packets.recv() => match packet {
+                        Some(Packet::CursorPosition { x, y }) => {
+                            let mut topo = topology_client.lock().unwrap();
+                            if let Some((origin_x, origin_y, _, _)) = topo.client_bounds(&name) {
+                                topo.remember_cursor(&name, (origin_x + x, origin_y + y));
+                            }
+                        }
+                        Some(Packet::Session(event)) => {
+                            let _ = session_tx.send(SessionChange {
+                                from: Some(name.clone()),
+                                event,
+                            });
+                        }
+                        Some(_) => {}
+                        None => {
+                            println!("Client {} ({}) disconnected", addr, name);
+                            break;
+                        }
+                    },
+                    event = server_events.recv() => {
+                        let now_focused = match event {
+                            Ok(ServerEvent::FocusChanged { focus }) => focus == name,
+                            // The missed events may have moved focus to or from this client
+                            Err(broadcast::error::RecvError::Lagged(_)) => matches!(
+                                topology_client.lock().unwrap().get_focus(),
+                                Focus::Client(focus) if *focus == name
+                            ),
+                            Ok(_) => continue,
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        };
+                        let packet = match (focused, now_focused) {
+                            (false, true) => {
+                                let topo = topology_client.lock().unwrap();
+                                Packet::FocusGained {
+                                    entry_point: topo
+                                        .entry_point(&name)
+                                        .unwrap_or((0.0, 0.0)),
+                                    entry_edge: topo.entry_edge(&name),
+                                }
+                            }
+                            // The client replies with its cursor position
+                            (true, false) => Packet::FocusLost,
+                            _ => continue,
+                        };
+                        focused = !focused;
                         if let Err(e) = writer.send(&packet).await {
                             println!("Failed to send to client {}: {}", addr, e);
                             break;
                         }
                     }
-                    Err(e) => {
-                        println!("Broadcast error: {}", e);
-                        break;
-                    }
+                    change = session_changes.recv() => match change {
+                        Ok(SessionChange { from, event }) if from.as_ref() != Some(&name) => {
+                            let policy = topology_client.lock().unwrap().session_sync_for(&name);
+                            if event.shared_by(policy)
+                                && let Err(e) = writer.send(&Packet::Session(event)).await
+                            {
+                                println!("Failed to send to client {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        // This client's own change, or too many changes at once
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    result = rx.recv() => match result {
+                        Ok(ClientEvent { client, event }) if client == name => {
+                            let packet = Packet::Event(event);
+                            if let Err(e) = writer.send(&packet).await {
+                                println!("Failed to send to client {}: {}", addr, e);
+                                break;
+                            }
+                        }
+                        // Meant for another client
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            println!("Client {} fell behind and missed {} events", name, missed);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
                 }
             }
+
+            connected_clients_clone.lock().unwrap().remove(&addr);
+            let mut topo = topology_client.lock().unwrap();
+            topo.publish(ServerEvent::ClientDisconnected {
+                name: name.clone(),
+                addr: addr.to_string(),
+            });
+            if *topo.get_focus() == Focus::Client(name.clone()) {
+                println!("Focused client {} left, returning focus to Local", name);
+                topo.set_focus(Focus::Local);
+            }
         });
     }
 }
+
+/// Hold an unknown client until it is approved over the control API.
+///
+/// Returns false if the client was not approved in time.
+async fn await_pairing(
+    topology: &SharedTopology,
+    pairing: &crate::pairing::SharedPairing,
+    name: &str,
+    addr: std::net::SocketAddr,
+) -> bool {
+    {
+        let topo = topology.lock().unwrap();
+        let config = topo.get_config();
+        if !config.require_pairing || config.active().clients.iter().any(|c| c.name == name) {
+            return true;
+        }
+    }
+
+    let approved = {
+        let mut pairing = pairing.lock().unwrap();
+        if pairing.is_approved(name, addr) {
+            return true;
+        }
+        pairing.request(name, addr)
+    };
+    let Some(approved) = approved else {
+        println!(
+            "Client {} ({}) rejected: another client of that name is waiting for approval",
+            addr, name
+        );
+        return false;
+    };
+
+    println!(
+        "Client {} ({}) is waiting for approval: aurora_ctl approve {}",
+        addr, name, name
+    );
+    match tokio::time::timeout(Duration::from_secs(PAIRING_TIMEOUT_SECS), approved).await {
+        Ok(Ok(())) => {
+            println!("Client {} ({}) approved", addr, name);
+            true
+        }
+        _ => {
+            println!("Client {} ({}) was not approved, disconnecting", addr, name);
+            pairing.lock().unwrap().cancel(name, addr);
+            false
+        }
+    }
+}
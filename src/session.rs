@@ -0,0 +1,343 @@
+//! Screen lock and screensaver sync between the server and its clients.
+//!
+//! Each machine watches its own session through a `SessionBackend` and reports
+//! changes as `SessionEvent`s. The server applies the changes its clients send
+//! and passes every change on to the clients whose `session_sync` policy covers
+//! it. A `SessionMirror` on each machine keeps a mirrored change from coming
+//! back as a new one. Unlocking is never mirrored, so every machine still asks
+//! for its own password.
+
+use crate::config::SessionSync;
+use crate::topology::SharedTopology;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+
+/// A lock or screensaver change on one machine
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Locked,
+    Unlocked,
+    ScreensaverStarted,
+    ScreensaverStopped,
+}
+
+impl SessionEvent {
+    /// Whether a client with `policy` shares this kind of change with the server
+    pub fn shared_by(self, policy: SessionSync) -> bool {
+        match policy {
+            SessionSync::Ignore => false,
+            SessionSync::MirrorScreensaver => matches!(
+                self,
+                SessionEvent::ScreensaverStarted | SessionEvent::ScreensaverStopped
+            ),
+            SessionSync::MirrorLock => true,
+        }
+    }
+}
+
+/// Called by a session backend for every change on this machine
+pub type SessionHandler = Arc<dyn Fn(SessionEvent) + Send + Sync>;
+
+/// Watches and changes the lock and screensaver state of this machine
+pub trait SessionBackend: Send {
+    /// Start reporting changes to `handler`. Returns once watching has started.
+    fn watch(&mut self, handler: SessionHandler) -> Result<()>;
+
+    /// Lock the screen or start or stop the screensaver, for a change mirrored
+    /// from another machine
+    fn apply(&mut self, event: SessionEvent) -> Result<()>;
+}
+
+/// The lock and screensaver state of one machine
+#[derive(Debug, Default)]
+pub struct SessionMirror {
+    locked: bool,
+    screensaver: bool,
+}
+
+impl SessionMirror {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A change seen on this machine. Returns whether it is news to pass on,
+    /// rather than the echo of a change mirrored here.
+    pub fn observed(&mut self, event: SessionEvent) -> bool {
+        let (state, value) = match event {
+            SessionEvent::Locked => (&mut self.locked, true),
+            SessionEvent::Unlocked => (&mut self.locked, false),
+            SessionEvent::ScreensaverStarted => (&mut self.screensaver, true),
+            SessionEvent::ScreensaverStopped => (&mut self.screensaver, false),
+        };
+        std::mem::replace(state, value) != value
+    }
+
+    /// A change from another machine. Returns whether to apply it here.
+    pub fn mirrored(&mut self, event: SessionEvent) -> bool {
+        event != SessionEvent::Unlocked && self.observed(event)
+    }
+}
+
+/// A session change and the client it came from; `None` for the server itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionChange {
+    pub from: Option<String>,
+    pub event: SessionEvent,
+}
+
+/// Run the server side of the sync until `changes` closes.
+///
+/// `changes` carries the server's own changes and those its clients send.
+/// Client changes their policy allows are applied to the server, and every
+/// change worth passing on goes out on `out` for the connections to forward.
+pub async fn run_server(
+    topology: SharedTopology,
+    backend: Box<dyn SessionBackend>,
+    mut changes: mpsc::UnboundedReceiver<SessionChange>,
+    out: broadcast::Sender<SessionChange>,
+) {
+    let apply = apply_in_background(backend);
+    let mut mirror = SessionMirror::new();
+    while let Some(change) = changes.recv().await {
+        let news = match &change.from {
+            None => mirror.observed(change.event),
+            Some(client) => {
+                let policy = topology.lock().unwrap().session_sync_for(client);
+                if !change.event.shared_by(policy) || !mirror.mirrored(change.event) {
+                    continue;
+                }
+                println!("{:?} on {}, mirroring here", change.event, client);
+                let _ = apply.send(change.event);
+                true
+            }
+        };
+        if news {
+            // No connections is fine
+            let _ = out.send(change);
+        }
+    }
+}
+
+/// Apply the changes sent on the returned channel with `backend`, on a thread of
+/// its own since backends block on D-Bus. The thread ends with the channel.
+pub fn apply_in_background(
+    mut backend: Box<dyn SessionBackend>,
+) -> mpsc::UnboundedSender<SessionEvent> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while let Some(event) = rx.blocking_recv() {
+            if let Err(e) = backend.apply(event) {
+                println!("Session sync failed: {:#}", e);
+            }
+        }
+    });
+    tx
+}
+
+/// This machine's session backend, already watching into `tx`. Connecting
+/// blocks, so it happens off the async runtime.
+pub async fn watch_local(
+    tx: mpsc::UnboundedSender<SessionChange>,
+) -> Option<Box<dyn SessionBackend>> {
+    tokio::task::spawn_blocking(move || {
+        let mut backend = create_backend()?;
+        match watch_into(backend.as_mut(), tx) {
+            Ok(()) => Some(backend),
+            Err(e) => {
+                println!("Screen lock sync unavailable: {:#}", e);
+                None
+            }
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Route a backend's changes into a channel, for `run_server` or the client loop
+pub fn watch_into(
+    backend: &mut dyn SessionBackend,
+    tx: mpsc::UnboundedSender<SessionChange>,
+) -> Result<()> {
+    backend.watch(Arc::new(move |event| {
+        let _ = tx.send(SessionChange { from: None, event });
+    }))
+}
+
+/// The session backend for this platform, or `None` where there is none
+pub fn create_backend() -> Option<Box<dyn SessionBackend>> {
+    #[cfg(target_os = "linux")]
+    match logind::LogindSession::new() {
+        Ok(session) => return Some(Box::new(session)),
+        Err(e) => println!("Screen lock sync unavailable: {:#}", e),
+    }
+    None
+}
+
+/// Records mirrored changes instead of touching the session, and reports
+/// changes scripted with `emit`
+#[derive(Default, Clone)]
+pub struct MockSession {
+    handler: Arc<Mutex<Option<SessionHandler>>>,
+    applied: Arc<Mutex<Vec<SessionEvent>>>,
+    /// Report applied changes back like a real session would
+    echo: bool,
+}
+
+impl MockSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A mock that reports every change it applies, as the OS does
+    pub fn echoing() -> Self {
+        Self {
+            echo: true,
+            ..Self::default()
+        }
+    }
+
+    /// Pretend the user changed this machine's session
+    pub fn emit(&self, event: SessionEvent) {
+        if let Some(handler) = self.handler.lock().unwrap().as_ref() {
+            handler(event);
+        }
+    }
+
+    pub fn applied(&self) -> Vec<SessionEvent> {
+        self.applied.lock().unwrap().clone()
+    }
+}
+
+impl SessionBackend for MockSession {
+    fn watch(&mut self, handler: SessionHandler) -> Result<()> {
+        *self.handler.lock().unwrap() = Some(handler);
+        Ok(())
+    }
+
+    fn apply(&mut self, event: SessionEvent) -> Result<()> {
+        // Echo first, so a caller that sees the change applied knows the
+        // echo is already on its way
+        if self.echo {
+            self.emit(event);
+        }
+        self.applied.lock().unwrap().push(event);
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod logind {
+    use super::{SessionBackend, SessionEvent, SessionHandler};
+    use anyhow::{Context, Result, bail};
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    const LOGIND: &str = "org.freedesktop.login1";
+    const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+    const SCREENSAVER: &str = "org.freedesktop.ScreenSaver";
+
+    /// The logind session on the system bus, for locking, and the freedesktop
+    /// screensaver on the session bus when the desktop provides one
+    pub struct LogindSession {
+        session: Proxy<'static>,
+        screensaver: Option<Proxy<'static>>,
+    }
+
+    impl LogindSession {
+        pub fn new() -> Result<Self> {
+            let system = Connection::system().context("Cannot connect to the system D-Bus")?;
+
+            // "auto" is our own session, or the user's graphical one when we run
+            // outside of any. Signals come from its real object path.
+            let auto = Proxy::new(
+                &system,
+                LOGIND,
+                "/org/freedesktop/login1/session/auto",
+                SESSION_INTERFACE,
+            )?;
+            let id: String = auto
+                .get_property("Id")
+                .context("No logind session to sync")?;
+            let manager = Proxy::new(
+                &system,
+                LOGIND,
+                "/org/freedesktop/login1",
+                "org.freedesktop.login1.Manager",
+            )?;
+            let path: OwnedObjectPath = manager.call("GetSession", &id.as_str())?;
+            let session = Proxy::new_owned(system, LOGIND, path, SESSION_INTERFACE)?;
+
+            let screensaver = Connection::session()
+                .and_then(|bus| {
+                    Proxy::new_owned(
+                        bus,
+                        SCREENSAVER,
+                        "/org/freedesktop/ScreenSaver",
+                        SCREENSAVER,
+                    )
+                })
+                .inspect_err(|e| println!("No screensaver to sync: {}", e))
+                .ok();
+
+            Ok(Self {
+                session,
+                screensaver,
+            })
+        }
+    }
+
+    impl SessionBackend for LogindSession {
+        fn watch(&mut self, handler: SessionHandler) -> Result<()> {
+            for (signal, event) in [
+                ("Lock", SessionEvent::Locked),
+                ("Unlock", SessionEvent::Unlocked),
+            ] {
+                let signals = self.session.receive_signal(signal)?;
+                let handler = handler.clone();
+                std::thread::spawn(move || {
+                    for _ in signals {
+                        handler(event);
+                    }
+                });
+            }
+
+            if let Some(screensaver) = &self.screensaver {
+                let signals = screensaver.receive_signal("ActiveChanged")?;
+                std::thread::spawn(move || {
+                    for message in signals {
+                        match message.body().deserialize::<bool>() {
+                            Ok(true) => handler(SessionEvent::ScreensaverStarted),
+                            Ok(false) => handler(SessionEvent::ScreensaverStopped),
+                            Err(_) => {}
+                        }
+                    }
+                });
+            }
+            Ok(())
+        }
+
+        fn apply(&mut self, event: SessionEvent) -> Result<()> {
+            match event {
+                SessionEvent::Locked => {
+                    self.session
+                        .call_method("Lock", &())
+                        .context("Failed to lock the session")?;
+                }
+                SessionEvent::Unlocked => bail!("Unlocking is never mirrored"),
+                SessionEvent::ScreensaverStarted | SessionEvent::ScreensaverStopped => {
+                    let Some(screensaver) = &self.screensaver else {
+                        bail!("No screensaver on the session bus");
+                    };
+                    let active = event == SessionEvent::ScreensaverStarted;
+                    screensaver
+                        .call_method("SetActive", &active)
+                        .context("Failed to change the screensaver")?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -1,5 +1,15 @@
-use crate::config::Config;
+use crate::config::{Config, SessionSync};
 use crate::connected::ConnectedClients;
+use crate::edge::{self, Edge, EdgeContact, EdgeState};
+use crate::event::Key;
+use crate::events::{EventSender, ServerEvent, create_event_channel};
+use crate::hotkey::Hotkey;
+use crate::input::remap::RemapTable;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::broadcast;
 
 pub struct Topology {
     config: Config,
@@ -7,6 +17,39 @@ pub struct Topology {
     current_focus: Focus,
     screen_width: f64,
     screen_height: f64,
+    /// Parsed profile hotkeys with the profile they switch to
+    profile_hotkeys: Vec<(Hotkey, String)>,
+    /// Focus stays where it is whatever the cursor does
+    focus_locked: bool,
+    /// Input stays on the server: no edge or hotkey switches to clients
+    paused: bool,
+    /// Parsed hotkey that toggles `focus_locked`
+    focus_lock_hotkey: Option<Hotkey>,
+    /// Edge switching policy state, carried between cursor moves
+    edge_state: EdgeState,
+    /// Parsed macro hotkeys with the macro they start
+    macro_hotkeys: Vec<(Hotkey, String)>,
+    /// Parsed local and client hotkeys with the focus they switch to
+    focus_hotkeys: Vec<(Hotkey, Focus)>,
+    /// Where an edge crossing put the cursor on the focused machine, in the shared
+    /// coordinate space; `None` after a direct focus change
+    entry: Option<(f64, f64)>,
+    /// Last cursor position on each machine in the shared coordinate space, by
+    /// focus name
+    cursor_positions: HashMap<String, (f64, f64)>,
+    /// Parsed key remaps of the active profile's clients, by client name
+    client_remaps: HashMap<String, RemapTable>,
+    events: EventSender,
+}
+
+/// Topology shared between the input capture thread, the server and the GUI
+pub type SharedTopology = Arc<Mutex<Topology>>;
+
+pub fn create_shared_topology(
+    config: Config,
+    connected_clients: ConnectedClients,
+) -> SharedTopology {
+    Arc::new(Mutex::new(Topology::new(config, connected_clients)))
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -15,29 +58,42 @@ pub enum Focus {
     Client(String), // Client Name
 }
 
+impl Focus {
+    /// Focus as shown to users and control clients: `"local"` or the client name
+    pub fn name(&self) -> &str {
+        match self {
+            Focus::Local => "local",
+            Focus::Client(name) => name,
+        }
+    }
+}
+
 impl Topology {
     pub fn new(config: Config, connected_clients: ConnectedClients) -> Self {
-        // Calculate total bounding box of local screens for initial "screen size"
-        // This is a simplification; we'll rely on the config for actual geometry.
-        let mut max_x = 0.0;
-        let mut max_y = 0.0;
-        for screen in &config.local_screens {
-            let right = (screen.x + screen.width as i32) as f64;
-            let bottom = (screen.y + screen.height as i32) as f64;
-            if right > max_x {
-                max_x = right;
-            }
-            if bottom > max_y {
-                max_y = bottom;
-            }
-        }
+        let (screen_width, screen_height) = local_bounds(&config);
+        let profile_hotkeys = profile_hotkeys(&config);
+        let macro_hotkeys = macro_hotkeys(&config);
+        let focus_hotkeys = focus_hotkeys(&config);
+        let focus_lock_hotkey = parse_hotkey(&config.focus_lock_hotkey);
+        let client_remaps = client_remaps(&config);
 
         Self {
             config,
             connected_clients,
             current_focus: Focus::Local,
-            screen_width: max_x,
-            screen_height: max_y,
+            screen_width,
+            screen_height,
+            profile_hotkeys,
+            macro_hotkeys,
+            focus_hotkeys,
+            entry: None,
+            cursor_positions: HashMap::new(),
+            client_remaps,
+            focus_locked: false,
+            paused: false,
+            focus_lock_hotkey,
+            edge_state: EdgeState::default(),
+            events: create_event_channel(),
         }
     }
 
@@ -46,35 +102,191 @@ impl Topology {
         self.screen_height = height;
     }
 
-    pub fn check_edge(&mut self, x: f64, y: f64) -> Option<Focus> {
+    /// The client to switch to for a cursor move to `(x, y)` at `now`, if the
+    /// edge policies allow it. `dragging` is whether a mouse button is held.
+    pub fn check_edge(&mut self, x: f64, y: f64, dragging: bool, now: Instant) -> Option<Focus> {
         if self.current_focus != Focus::Local {
             return None;
         }
+        if self.focus_locked || self.paused {
+            self.edge_state = EdgeState::default();
+            return None;
+        }
+
+        // If we are not inside any local screen, we might be crossing into a client
+        let target = if self.is_inside_local(x, y) {
+            None
+        } else {
+            self.find_client_at(x as i32, y as i32)
+        };
+        let contact = target.as_ref().and_then(|_| self.edge_contact(x, y));
+        let policy = match &contact {
+            Some(contact) => self.config.edge_policy_for(contact.edge),
+            None => &self.config.edge_policy,
+        };
+
+        let (switch, state) =
+            edge::decide(policy, &self.edge_state, contact.as_ref(), dragging, now);
+        self.edge_state = state;
+        if switch { target } else { None }
+    }
+
+    /// Which edge of the nearest local screen a point outside them lies beyond
+    fn edge_contact(&self, x: f64, y: f64) -> Option<EdgeContact> {
+        self.config
+            .active()
+            .local_screens
+            .iter()
+            .map(|screen| {
+                let left = screen.x as f64;
+                let top = screen.y as f64;
+                let right = left + screen.width as f64;
+                let bottom = top + screen.height as f64;
+
+                // How far past each side the point is
+                let past_x = (left - x).max(x - right + 1.0).max(0.0);
+                let past_y = (top - y).max(y - bottom + 1.0).max(0.0);
+                let contact = if past_x >= past_y {
+                    EdgeContact {
+                        edge: if x < left { Edge::Left } else { Edge::Right },
+                        offset: (y - top).clamp(0.0, screen.height as f64),
+                        length: screen.height as f64,
+                    }
+                } else {
+                    EdgeContact {
+                        edge: if y < top { Edge::Top } else { Edge::Bottom },
+                        offset: (x - left).clamp(0.0, screen.width as f64),
+                        length: screen.width as f64,
+                    }
+                };
+                (past_x.hypot(past_y), contact)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, contact)| contact)
+    }
 
-        // Check if point is inside ANY local screen
-        let mut inside_local = false;
-        for screen in &self.config.local_screens {
+    /// Whether a point lies inside any local screen of the active profile
+    pub fn is_inside_local(&self, x: f64, y: f64) -> bool {
+        self.config.active().local_screens.iter().any(|screen| {
             let sx = screen.x as f64;
             let sy = screen.y as f64;
             let sw = screen.width as f64;
             let sh = screen.height as f64;
 
-            if x >= sx && x < sx + sw && y >= sy && y < sy + sh {
-                inside_local = true;
-                break;
-            }
-        }
+            x >= sx && x < sx + sw && y >= sy && y < sy + sh
+        })
+    }
 
-        if !inside_local {
-            // If we are not inside any local screen, we might be crossing into a client
-            return self.find_client_at(x as i32, y as i32);
+    /// A client's rectangle as `(x, y, width, height)` in the shared coordinate
+    /// space: its configured position, or the one it reported when connecting
+    pub fn client_bounds(&self, name: &str) -> Option<(f64, f64, f64, f64)> {
+        if let Some(client) = self.config.active().clients.iter().find(|c| c.name == name) {
+            return Some((
+                client.x as f64,
+                client.y as f64,
+                client.width as f64,
+                client.height as f64,
+            ));
         }
-        None
+
+        let clients = self.connected_clients.lock().ok()?;
+        let info = &clients
+            .values()
+            .find(|c| c.screen_info.name == name)?
+            .screen_info;
+        Some((
+            info.x as f64,
+            info.y as f64,
+            info.width as f64,
+            info.height as f64,
+        ))
+    }
+
+    /// Where the cursor lands on `client` when focus moves there without crossing
+    /// an edge: where it last was in jump mode, otherwise the middle of its screen
+    pub fn landing_point(&self, client: &str) -> Option<(f64, f64)> {
+        let bounds = self.client_bounds(client)?;
+        Some(match self.last_cursor(client) {
+            Some(last) if self.config.jump_mode => clamp_to(bounds, last),
+            _ => (bounds.0 + bounds.2 / 2.0, bounds.1 + bounds.3 / 2.0),
+        })
+    }
+
+    /// Where the cursor entered `client` when it last gained focus, relative to
+    /// its own top-left corner
+    pub fn entry_point(&self, client: &str) -> Option<(f64, f64)> {
+        let (x, y) = match self.entry {
+            Some(entry) if self.current_focus == Focus::Client(client.to_string()) => entry,
+            _ => self.landing_point(client)?,
+        };
+        let (origin_x, origin_y, _, _) = self.client_bounds(client)?;
+        Some((x - origin_x, y - origin_y))
+    }
+
+    /// The side `machine` was entered across when an edge crossing focused it;
+    /// `None` when it is not focused or focus moved there directly
+    pub fn entry_edge(&self, machine: &str) -> Option<Edge> {
+        let point = self
+            .entry
+            .filter(|_| self.current_focus.name() == machine)?;
+        let bounds = match &self.current_focus {
+            Focus::Local => self.local_screen_at(point)?,
+            Focus::Client(name) => self.client_bounds(name)?,
+        };
+        Some(nearest_side(bounds, point))
+    }
+
+    /// The local screen containing a point, as `(x, y, width, height)`
+    fn local_screen_at(&self, (x, y): (f64, f64)) -> Option<(f64, f64, f64, f64)> {
+        self.config
+            .active()
+            .local_screens
+            .iter()
+            .map(|screen| {
+                (
+                    screen.x as f64,
+                    screen.y as f64,
+                    screen.width as f64,
+                    screen.height as f64,
+                )
+            })
+            .find(|&(left, top, width, height)| {
+                x >= left && x < left + width && y >= top && y < top + height
+            })
+    }
+
+    /// Display scale factor a connected client reported, 1.0 if unknown
+    pub fn client_scale(&self, name: &str) -> f64 {
+        let Ok(clients) = self.connected_clients.lock() else {
+            return 1.0;
+        };
+        clients
+            .values()
+            .find(|c| c.screen_info.name == name)
+            .map(|c| c.screen_info.scale)
+            .filter(|scale| *scale > 0.0)
+            .unwrap_or(1.0)
+    }
+
+    /// Centre of the first local screen, where the cursor is parked while a
+    /// client is focused
+    pub fn parking_spot(&self) -> (f64, f64) {
+        self.config
+            .active()
+            .local_screens
+            .first()
+            .map(|screen| {
+                (
+                    screen.x as f64 + screen.width as f64 / 2.0,
+                    screen.y as f64 + screen.height as f64 / 2.0,
+                )
+            })
+            .unwrap_or((self.screen_width / 2.0, self.screen_height / 2.0))
     }
 
     fn find_client_at(&self, x: i32, y: i32) -> Option<Focus> {
         // Check configured clients (static)
-        for client in &self.config.clients {
+        for client in &self.config.active().clients {
             let cx = client.x;
             let cy = client.y;
             let cw = client.width as i32;
@@ -104,9 +316,91 @@ impl Topology {
     }
 
     pub fn set_focus(&mut self, focus: Focus) {
+        self.change_focus(focus, None);
+    }
+
+    /// Move focus to the client the cursor crossed onto at `point`
+    pub fn set_focus_at(&mut self, focus: Focus, point: (f64, f64)) {
+        self.change_focus(focus, Some(point));
+    }
+
+    fn change_focus(&mut self, focus: Focus, entry: Option<(f64, f64)>) {
+        if self.current_focus != focus {
+            self.entry = entry;
+            self.publish(ServerEvent::FocusChanged {
+                focus: focus.name().to_string(),
+            });
+        }
         self.current_focus = focus;
     }
 
+    /// Pin focus on the current machine, local or client, by disabling edge
+    /// switching. Explicit focus changes still work.
+    pub fn set_focus_locked(&mut self, locked: bool) {
+        if self.focus_locked == locked {
+            return;
+        }
+        self.focus_locked = locked;
+        if locked {
+            println!("Focus locked to {}", self.current_focus.name());
+        } else {
+            println!("Focus unlocked");
+        }
+        self.publish(ServerEvent::FocusLockChanged { locked });
+    }
+
+    pub fn is_focus_locked(&self) -> bool {
+        self.focus_locked
+    }
+
+    /// Stop sharing input with clients, bringing focus back to the server, or
+    /// start again
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused == paused {
+            return;
+        }
+        self.paused = paused;
+        if paused {
+            self.set_focus(Focus::Local);
+            println!("Sharing paused");
+        } else {
+            println!("Sharing resumed");
+        }
+        self.publish(ServerEvent::SharingPaused { paused });
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether pressing `key` with `pressed` held completes the focus lock hotkey
+    pub fn is_focus_lock_hotkey(&self, pressed: &HashSet<Key>, key: Key) -> bool {
+        self.focus_lock_hotkey
+            .as_ref()
+            .is_some_and(|hotkey| hotkey.matches(pressed, key))
+    }
+
+    /// Remember where the cursor was on a machine, `"local"` or a client name,
+    /// when focus left it
+    pub fn remember_cursor(&mut self, machine: &str, position: (f64, f64)) {
+        self.cursor_positions.insert(machine.to_string(), position);
+    }
+
+    /// Where the cursor last was on a machine, in the shared coordinate space
+    pub fn last_cursor(&self, machine: &str) -> Option<(f64, f64)> {
+        self.cursor_positions.get(machine).copied()
+    }
+
+    /// Receive focus, connection and config events
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn publish(&self, event: ServerEvent) {
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
     pub fn get_focus(&self) -> &Focus {
         &self.current_focus
     }
@@ -116,6 +410,173 @@ impl Topology {
     }
 
     pub fn update_config(&mut self, config: Config) {
+        let (screen_width, screen_height) = local_bounds(&config);
+        self.screen_width = screen_width;
+        self.screen_height = screen_height;
+        self.profile_hotkeys = profile_hotkeys(&config);
+        self.macro_hotkeys = macro_hotkeys(&config);
+        self.focus_hotkeys = focus_hotkeys(&config);
+        self.focus_lock_hotkey = parse_hotkey(&config.focus_lock_hotkey);
+        self.client_remaps = client_remaps(&config);
+
+        // The focused client may not exist in the new layout
+        if let Focus::Client(name) = &self.current_focus {
+            let configured = config.active().clients.iter().any(|c| &c.name == name);
+            let connected = self
+                .connected_clients
+                .lock()
+                .map(|clients| clients.values().any(|c| &c.screen_info.name == name))
+                .unwrap_or(false);
+            if !configured && !connected {
+                self.set_focus(Focus::Local);
+            }
+        }
+
         self.config = config;
     }
+
+    /// Switch to another named profile.
+    ///
+    /// Focus returns to local since the previous profile's clients may not exist in
+    /// the new one. The config watcher persists the choice to config.json on
+    /// `ProfileChanged`, so callers holding the lock never wait on the disk.
+    pub fn switch_profile(&mut self, name: &str) -> Result<()> {
+        let mut config = self.config.clone();
+        config.set_active_profile(name)?;
+        self.update_config(config);
+        self.set_focus(Focus::Local);
+        self.publish(ServerEvent::ProfileChanged {
+            profile: name.to_string(),
+        });
+        println!("Switched to profile '{}'", name);
+        Ok(())
+    }
+
+    /// The profile whose hotkey is completed by pressing `key` with `pressed` held
+    pub fn profile_for_hotkey(&self, pressed: &HashSet<Key>, key: Key) -> Option<&str> {
+        self.profile_hotkeys
+            .iter()
+            .find(|(hotkey, _)| hotkey.matches(pressed, key))
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// The macro whose hotkey is completed by pressing `key` with `pressed` held
+    pub fn macro_for_hotkey(&self, pressed: &HashSet<Key>, key: Key) -> Option<&str> {
+        self.macro_hotkeys
+            .iter()
+            .find(|(hotkey, _)| hotkey.matches(pressed, key))
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// The focus a local or client hotkey completed by pressing `key` with
+    /// `pressed` held switches to. Client hotkeys do nothing while paused.
+    pub fn focus_for_hotkey(&self, pressed: &HashSet<Key>, key: Key) -> Option<Focus> {
+        self.focus_hotkeys
+            .iter()
+            .find(|(hotkey, _)| hotkey.matches(pressed, key))
+            .map(|(_, focus)| focus.clone())
+            .filter(|focus| !self.paused || *focus == Focus::Local)
+    }
+
+    /// The session sync policy of a client, `Ignore` if it is not configured
+    pub fn session_sync_for(&self, client: &str) -> SessionSync {
+        self.config
+            .active()
+            .clients
+            .iter()
+            .find(|c| c.name == client)
+            .map_or(SessionSync::Ignore, |c| c.session_sync)
+    }
+
+    /// Key remaps for a client, if it has any
+    pub fn remap_for(&self, client: &str) -> Option<&RemapTable> {
+        self.client_remaps.get(client)
+    }
+}
+
+fn profile_hotkeys(config: &Config) -> Vec<(Hotkey, String)> {
+    config
+        .profiles
+        .iter()
+        .filter_map(|profile| {
+            let hotkey = profile.hotkey.as_ref()?.parse().ok()?;
+            Some((hotkey, profile.name.clone()))
+        })
+        .collect()
+}
+
+fn parse_hotkey(hotkey: &Option<String>) -> Option<Hotkey> {
+    hotkey.as_ref()?.parse().ok()
+}
+
+fn macro_hotkeys(config: &Config) -> Vec<(Hotkey, String)> {
+    config
+        .macros
+        .iter()
+        .filter_map(|m| {
+            let hotkey = m.hotkey.as_ref()?.parse().ok()?;
+            Some((hotkey, m.name.clone()))
+        })
+        .collect()
+}
+
+fn focus_hotkeys(config: &Config) -> Vec<(Hotkey, Focus)> {
+    let local = parse_hotkey(&config.local_hotkey).map(|hotkey| (hotkey, Focus::Local));
+    let clients = config.active().clients.iter().filter_map(|client| {
+        let hotkey = parse_hotkey(&client.hotkey)?;
+        Some((hotkey, Focus::Client(client.name.clone())))
+    });
+    local.into_iter().chain(clients).collect()
+}
+
+fn client_remaps(config: &Config) -> HashMap<String, RemapTable> {
+    config
+        .active()
+        .clients
+        .iter()
+        .map(|client| (client.name.clone(), RemapTable::from_config(&client.remap)))
+        .filter(|(_, table)| !table.is_empty())
+        .collect()
+}
+
+/// Clamp a point into an `(x, y, width, height)` rectangle
+pub fn clamp_to(bounds: (f64, f64, f64, f64), (x, y): (f64, f64)) -> (f64, f64) {
+    let (left, top, width, height) = bounds;
+    (
+        x.clamp(left, left + (width - 1.0).max(0.0)),
+        y.clamp(top, top + (height - 1.0).max(0.0)),
+    )
+}
+
+/// The side of an `(x, y, width, height)` rectangle closest to a point inside it
+fn nearest_side(bounds: (f64, f64, f64, f64), (x, y): (f64, f64)) -> Edge {
+    let (left, top, width, height) = bounds;
+    [
+        (x - left, Edge::Left),
+        (left + width - x, Edge::Right),
+        (y - top, Edge::Top),
+        (top + height - y, Edge::Bottom),
+    ]
+    .into_iter()
+    .min_by(|a, b| a.0.total_cmp(&b.0))
+    .map(|(_, edge)| edge)
+    .unwrap_or(Edge::Left)
+}
+
+/// Calculate total bounding box of local screens for the "screen size".
+/// This is a simplification; we rely on the config for actual geometry.
+fn local_bounds(config: &Config) -> (f64, f64) {
+    let mut max_x = 0.0;
+    let mut max_y = 0.0;
+    for screen in &config.active().local_screens {
+        let right = (screen.x + screen.width as i32) as f64;
+        let bottom = (screen.y + screen.height as i32) as f64;
+        if right > max_x {
+            max_x = right;
+        }
+        if bottom > max_y {
+            max_y = bottom;
+        }
+    }
+    (max_x, max_y)
 }
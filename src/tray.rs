@@ -0,0 +1,331 @@
+//! System tray icon for the server and client.
+//!
+//! The icon shows a `TrayStatus`: the connection, where input goes and, on the
+//! server, whether edge switching is locked or sharing is paused. Its menu sends
+//! `TrayAction`s back for the binary to carry out. On Linux the icon is a
+//! StatusNotifierItem; elsewhere `spawn` reports that there is no tray.
+
+use crate::connected::ConnectedClients;
+use crate::topology::{Focus, SharedTopology, Topology};
+use tokio::sync::{broadcast, mpsc, watch};
+
+/// What the tray icon shows
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrayStatus {
+    /// e.g. "2 clients connected" or "Connected to 10.0.0.1:8080"
+    pub connection: String,
+    /// The machine that has the keyboard and mouse
+    pub focus: String,
+    /// Offer the server's menu; the fields below only apply to the server
+    pub server: bool,
+    pub focus_locked: bool,
+    pub paused: bool,
+    pub profiles: Vec<String>,
+    pub active_profile: String,
+}
+
+/// A pick from the tray menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayAction {
+    OpenConfiguration,
+    ToggleFocusLock,
+    TogglePause,
+    SwitchProfile(String),
+    Quit,
+}
+
+/// One entry of the tray menu
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuEntry {
+    /// A line of status that cannot be picked
+    Info(String),
+    Action {
+        label: String,
+        action: TrayAction,
+    },
+    Toggle {
+        label: String,
+        checked: bool,
+        action: TrayAction,
+    },
+    /// Switch between profiles, with `selected` the active one
+    Profiles {
+        options: Vec<String>,
+        selected: usize,
+    },
+    Separator,
+}
+
+impl TrayStatus {
+    /// The server's status, with `connected` clients connected
+    pub fn for_server(topology: &Topology, connected: usize) -> Self {
+        let config = topology.get_config();
+        Self {
+            connection: match connected {
+                0 => "No clients connected".to_string(),
+                1 => "1 client connected".to_string(),
+                n => format!("{} clients connected", n),
+            },
+            focus: match topology.get_focus() {
+                Focus::Local => "this machine".to_string(),
+                Focus::Client(name) => name.clone(),
+            },
+            server: true,
+            focus_locked: topology.is_focus_locked(),
+            paused: topology.is_paused(),
+            profiles: config.profiles.iter().map(|p| p.name.clone()).collect(),
+            active_profile: config.active_profile.clone(),
+        }
+    }
+
+    /// A client's status while connected to `host`
+    pub fn for_client(host: &str, focused: bool) -> Self {
+        Self {
+            connection: format!("Connected to {}", host),
+            focus: if focused {
+                "this machine".to_string()
+            } else {
+                "another machine".to_string()
+            },
+            ..Self::default()
+        }
+    }
+
+    /// One line for the icon's tooltip
+    pub fn summary(&self) -> String {
+        if self.paused {
+            return format!("{}; sharing paused", self.connection);
+        }
+        format!("{}; input on {}", self.connection, self.focus)
+    }
+
+    pub fn menu(&self) -> Vec<MenuEntry> {
+        let mut menu = vec![MenuEntry::Info(self.summary()), MenuEntry::Separator];
+        if self.server {
+            menu.push(MenuEntry::Action {
+                label: "Open configuration".to_string(),
+                action: TrayAction::OpenConfiguration,
+            });
+            menu.push(MenuEntry::Toggle {
+                label: "Lock to screen".to_string(),
+                checked: self.focus_locked,
+                action: TrayAction::ToggleFocusLock,
+            });
+            menu.push(MenuEntry::Toggle {
+                label: "Pause sharing".to_string(),
+                checked: self.paused,
+                action: TrayAction::TogglePause,
+            });
+            if self.profiles.len() > 1 {
+                menu.push(MenuEntry::Profiles {
+                    selected: self
+                        .profiles
+                        .iter()
+                        .position(|p| *p == self.active_profile)
+                        .unwrap_or(0),
+                    options: self.profiles.clone(),
+                });
+            }
+            menu.push(MenuEntry::Separator);
+        }
+        menu.push(MenuEntry::Action {
+            label: "Quit".to_string(),
+            action: TrayAction::Quit,
+        });
+        menu
+    }
+}
+
+/// Keep the server's tray status current with its topology and clients
+pub fn watch_server(
+    topology: SharedTopology,
+    connected_clients: ConnectedClients,
+) -> watch::Receiver<TrayStatus> {
+    let mut events = topology.lock().unwrap().subscribe();
+    let status = move || {
+        let connected = connected_clients.lock().unwrap().len();
+        TrayStatus::for_server(&topology.lock().unwrap(), connected)
+    };
+    let (tx, rx) = watch::channel(status());
+    tokio::spawn(async move {
+        // Any event may change the status, and so may the ones missed when lagging
+        while let Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) = events.recv().await {
+            if tx.send(status()).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Carry out a server menu pick on the topology. Opening the configuration and
+/// quitting are up to the binary, so those are handed back.
+pub fn apply(topology: &SharedTopology, action: TrayAction) -> Option<TrayAction> {
+    let mut topo = topology.lock().unwrap();
+    match action {
+        TrayAction::ToggleFocusLock => {
+            let locked = !topo.is_focus_locked();
+            topo.set_focus_locked(locked);
+        }
+        TrayAction::TogglePause => {
+            let paused = !topo.is_paused();
+            topo.set_paused(paused);
+        }
+        TrayAction::SwitchProfile(name) => {
+            if let Err(e) = topo.switch_profile(&name) {
+                println!("Failed to switch profile: {:#}", e);
+            }
+        }
+        other => return Some(other),
+    }
+    None
+}
+
+/// Show `status` in the system tray for as long as it is updated, sending menu
+/// picks to `actions`. Returns whether there is a tray to show it in.
+pub async fn spawn(
+    status: watch::Receiver<TrayStatus>,
+    actions: mpsc::UnboundedSender<TrayAction>,
+) -> bool {
+    #[cfg(target_os = "linux")]
+    match sni::spawn(status, actions).await {
+        Ok(()) => return true,
+        Err(e) => println!("No tray icon: {}", e),
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (status, actions);
+        println!("No tray icon on this platform");
+    }
+    false
+}
+
+#[cfg(target_os = "linux")]
+mod sni {
+    use super::{MenuEntry, TrayAction, TrayStatus};
+    use ksni::TrayMethods;
+    use ksni::menu::{CheckmarkItem, RadioGroup, RadioItem, StandardItem, SubMenu};
+    use tokio::sync::{mpsc, watch};
+
+    /// A StatusNotifierItem, as shown by KDE, GNOME with the AppIndicator
+    /// extension and most other Linux panels
+    struct StatusNotifier {
+        status: TrayStatus,
+        actions: mpsc::UnboundedSender<TrayAction>,
+    }
+
+    impl StatusNotifier {
+        fn send(&self, action: TrayAction) {
+            let _ = self.actions.send(action);
+        }
+    }
+
+    impl ksni::Tray for StatusNotifier {
+        fn id(&self) -> String {
+            "aurora-kvm".to_string()
+        }
+
+        fn title(&self) -> String {
+            "AuroraKVM".to_string()
+        }
+
+        fn icon_name(&self) -> String {
+            if self.status.paused {
+                "media-playback-pause".to_string()
+            } else {
+                "input-keyboard".to_string()
+            }
+        }
+
+        fn tool_tip(&self) -> ksni::ToolTip {
+            ksni::ToolTip {
+                title: "AuroraKVM".to_string(),
+                description: self.status.summary(),
+                ..Default::default()
+            }
+        }
+
+        fn activate(&mut self, _x: i32, _y: i32) {
+            if self.status.server {
+                self.send(TrayAction::OpenConfiguration);
+            }
+        }
+
+        fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+            self.status.menu().into_iter().map(menu_item).collect()
+        }
+    }
+
+    fn menu_item(entry: MenuEntry) -> ksni::MenuItem<StatusNotifier> {
+        match entry {
+            MenuEntry::Info(label) => StandardItem {
+                label,
+                enabled: false,
+                ..Default::default()
+            }
+            .into(),
+            MenuEntry::Action { label, action } => StandardItem {
+                label,
+                activate: Box::new(move |tray: &mut StatusNotifier| tray.send(action.clone())),
+                ..Default::default()
+            }
+            .into(),
+            MenuEntry::Toggle {
+                label,
+                checked,
+                action,
+            } => CheckmarkItem {
+                label,
+                checked,
+                activate: Box::new(move |tray: &mut StatusNotifier| tray.send(action.clone())),
+                ..Default::default()
+            }
+            .into(),
+            MenuEntry::Profiles { options, selected } => SubMenu {
+                label: "Switch profile".to_string(),
+                submenu: vec![
+                    RadioGroup {
+                        selected,
+                        options: options
+                            .iter()
+                            .map(|name| RadioItem {
+                                label: name.clone(),
+                                ..Default::default()
+                            })
+                            .collect(),
+                        select: Box::new(move |tray: &mut StatusNotifier, i| {
+                            if let Some(name) = options.get(i) {
+                                tray.send(TrayAction::SwitchProfile(name.clone()));
+                            }
+                        }),
+                    }
+                    .into(),
+                ],
+                ..Default::default()
+            }
+            .into(),
+            MenuEntry::Separator => ksni::MenuItem::Separator,
+        }
+    }
+
+    pub async fn spawn(
+        mut status: watch::Receiver<TrayStatus>,
+        actions: mpsc::UnboundedSender<TrayAction>,
+    ) -> Result<(), ksni::Error> {
+        let tray = StatusNotifier {
+            status: status.borrow_and_update().clone(),
+            actions,
+        };
+        let handle = tray.spawn().await?;
+        tokio::spawn(async move {
+            while status.changed().await.is_ok() {
+                let current = status.borrow_and_update().clone();
+                if handle.update(|tray| tray.status = current).await.is_none() {
+                    break;
+                }
+            }
+            handle.shutdown().await;
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,638 @@
+use crate::config::{CONFIG_VERSION, Config, Profile};
+use crate::hotkey::Hotkey;
+use crate::input::remap::RemapRule;
+use crate::macros;
+use crate::migrate;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single problem found in a config, pointing at the offending JSON value
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// JSON path such as `clients[1].ip`
+    pub path: String,
+    /// 1-based line in the source file, when the config came from text
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(
+                f,
+                "{}: {} (line {}): {}",
+                severity, self.path, line, self.message
+            ),
+            None => write!(f, "{}: {}: {}", severity, self.path, self.message),
+        }
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+/// Parse and validate config source text.
+///
+/// Returns the parsed config (if it parsed at all) and every diagnostic, each
+/// annotated with the line of the value it refers to.
+pub fn check_source(source: &str) -> (Option<Config>, Vec<Diagnostic>) {
+    // Older schemas are checked as they will look after the upgrade on load
+    let mut notes = Vec::new();
+    let migrated = match migrate::migrate_source(source) {
+        Ok(migrated) => migrated,
+        Err(e) => {
+            let diagnostic = Diagnostic {
+                severity: Severity::Error,
+                path: "version".to_string(),
+                line: None,
+                message: e.to_string(),
+            };
+            return (None, vec![diagnostic]);
+        }
+    };
+    let source = match &migrated {
+        Some((from_version, text)) => {
+            notes.push(Diagnostic {
+                severity: Severity::Warning,
+                path: "version".to_string(),
+                line: None,
+                message: format!(
+                    "schema version {} will be upgraded to {} on load; line numbers refer to the upgraded file",
+                    from_version, CONFIG_VERSION
+                ),
+            });
+            text.as_str()
+        }
+        None => source,
+    };
+
+    let config: Config = match serde_json::from_str(source) {
+        Ok(config) => config,
+        Err(e) => {
+            let diagnostic = Diagnostic {
+                severity: Severity::Error,
+                path: "$".to_string(),
+                line: Some(e.line()),
+                message: e.to_string(),
+            };
+            return (None, vec![diagnostic]);
+        }
+    };
+
+    let lines = locate_paths(source);
+    let mut diagnostics = notes;
+    diagnostics.extend(validate(&config));
+    for diagnostic in &mut diagnostics {
+        diagnostic.line = line_for(&lines, &diagnostic.path);
+    }
+
+    (Some(config), diagnostics)
+}
+
+/// Run every check over an in-memory config
+pub fn validate(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if let Some(hotkey) = &config.input_grab_hotkey
+        && let Err(e) = hotkey.parse::<Hotkey>()
+    {
+        push(
+            &mut diagnostics,
+            Severity::Error,
+            "input_grab_hotkey",
+            e.to_string(),
+        );
+    }
+
+    if let Some(hotkey) = &config.focus_lock_hotkey
+        && let Err(e) = hotkey.parse::<Hotkey>()
+    {
+        push(
+            &mut diagnostics,
+            Severity::Error,
+            "focus_lock_hotkey",
+            e.to_string(),
+        );
+    }
+
+    if let Some(hotkey) = &config.local_hotkey
+        && let Err(e) = hotkey.parse::<Hotkey>()
+    {
+        push(
+            &mut diagnostics,
+            Severity::Error,
+            "local_hotkey",
+            e.to_string(),
+        );
+    }
+
+    if config.profiles.is_empty() {
+        push(
+            &mut diagnostics,
+            Severity::Error,
+            "profiles",
+            "at least one profile is required".to_string(),
+        );
+    } else if !config
+        .profiles
+        .iter()
+        .any(|p| p.name == config.active_profile)
+    {
+        push(
+            &mut diagnostics,
+            Severity::Error,
+            "active_profile",
+            format!("no profile named '{}'", config.active_profile),
+        );
+    }
+
+    let mut seen_profiles: HashMap<&str, usize> = HashMap::new();
+    for (i, profile) in config.profiles.iter().enumerate() {
+        let path = format!("profiles[{}]", i);
+
+        if let Some(first) = seen_profiles.insert(profile.name.as_str(), i) {
+            push(
+                &mut diagnostics,
+                Severity::Error,
+                &format!("{}.name", path),
+                format!(
+                    "duplicate profile name '{}' (also used by profiles[{}])",
+                    profile.name, first
+                ),
+            );
+        }
+
+        if let Some(hotkey) = &profile.hotkey
+            && let Err(e) = hotkey.parse::<Hotkey>()
+        {
+            push(
+                &mut diagnostics,
+                Severity::Error,
+                &format!("{}.hotkey", path),
+                e.to_string(),
+            );
+        }
+
+        validate_profile(profile, &path, &mut diagnostics);
+    }
+
+    validate_macros(config, &mut diagnostics);
+
+    diagnostics
+}
+
+fn push(diagnostics: &mut Vec<Diagnostic>, severity: Severity, path: &str, message: String) {
+    diagnostics.push(Diagnostic {
+        severity,
+        path: path.to_string(),
+        line: None,
+        message,
+    });
+}
+
+fn validate_macros(config: &Config, diagnostics: &mut Vec<Diagnostic>) {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for (i, macro_config) in config.macros.iter().enumerate() {
+        let path = format!("macros[{}]", i);
+
+        if let Some(first) = seen.insert(macro_config.name.as_str(), i) {
+            push(
+                diagnostics,
+                Severity::Error,
+                &format!("{}.name", path),
+                format!(
+                    "duplicate macro name '{}' (also used by macros[{}])",
+                    macro_config.name, first
+                ),
+            );
+        }
+
+        if let Some(hotkey) = &macro_config.hotkey
+            && let Err(e) = hotkey.parse::<Hotkey>()
+        {
+            push(
+                diagnostics,
+                Severity::Error,
+                &format!("{}.hotkey", path),
+                e.to_string(),
+            );
+        }
+
+        if let Some(target) = &macro_config.target {
+            let configured = config
+                .profiles
+                .iter()
+                .flat_map(|p| &p.clients)
+                .any(|c| &c.name == target);
+            if !configured {
+                push(
+                    diagnostics,
+                    Severity::Warning,
+                    &format!("{}.target", path),
+                    format!("no profile has a client named '{}'", target),
+                );
+            }
+        }
+
+        for (j, step) in macro_config.steps.iter().enumerate() {
+            if let Err(e) = macros::expand_step(step) {
+                push(
+                    diagnostics,
+                    Severity::Error,
+                    &format!("{}.steps[{}]", path, j),
+                    format!("{:#}", e),
+                );
+            }
+        }
+    }
+}
+
+/// Check the screen layout of one profile; `prefix` is the profile's JSON path
+fn validate_profile(profile: &Profile, prefix: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if profile.local_screens.is_empty() {
+        push(
+            diagnostics,
+            Severity::Warning,
+            &format!("{}.local_screens", prefix),
+            "no local screens configured".to_string(),
+        );
+    }
+
+    // Every screen rectangle with its JSON path, locals first
+    let mut rects: Vec<(String, Rect)> = Vec::new();
+
+    for (i, screen) in profile.local_screens.iter().enumerate() {
+        let path = format!("{}.local_screens[{}]", prefix, i);
+        if screen.width == 0 || screen.height == 0 {
+            push(
+                diagnostics,
+                Severity::Error,
+                &path,
+                format!("screen has zero size ({}x{})", screen.width, screen.height),
+            );
+        }
+        rects.push((
+            path,
+            Rect::new(screen.x, screen.y, screen.width, screen.height),
+        ));
+    }
+
+    let mut seen_names: HashMap<&str, usize> = HashMap::new();
+    for (i, client) in profile.clients.iter().enumerate() {
+        let path = format!("{}.clients[{}]", prefix, i);
+
+        if let Some(first) = seen_names.insert(client.name.as_str(), i) {
+            push(
+                diagnostics,
+                Severity::Error,
+                &format!("{}.name", path),
+                format!(
+                    "duplicate client name '{}' (also used by {}.clients[{}])",
+                    client.name, prefix, first
+                ),
+            );
+        }
+
+        if client.ip.is_empty() {
+            push(
+                diagnostics,
+                Severity::Warning,
+                &format!("{}.ip", path),
+                format!("client '{}' has no IP address", client.name),
+            );
+        } else if client.ip.parse::<IpAddr>().is_err() && client.ip.parse::<SocketAddr>().is_err() {
+            push(
+                diagnostics,
+                Severity::Error,
+                &format!("{}.ip", path),
+                format!("'{}' is not a valid IP address or IP:port", client.ip),
+            );
+        }
+
+        if let Some(hotkey) = &client.hotkey
+            && let Err(e) = hotkey.parse::<Hotkey>()
+        {
+            push(
+                diagnostics,
+                Severity::Error,
+                &format!("{}.hotkey", path),
+                e.to_string(),
+            );
+        }
+
+        if client.width == 0 || client.height == 0 {
+            push(
+                diagnostics,
+                Severity::Error,
+                &path,
+                format!(
+                    "client '{}' has zero size ({}x{})",
+                    client.name, client.width, client.height
+                ),
+            );
+        }
+
+        if !(client.scroll_speed.is_finite() && client.scroll_speed > 0.0) {
+            push(
+                diagnostics,
+                Severity::Error,
+                &format!("{}.scroll_speed", path),
+                format!(
+                    "scroll speed must be a positive number, got {}",
+                    client.scroll_speed
+                ),
+            );
+        }
+
+        if !(client.pointer_speed.is_finite() && client.pointer_speed > 0.0) {
+            push(
+                diagnostics,
+                Severity::Error,
+                &format!("{}.pointer_speed", path),
+                format!(
+                    "pointer speed must be a positive number, got {}",
+                    client.pointer_speed
+                ),
+            );
+        }
+
+        let mut previous_speed = 0.0;
+        for (j, &[speed, multiplier]) in client.pointer_acceleration.iter().enumerate() {
+            let point_path = format!("{}.pointer_acceleration[{}]", path, j);
+            if !(speed.is_finite() && speed >= previous_speed) {
+                push(
+                    diagnostics,
+                    Severity::Error,
+                    &point_path,
+                    format!(
+                        "speeds must be non-negative and increasing, got {} after {}",
+                        speed, previous_speed
+                    ),
+                );
+            }
+            if !(multiplier.is_finite() && multiplier > 0.0) {
+                push(
+                    diagnostics,
+                    Severity::Error,
+                    &point_path,
+                    format!("multiplier must be a positive number, got {}", multiplier),
+                );
+            }
+            previous_speed = speed;
+        }
+
+        for (from, to) in &client.remap {
+            if let Err(e) = RemapRule::parse(from, to) {
+                push(
+                    diagnostics,
+                    Severity::Error,
+                    &format!("{}.remap.{}", path, from),
+                    format!("{:#}", e),
+                );
+            }
+        }
+
+        rects.push((
+            path,
+            Rect::new(client.x, client.y, client.width, client.height),
+        ));
+    }
+
+    for (i, (path_a, a)) in rects.iter().enumerate() {
+        for (path_b, b) in rects.iter().skip(i + 1) {
+            if a.overlaps(b) {
+                push(
+                    diagnostics,
+                    Severity::Error,
+                    path_b,
+                    format!("overlaps {}", path_a),
+                );
+            }
+        }
+    }
+
+    // Clients that touch nothing can never be reached by moving the mouse
+    let local_count = profile.local_screens.len();
+    for (i, (path, rect)) in rects.iter().enumerate().skip(local_count) {
+        if rect.is_empty() {
+            continue;
+        }
+        let adjacent = rects
+            .iter()
+            .enumerate()
+            .any(|(j, (_, other))| i != j && rect.touches(other));
+        if !adjacent {
+            push(
+                diagnostics,
+                Severity::Warning,
+                path,
+                format!(
+                    "client '{}' is not adjacent to any other screen and cannot be reached",
+                    profile.clients[i - local_count].name
+                ),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    left: i64,
+    top: i64,
+    right: i64,
+    bottom: i64,
+}
+
+impl Rect {
+    fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            left: x as i64,
+            top: y as i64,
+            right: x as i64 + width as i64,
+            bottom: y as i64 + height as i64,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.left == self.right || self.top == self.bottom
+    }
+
+    fn overlaps(&self, other: &Rect) -> bool {
+        !self.is_empty()
+            && !other.is_empty()
+            && self.left < other.right
+            && other.left < self.right
+            && self.top < other.bottom
+            && other.top < self.bottom
+    }
+
+    /// Shares a stretch of edge with `other`
+    fn touches(&self, other: &Rect) -> bool {
+        let vertical_overlap = self.top < other.bottom && other.top < self.bottom;
+        let horizontal_overlap = self.left < other.right && other.left < self.right;
+
+        ((self.right == other.left || other.right == self.left) && vertical_overlap)
+            || ((self.bottom == other.top || other.bottom == self.top) && horizontal_overlap)
+    }
+}
+
+/// Find the line of `path`, falling back to the nearest enclosing value
+fn line_for(lines: &HashMap<String, usize>, path: &str) -> Option<usize> {
+    let mut path = path;
+    loop {
+        if let Some(&line) = lines.get(path) {
+            return Some(line);
+        }
+        let cut = path.rfind(['.', '['])?;
+        path = &path[..cut];
+    }
+}
+
+/// Map every JSON path in `source` to the 1-based line its value starts on.
+///
+/// This is a minimal scanner rather than a full parser; it is only run on text
+/// serde_json has already accepted.
+fn locate_paths(source: &str) -> HashMap<String, usize> {
+    let mut locator = Locator {
+        bytes: source.as_bytes(),
+        pos: 0,
+        line: 1,
+        lines: HashMap::new(),
+    };
+    locator.value(String::new());
+    locator.lines
+}
+
+struct Locator<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+    lines: HashMap<String, usize>,
+}
+
+impl Locator<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) {
+        if self.peek() == Some(b'\n') {
+            self.line += 1;
+        }
+        self.pos += 1;
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+            self.bump();
+        }
+    }
+
+    fn value(&mut self, path: String) {
+        self.skip_whitespace();
+        if !path.is_empty() {
+            self.lines.insert(path.clone(), self.line);
+        }
+        match self.peek() {
+            Some(b'{') => self.object(&path),
+            Some(b'[') => self.array(&path),
+            Some(b'"') => {
+                self.string();
+            }
+            Some(_) => {
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b'}' | b']' | b' ' | b'\t' | b'\r' | b'\n')
+                ) {
+                    self.bump();
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn string(&mut self) -> String {
+        let mut out = Vec::new();
+        self.bump(); // opening quote
+        while let Some(byte) = self.peek() {
+            self.bump();
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    if let Some(escaped) = self.peek() {
+                        out.push(escaped);
+                        self.bump();
+                    }
+                }
+                _ => out.push(byte),
+            }
+        }
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    fn object(&mut self, path: &str) {
+        self.bump(); // {
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'"') => {
+                    let key = self.string();
+                    self.skip_whitespace();
+                    if self.peek() == Some(b':') {
+                        self.bump();
+                    }
+                    let child = if path.is_empty() {
+                        key
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    self.value(child);
+                }
+                Some(b',') => self.bump(),
+                Some(b'}') => {
+                    self.bump();
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn array(&mut self, path: &str) {
+        self.bump(); // [
+        let mut index = 0;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.bump(),
+                Some(b']') => {
+                    self.bump();
+                    return;
+                }
+                None => return,
+                Some(_) => {
+                    let start = self.pos;
+                    self.value(format!("{}[{}]", path, index));
+                    index += 1;
+                    if self.pos == start {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Fixtures shared by the integration tests. Each test crate uses only some.
+#![allow(dead_code)]
+
+use aurora_kvm::config::{ClientConfig, Config, LocalScreen};
+use aurora_kvm::connected::create_connected_clients;
+use aurora_kvm::event::KvmEvent;
+use aurora_kvm::topology::{SharedTopology, create_shared_topology};
+
+/// One 1920x1080 local screen with a same-sized client, "laptop", to its right
+pub fn test_config() -> Config {
+    let mut config = Config::default();
+    let profile = config.active_mut();
+    profile.local_screens = vec![LocalScreen {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    }];
+    profile.clients = vec![ClientConfig {
+        name: "laptop".to_string(),
+        x: 1920,
+        ..Default::default()
+    }];
+    config
+}
+
+/// A topology with `test_config`'s layout
+pub fn test_topology() -> SharedTopology {
+    create_shared_topology(test_config(), create_connected_clients())
+}
+
+pub fn at(x: f64, y: f64) -> KvmEvent {
+    KvmEvent::MouseMove { x, y }
+}
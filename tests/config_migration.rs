@@ -0,0 +1,148 @@
+use aurora_kvm::config::{CONFIG_VERSION, Config, DEFAULT_PROFILE};
+use aurora_kvm::migrate;
+use std::path::{Path, PathBuf};
+
+fn fixture_path(version: u32) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(format!("config_v{}.json", version))
+}
+
+/// Copy a fixture into a fresh scratch directory so loading can rewrite it
+fn scratch_copy(version: u32, test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "aurora_kvm_{}_{}_v{}",
+        test,
+        std::process::id(),
+        version
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.json");
+    std::fs::copy(fixture_path(version), &path).unwrap();
+    path
+}
+
+#[test]
+fn every_historical_version_has_a_fixture() {
+    for version in 0..=CONFIG_VERSION {
+        assert!(
+            fixture_path(version).exists(),
+            "missing fixture for schema version {}",
+            version
+        );
+    }
+}
+
+#[test]
+fn fixtures_load_as_current_version() {
+    for version in 0..=CONFIG_VERSION {
+        let path = scratch_copy(version, "load");
+        let config = Config::load_from(&path)
+            .unwrap_or_else(|e| panic!("fixture v{} failed to load: {:#}", version, e));
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.secret.as_deref(), Some("my_secret_key"));
+        let profile = config.active();
+        assert_eq!(profile.local_screens.len(), 2);
+        assert_eq!(profile.clients.len(), 1);
+        assert_eq!(profile.clients[0].name, "Laptop");
+        assert_eq!(profile.clients[0].x, 3840);
+    }
+}
+
+#[test]
+fn single_layout_becomes_default_profile() {
+    for version in 0..2 {
+        let path = scratch_copy(version, "profiles");
+        let config = Config::load_from(&path).unwrap();
+
+        assert_eq!(config.active_profile, DEFAULT_PROFILE);
+        assert_eq!(config.profile_names(), vec![DEFAULT_PROFILE.to_string()]);
+    }
+}
+
+#[test]
+fn migration_rewrites_file_and_keeps_backup() {
+    for version in 0..CONFIG_VERSION {
+        let path = scratch_copy(version, "rewrite");
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        Config::load_from(&path).unwrap();
+
+        let backup = path.with_extension(format!("json.v{}.bak", version));
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), original);
+
+        let rewritten: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(migrate::schema_version(&rewritten).unwrap(), CONFIG_VERSION);
+    }
+}
+
+#[test]
+fn current_version_is_not_rewritten() {
+    let path = scratch_copy(CONFIG_VERSION, "current");
+    let original = std::fs::read_to_string(&path).unwrap();
+
+    Config::load_from(&path).unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    assert!(
+        !path
+            .with_extension(format!("json.v{}.bak", CONFIG_VERSION))
+            .exists()
+    );
+}
+
+#[test]
+fn newer_version_is_rejected() {
+    let mut value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fixture_path(CONFIG_VERSION)).unwrap())
+            .unwrap();
+    value["version"] = serde_json::Value::from(CONFIG_VERSION + 1);
+
+    assert!(migrate::migrate(&mut value).is_err());
+}
+
+#[test]
+fn json_schema_describes_config() {
+    let schema = aurora_kvm::config::json_schema();
+    let properties = schema["properties"].as_object().unwrap();
+
+    for field in ["version", "port", "active_profile", "profiles"] {
+        assert!(properties.contains_key(field), "schema lacks '{}'", field);
+    }
+}
+
+#[test]
+fn persisting_a_profile_keeps_other_edits() {
+    let path = scratch_copy(CONFIG_VERSION, "persist_profile");
+
+    // An edit on disk that the server hasn't reloaded yet
+    let mut edited = Config::load_from(&path).unwrap();
+    edited.port = 9000;
+    edited.save_to(&path).unwrap();
+
+    aurora_kvm::reload::persist_active_profile(&path, "travel").unwrap();
+    let saved = Config::load_from(&path).unwrap();
+    assert_eq!(saved.active_profile, "travel");
+    assert_eq!(saved.port, 9000);
+
+    assert!(aurora_kvm::reload::persist_active_profile(&path, "missing").is_err());
+}
+
+#[test]
+fn a_config_without_profiles_still_has_an_active_one() {
+    let mut config = Config {
+        profiles: Vec::new(),
+        ..Config::default()
+    };
+    assert_eq!(config.active().name, DEFAULT_PROFILE);
+    assert!(config.active().clients.is_empty());
+
+    config.active_mut().hotkey = Some("Ctrl+Alt+F1".to_string());
+    assert_eq!(config.profiles.len(), 1);
+    assert_eq!(config.active().hotkey.as_deref(), Some("Ctrl+Alt+F1"));
+}
@@ -0,0 +1,369 @@
+use aurora_kvm::config::{ClientConfig, Config, MacroConfig, MacroStep, Profile};
+use aurora_kvm::connected::{ConnectedClient, create_connected_clients};
+use aurora_kvm::control::{ControlState, handle_connection};
+use aurora_kvm::event::{Key, KvmEvent};
+use aurora_kvm::net::ScreenInfo;
+use aurora_kvm::pairing::create_pairing;
+use aurora_kvm::topology::{Focus, create_shared_topology};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines, ReadHalf, WriteHalf, duplex,
+};
+use tokio::sync::{Notify, broadcast};
+use tokio::time::timeout;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// A server with "laptop" connected to the right of its screen, a "travel"
+/// profile and an "unlock" macro for the laptop
+fn test_state() -> (ControlState, Arc<Notify>) {
+    let mut config = Config::default();
+    config.active_mut().clients = vec![ClientConfig {
+        name: "laptop".to_string(),
+        x: 1920,
+        ..Default::default()
+    }];
+    config.profiles.push(Profile {
+        name: "travel".to_string(),
+        ..Default::default()
+    });
+    config.macros = vec![MacroConfig {
+        name: "unlock".to_string(),
+        hotkey: None,
+        target: Some("laptop".to_string()),
+        steps: vec![MacroStep::Tap("Escape".to_string())],
+    }];
+
+    let connected_clients = create_connected_clients();
+    let kick = Arc::new(Notify::new());
+    let addr = "10.0.0.2:5000".parse().unwrap();
+    connected_clients.lock().unwrap().insert(
+        addr,
+        ConnectedClient {
+            addr,
+            screen_info: ScreenInfo {
+                width: 1920,
+                height: 1080,
+                x: 0,
+                y: 0,
+                name: "laptop".to_string(),
+                scale: 1.0,
+            },
+            kick: kick.clone(),
+        },
+    );
+
+    let state = ControlState {
+        port: 8080,
+        topology: create_shared_topology(config, connected_clients.clone()),
+        connected_clients,
+        pairing: create_pairing(),
+        tx: broadcast::channel(100).0,
+    };
+    (state, kick)
+}
+
+/// The client end of a control connection served by `handle_connection`
+struct Connection {
+    lines: Lines<BufReader<ReadHalf<DuplexStream>>>,
+    write: WriteHalf<DuplexStream>,
+}
+
+impl Connection {
+    fn open(state: &ControlState) -> Self {
+        let (client, server) = duplex(4096);
+        tokio::spawn(handle_connection(state.clone(), server));
+        let (read, write) = tokio::io::split(client);
+        Self {
+            lines: BufReader::new(read).lines(),
+            write,
+        }
+    }
+
+    async fn send_line(&mut self, line: &str) {
+        self.write.write_all(line.as_bytes()).await.unwrap();
+        self.write.write_all(b"\n").await.unwrap();
+    }
+
+    async fn next(&mut self) -> Value {
+        let line = timeout(Duration::from_secs(1), self.lines.next_line())
+            .await
+            .expect("no reply")
+            .unwrap()
+            .expect("connection closed");
+        serde_json::from_str(&line).unwrap()
+    }
+
+    async fn call(&mut self, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params });
+        self.send_line(&request.to_string()).await;
+        let response = self.next().await;
+        assert_eq!(response["id"], 7, "{}", response);
+        response
+    }
+
+    /// The result of a call expected to succeed
+    async fn ok(&mut self, method: &str, params: Value) -> Value {
+        let mut response = self.call(method, params).await;
+        assert!(response.get("error").is_none(), "{}", response);
+        response["result"].take()
+    }
+
+    /// The error code of a call expected to fail
+    async fn error(&mut self, method: &str, params: Value) -> i64 {
+        let response = self.call(method, params).await;
+        assert!(response.get("result").is_none(), "{}", response);
+        response["error"]["code"].as_i64().unwrap()
+    }
+}
+
+#[tokio::test]
+async fn queries_report_the_server_state() {
+    let (state, _kick) = test_state();
+    state
+        .pairing
+        .lock()
+        .unwrap()
+        .request("tablet", "10.0.0.3:5000".parse().unwrap());
+    let mut conn = Connection::open(&state);
+
+    let status = conn.ok("status", Value::Null).await;
+    assert_eq!(status["port"], 8080);
+    assert_eq!(status["active_profile"], "default");
+    assert_eq!(status["focus"], "local");
+    assert_eq!(status["focus_locked"], false);
+    assert_eq!(status["paused"], false);
+    assert_eq!(status["clients"], 1);
+
+    let clients = conn.ok("clients", Value::Null).await;
+    assert_eq!(clients["connected"][0]["name"], "laptop");
+    assert_eq!(clients["connected"][0]["addr"], "10.0.0.2:5000");
+    assert_eq!(
+        clients["pending"],
+        json!([{ "name": "tablet", "addr": "10.0.0.3:5000" }])
+    );
+
+    assert_eq!(
+        conn.ok("focus", Value::Null).await,
+        json!({ "focus": "local" })
+    );
+}
+
+#[tokio::test]
+async fn set_focus_moves_focus_to_connected_clients_only() {
+    let (state, _kick) = test_state();
+    let mut conn = Connection::open(&state);
+
+    assert_eq!(
+        conn.ok("set_focus", json!({ "target": "laptop" })).await,
+        json!({ "focus": "laptop" })
+    );
+    assert_eq!(
+        *state.topology.lock().unwrap().get_focus(),
+        Focus::Client("laptop".to_string())
+    );
+
+    assert_eq!(
+        conn.error("set_focus", json!({ "target": "phone" })).await,
+        SERVER_ERROR
+    );
+    assert_eq!(conn.error("set_focus", json!({})).await, INVALID_PARAMS);
+
+    conn.ok("set_focus", json!({ "target": "local" })).await;
+    assert_eq!(*state.topology.lock().unwrap().get_focus(), Focus::Local);
+}
+
+#[tokio::test]
+async fn lock_and_pause_toggle_or_take_a_boolean() {
+    let (state, _kick) = test_state();
+    let mut conn = Connection::open(&state);
+
+    let locked = conn.ok("lock_focus", Value::Null).await;
+    assert_eq!(locked, json!({ "focus_locked": true, "focus": "local" }));
+    let locked = conn.ok("lock_focus", json!({ "locked": true })).await;
+    assert_eq!(locked["focus_locked"], true);
+    assert_eq!(
+        conn.error("lock_focus", json!({ "locked": "yes" })).await,
+        INVALID_PARAMS
+    );
+    conn.ok("lock_focus", json!({ "locked": false })).await;
+
+    // Pausing brings focus home and keeps it there
+    conn.ok("set_focus", json!({ "target": "laptop" })).await;
+    let paused = conn.ok("set_paused", Value::Null).await;
+    assert_eq!(paused, json!({ "paused": true, "focus": "local" }));
+    assert_eq!(conn.ok("status", Value::Null).await["paused"], true);
+    assert_eq!(
+        conn.error("set_focus", json!({ "target": "laptop" })).await,
+        SERVER_ERROR
+    );
+    assert_eq!(
+        conn.error("run_macro", json!({ "name": "unlock" })).await,
+        SERVER_ERROR
+    );
+    assert_eq!(
+        conn.error("set_paused", json!({ "paused": 1 })).await,
+        INVALID_PARAMS
+    );
+
+    conn.ok("set_paused", json!({ "paused": false })).await;
+    conn.ok("set_focus", json!({ "target": "laptop" })).await;
+}
+
+#[tokio::test]
+async fn switch_profile_activates_known_profiles() {
+    let (state, _kick) = test_state();
+    let mut conn = Connection::open(&state);
+
+    assert_eq!(
+        conn.ok("switch_profile", json!({ "name": "travel" })).await,
+        json!({ "active_profile": "travel" })
+    );
+    assert_eq!(
+        state.topology.lock().unwrap().get_config().active_profile,
+        "travel"
+    );
+    assert_eq!(
+        conn.error("switch_profile", json!({ "name": "moon" }))
+            .await,
+        SERVER_ERROR
+    );
+    assert_eq!(
+        conn.error("switch_profile", json!({})).await,
+        INVALID_PARAMS
+    );
+}
+
+#[tokio::test]
+async fn kick_and_approve_pairing_act_on_clients_by_name() {
+    let (state, kick) = test_state();
+    let approval = state
+        .pairing
+        .lock()
+        .unwrap()
+        .request("tablet", "10.0.0.3:5000".parse().unwrap())
+        .unwrap();
+    let mut conn = Connection::open(&state);
+
+    assert_eq!(
+        conn.ok("kick", json!({ "name": "laptop" })).await,
+        json!({ "kicked": 1 })
+    );
+    timeout(Duration::from_secs(1), kick.notified())
+        .await
+        .unwrap();
+    assert_eq!(
+        conn.error("kick", json!({ "name": "phone" })).await,
+        SERVER_ERROR
+    );
+
+    assert_eq!(
+        conn.ok("approve_pairing", json!({ "name": "tablet" }))
+            .await,
+        json!({ "approved": "tablet" })
+    );
+    timeout(Duration::from_secs(1), approval)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        conn.error("approve_pairing", json!({ "name": "tablet" }))
+            .await,
+        SERVER_ERROR
+    );
+}
+
+#[tokio::test]
+async fn run_macro_sends_to_the_macro_target() {
+    let (state, _kick) = test_state();
+    let mut events = state.tx.subscribe();
+    let mut conn = Connection::open(&state);
+
+    assert_eq!(
+        conn.ok("run_macro", json!({ "name": "unlock" })).await,
+        json!({ "macro": "unlock", "target": "laptop" })
+    );
+    let event = timeout(Duration::from_secs(1), events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.client, "laptop");
+    assert_eq!(event.event, KvmEvent::key_press(Key::Escape));
+
+    assert_eq!(
+        conn.error("run_macro", json!({ "name": "missing" })).await,
+        SERVER_ERROR
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn reload_reads_the_config_file() {
+    let (state, _kick) = test_state();
+    let dir = std::env::temp_dir().join(format!("aurora_kvm_control_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("aurora_kvm")).unwrap();
+    let mut config = Config::default();
+    config.active_mut().clients = vec![ClientConfig {
+        name: "desktop".to_string(),
+        x: -1920,
+        ..Default::default()
+    }];
+    config
+        .save_to(&dir.join("aurora_kvm").join("config.json"))
+        .unwrap();
+    // No other test here reads the config path
+    unsafe { std::env::set_var("XDG_CONFIG_HOME", &dir) };
+    let mut conn = Connection::open(&state);
+
+    assert_eq!(
+        conn.ok("reload", Value::Null).await,
+        json!({ "reloaded": true })
+    );
+    let topo = state.topology.lock().unwrap();
+    assert_eq!(topo.get_config().active().clients[0].name, "desktop");
+}
+
+#[tokio::test]
+async fn bad_requests_get_errors_and_the_connection_lives_on() {
+    let (state, _kick) = test_state();
+    let mut conn = Connection::open(&state);
+
+    assert_eq!(conn.error("teleport", Value::Null).await, METHOD_NOT_FOUND);
+
+    conn.send_line("{ not json").await;
+    let response = conn.next().await;
+    assert_eq!(response["id"], Value::Null);
+    assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+    // Blank lines are skipped
+    conn.send_line("").await;
+    assert_eq!(conn.ok("focus", Value::Null).await["focus"], "local");
+}
+
+#[tokio::test]
+async fn subscribe_streams_focus_changes() {
+    let (state, _kick) = test_state();
+    let mut conn = Connection::open(&state);
+
+    assert_eq!(
+        conn.ok("subscribe", Value::Null).await,
+        json!({ "subscribed": true })
+    );
+    state
+        .topology
+        .lock()
+        .unwrap()
+        .set_focus(Focus::Client("laptop".to_string()));
+
+    let notification = conn.next().await;
+    assert_eq!(notification["method"], "event");
+    assert_eq!(
+        notification["params"],
+        json!({ "event": "focus_changed", "focus": "laptop" })
+    );
+}
@@ -0,0 +1,188 @@
+use aurora_kvm::config::{ClientConfig, Config, EdgePolicy, LocalScreen};
+use aurora_kvm::connected::create_connected_clients;
+use aurora_kvm::edge::{Edge, EdgeContact, EdgeState, decide};
+use aurora_kvm::topology::{Focus, create_shared_topology};
+use std::time::{Duration, Instant};
+
+/// Pushing against the middle of the right edge of a 1080 px tall screen
+const RIGHT: EdgeContact = EdgeContact {
+    edge: Edge::Right,
+    offset: 540.0,
+    length: 1080.0,
+};
+
+/// Feeds moves to `decide` at given millisecond offsets, like the router would
+struct Run {
+    policy: EdgePolicy,
+    state: EdgeState,
+    start: Instant,
+}
+
+impl Run {
+    fn new(policy: EdgePolicy) -> Self {
+        Self {
+            policy,
+            state: EdgeState::default(),
+            start: Instant::now(),
+        }
+    }
+
+    fn at(&mut self, ms: u64, contact: Option<EdgeContact>, dragging: bool) -> bool {
+        let now = self.start + Duration::from_millis(ms);
+        let (switch, state) = decide(&self.policy, &self.state, contact.as_ref(), dragging, now);
+        self.state = state;
+        switch
+    }
+
+    fn push(&mut self, ms: u64) -> bool {
+        self.at(ms, Some(RIGHT), false)
+    }
+
+    fn leave(&mut self, ms: u64) -> bool {
+        self.at(ms, None, false)
+    }
+}
+
+#[test]
+fn default_policy_switches_immediately() {
+    let mut run = Run::new(EdgePolicy::default());
+
+    assert!(run.push(0));
+}
+
+#[test]
+fn switch_delay_needs_the_cursor_to_stay_at_the_edge() {
+    let mut run = Run::new(EdgePolicy {
+        switch_delay_ms: 200,
+        ..Default::default()
+    });
+
+    assert!(!run.push(0));
+    assert!(!run.push(150));
+    // Leaving restarts the wait
+    assert!(!run.leave(180));
+    assert!(!run.push(250));
+    assert!(!run.push(400));
+    assert!(run.push(450));
+}
+
+#[test]
+fn double_tap_needs_two_pushes_within_the_window() {
+    let mut run = Run::new(EdgePolicy {
+        double_tap_ms: 400,
+        ..Default::default()
+    });
+
+    assert!(!run.push(0));
+    assert!(!run.push(50));
+    assert!(!run.leave(100));
+    assert!(run.push(300));
+
+    // Too slow: the second push only counts as a new first tap
+    let mut run = Run::new(EdgePolicy {
+        double_tap_ms: 400,
+        ..Default::default()
+    });
+    assert!(!run.push(0));
+    assert!(!run.leave(100));
+    assert!(!run.push(500));
+    assert!(!run.leave(600));
+    assert!(run.push(700));
+}
+
+#[test]
+fn double_tap_must_hit_the_same_edge() {
+    let mut run = Run::new(EdgePolicy {
+        double_tap_ms: 400,
+        ..Default::default()
+    });
+    let top = EdgeContact {
+        edge: Edge::Top,
+        offset: 960.0,
+        length: 1920.0,
+    };
+
+    assert!(!run.at(0, Some(top), false));
+    assert!(!run.leave(50));
+    assert!(!run.push(100));
+}
+
+#[test]
+fn double_tap_and_delay_combine() {
+    let mut run = Run::new(EdgePolicy {
+        double_tap_ms: 400,
+        switch_delay_ms: 100,
+        ..Default::default()
+    });
+
+    assert!(!run.push(0));
+    assert!(!run.leave(50));
+    assert!(!run.push(200));
+    assert!(run.push(300));
+}
+
+#[test]
+fn corner_guard_blocks_both_ends_of_an_edge() {
+    let mut run = Run::new(EdgePolicy {
+        corner_guard_px: 30,
+        ..Default::default()
+    });
+    let at = |offset| EdgeContact { offset, ..RIGHT };
+
+    assert!(!run.at(0, Some(at(10.0)), false));
+    assert!(!run.at(10, Some(at(1060.0)), false));
+    assert!(run.at(20, Some(at(30.0)), false));
+}
+
+#[test]
+fn dragging_blocks_switching_only_when_asked() {
+    let mut run = Run::new(EdgePolicy {
+        block_while_dragging: true,
+        ..Default::default()
+    });
+    assert!(!run.at(0, Some(RIGHT), true));
+    assert!(run.at(10, Some(RIGHT), false));
+
+    let mut run = Run::new(EdgePolicy::default());
+    assert!(run.at(0, Some(RIGHT), true));
+}
+
+#[test]
+fn topology_applies_per_edge_overrides() {
+    let mut config = Config::default();
+    config.edge_policy.corner_guard_px = 100;
+    config.edge_overrides.left = Some(EdgePolicy::default());
+    let profile = config.active_mut();
+    profile.local_screens = vec![LocalScreen {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    }];
+    profile.clients = vec![
+        ClientConfig {
+            name: "right".to_string(),
+            x: 1920,
+            ..Default::default()
+        },
+        ClientConfig {
+            name: "left".to_string(),
+            x: -1920,
+            ..Default::default()
+        },
+    ];
+    let topology = create_shared_topology(config, create_connected_clients());
+    let mut topo = topology.lock().unwrap();
+    let now = Instant::now();
+
+    // Near the top corner: guarded on the right, not on the left
+    assert_eq!(topo.check_edge(1925.0, 20.0, false, now), None);
+    assert_eq!(
+        topo.check_edge(-5.0, 20.0, false, now),
+        Some(Focus::Client("left".to_string()))
+    );
+    assert_eq!(
+        topo.check_edge(1925.0, 500.0, false, now),
+        Some(Focus::Client("right".to_string()))
+    );
+}
@@ -0,0 +1,291 @@
+use aurora_kvm::event::{Button, Key, KvmEvent};
+use aurora_kvm::input::evdev_map::{
+    ABS_X, ABS_Y, BUTTON_CODES, EV_ABS, EV_KEY, EV_REL, EV_SYN, EvdevEncoder, EvdevTranslator,
+    REL_HWHEEL, REL_HWHEEL_HI_RES, REL_WHEEL, REL_WHEEL_HI_RES, REL_X, REL_Y, RawEvent,
+    SYN_DROPPED, SYN_REPORT, key_code, key_from_code,
+};
+use aurora_kvm::scancode::{NO_USAGE, PHYSICAL_KEYS, usage_for_key};
+
+fn syn() -> RawEvent {
+    RawEvent::new(EV_SYN, SYN_REPORT, 0)
+}
+
+fn encode(event: &KvmEvent) -> Vec<RawEvent> {
+    EvdevEncoder::new().encode(event)
+}
+
+fn feed_all(translator: &mut EvdevTranslator, raw: &[RawEvent]) -> Vec<KvmEvent> {
+    raw.iter().flat_map(|r| translator.feed(*r)).collect()
+}
+
+#[test]
+fn every_physical_key_round_trips_through_evdev() {
+    let mut translator = EvdevTranslator::new();
+
+    for physical in PHYSICAL_KEYS {
+        let key = physical.key;
+        let press = KvmEvent::KeyPress {
+            key,
+            usage: physical.usage,
+        };
+        let release = KvmEvent::KeyRelease {
+            key,
+            usage: physical.usage,
+        };
+
+        let mut raw = encode(&press);
+        raw.extend(encode(&release));
+        assert_eq!(
+            raw,
+            vec![
+                RawEvent::new(EV_KEY, physical.evdev, 1),
+                RawEvent::new(EV_KEY, physical.evdev, 0)
+            ]
+        );
+
+        assert_eq!(
+            feed_all(&mut translator, &raw),
+            vec![press, release],
+            "usage {:#010x} did not round-trip",
+            physical.usage
+        );
+    }
+}
+
+#[test]
+fn function_key_round_trips_without_a_usage() {
+    let mut translator = EvdevTranslator::new();
+    let raw = encode(&KvmEvent::key_press(Key::Function));
+
+    assert_eq!(
+        feed_all(&mut translator, &raw),
+        vec![KvmEvent::KeyPress {
+            key: Key::Function,
+            usage: NO_USAGE
+        }]
+    );
+}
+
+#[test]
+fn usage_takes_precedence_over_the_logical_key() {
+    // An AZERTY server reports the key labelled A at the QWERTY Q position
+    let event = KvmEvent::KeyPress {
+        key: Key::KeyA,
+        usage: usage_for_key(Key::KeyQ),
+    };
+    assert_eq!(encode(&event), vec![RawEvent::new(EV_KEY, 16, 1)]);
+}
+
+#[test]
+fn every_button_round_trips_through_evdev() {
+    let mut translator = EvdevTranslator::new();
+
+    for (button, _) in BUTTON_CODES {
+        let mut raw = encode(&KvmEvent::ButtonPress(*button));
+        raw.extend(encode(&KvmEvent::ButtonRelease(*button)));
+
+        assert_eq!(
+            feed_all(&mut translator, &raw),
+            vec![
+                KvmEvent::ButtonPress(*button),
+                KvmEvent::ButtonRelease(*button)
+            ]
+        );
+    }
+}
+
+#[test]
+fn unnamed_key_codes_are_kept_as_unknown() {
+    assert_eq!(key_from_code(240), (Key::Unknown(240), NO_USAGE));
+    assert_eq!(key_code(Key::Unknown(240), NO_USAGE), None);
+}
+
+#[test]
+fn autorepeat_is_reported_as_press() {
+    let mut translator = EvdevTranslator::new();
+    let events = feed_all(
+        &mut translator,
+        &[
+            RawEvent::new(EV_KEY, 30, 1),
+            RawEvent::new(EV_KEY, 30, 2),
+            RawEvent::new(EV_KEY, 30, 0),
+        ],
+    );
+
+    assert_eq!(
+        events,
+        vec![
+            KvmEvent::key_press(Key::KeyA),
+            KvmEvent::key_press(Key::KeyA),
+            KvmEvent::key_release(Key::KeyA)
+        ]
+    );
+}
+
+#[test]
+fn relative_motion_is_accumulated_per_frame() {
+    let mut translator = EvdevTranslator::new();
+    translator.set_cursor(100.0, 100.0);
+
+    let events = feed_all(
+        &mut translator,
+        &[
+            RawEvent::new(EV_REL, REL_X, 5),
+            RawEvent::new(EV_REL, REL_Y, -3),
+            syn(),
+            RawEvent::new(EV_REL, REL_X, 10),
+            syn(),
+        ],
+    );
+
+    assert_eq!(
+        events,
+        vec![
+            KvmEvent::MouseMove { x: 105.0, y: 97.0 },
+            KvmEvent::MouseMove { x: 115.0, y: 97.0 }
+        ]
+    );
+    assert_eq!(translator.cursor(), (115.0, 97.0));
+}
+
+#[test]
+fn wheel_is_reported_once_per_frame() {
+    let mut translator = EvdevTranslator::new();
+    let events = feed_all(
+        &mut translator,
+        &[
+            RawEvent::new(EV_REL, REL_WHEEL, 1),
+            RawEvent::new(EV_REL, REL_HWHEEL, -1),
+            syn(),
+        ],
+    );
+
+    assert_eq!(
+        events,
+        vec![KvmEvent::Wheel {
+            delta_x: -1.0,
+            delta_y: 1.0
+        }]
+    );
+}
+
+#[test]
+fn hi_res_wheel_replaces_notches_in_the_same_frame() {
+    let mut translator = EvdevTranslator::new();
+    let events = feed_all(
+        &mut translator,
+        &[
+            RawEvent::new(EV_REL, REL_WHEEL_HI_RES, 30),
+            syn(),
+            RawEvent::new(EV_REL, REL_WHEEL_HI_RES, 90),
+            RawEvent::new(EV_REL, REL_WHEEL, 1),
+            syn(),
+        ],
+    );
+
+    assert_eq!(
+        events,
+        vec![
+            KvmEvent::Wheel {
+                delta_x: 0.0,
+                delta_y: 0.25
+            },
+            KvmEvent::Wheel {
+                delta_x: 0.0,
+                delta_y: 0.75
+            }
+        ]
+    );
+}
+
+#[test]
+fn fractional_wheel_sends_notches_once_they_add_up() {
+    let mut encoder = EvdevEncoder::new();
+    let wheel = KvmEvent::Wheel {
+        delta_x: -0.5,
+        delta_y: 0.25,
+    };
+
+    assert_eq!(
+        encoder.encode(&wheel),
+        vec![
+            RawEvent::new(EV_REL, REL_HWHEEL_HI_RES, -60),
+            RawEvent::new(EV_REL, REL_WHEEL_HI_RES, 30)
+        ]
+    );
+    encoder.encode(&wheel);
+    encoder.encode(&wheel);
+    assert_eq!(
+        encoder.encode(&wheel),
+        vec![
+            RawEvent::new(EV_REL, REL_HWHEEL_HI_RES, -60),
+            RawEvent::new(EV_REL, REL_HWHEEL, -1),
+            RawEvent::new(EV_REL, REL_WHEEL_HI_RES, 30),
+            RawEvent::new(EV_REL, REL_WHEEL, 1)
+        ]
+    );
+}
+
+#[test]
+fn thumb_buttons_map_from_both_code_pairs() {
+    let mut translator = EvdevTranslator::new();
+    // BTN_SIDE and BTN_BACK are both "back"
+    for code in [0x113, 0x116] {
+        assert_eq!(
+            translator.feed(RawEvent::new(EV_KEY, code, 1)),
+            vec![KvmEvent::ButtonPress(Button::X1)]
+        );
+    }
+    assert_eq!(
+        encode(&KvmEvent::ButtonPress(Button::X2)),
+        vec![RawEvent::new(EV_KEY, 0x114, 1)]
+    );
+}
+
+#[test]
+fn dropped_frames_are_discarded() {
+    let mut translator = EvdevTranslator::new();
+    let events = feed_all(
+        &mut translator,
+        &[
+            RawEvent::new(EV_REL, REL_X, 50),
+            RawEvent::new(EV_SYN, SYN_DROPPED, 0),
+            syn(),
+        ],
+    );
+
+    assert!(events.is_empty());
+    assert_eq!(translator.cursor(), (0.0, 0.0));
+}
+
+#[test]
+fn touch_buttons_are_ignored() {
+    // BTN_TOUCH from a touchpad
+    let mut translator = EvdevTranslator::new();
+    assert!(translator.feed(RawEvent::new(EV_KEY, 0x14a, 1)).is_empty());
+}
+
+#[test]
+fn pointer_events_encode_for_uinput() {
+    assert_eq!(
+        encode(&KvmEvent::MouseMove { x: 10.4, y: 20.6 }),
+        vec![
+            RawEvent::new(EV_ABS, ABS_X, 10),
+            RawEvent::new(EV_ABS, ABS_Y, 21)
+        ]
+    );
+    assert_eq!(
+        encode(&KvmEvent::Wheel {
+            delta_x: 0.0,
+            delta_y: -2.0
+        }),
+        vec![
+            RawEvent::new(EV_REL, REL_WHEEL_HI_RES, -240),
+            RawEvent::new(EV_REL, REL_WHEEL, -2)
+        ]
+    );
+    assert_eq!(
+        encode(&KvmEvent::ButtonPress(Button::Left)),
+        vec![RawEvent::new(EV_KEY, 0x110, 1)]
+    );
+}
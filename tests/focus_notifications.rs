@@ -0,0 +1,75 @@
+mod common;
+
+use aurora_kvm::config::Config;
+use aurora_kvm::connected::create_connected_clients;
+use aurora_kvm::event::{Button, Key, KvmEvent};
+use aurora_kvm::input::held::HeldInput;
+use aurora_kvm::input::router::InputRouter;
+use aurora_kvm::topology::{Focus, SharedTopology, create_shared_topology};
+use tokio::sync::broadcast;
+
+/// `common::test_topology`, optionally in jump mode
+fn test_topology(jump_mode: bool) -> SharedTopology {
+    let config = Config {
+        jump_mode,
+        ..common::test_config()
+    };
+    create_shared_topology(config, create_connected_clients())
+}
+
+#[test]
+fn edge_crossing_enters_where_the_cursor_crossed() {
+    let topology = test_topology(false);
+    let (tx, _rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology.clone(), tx);
+
+    router.handle(KvmEvent::MouseMove {
+        x: 1900.0,
+        y: 400.0,
+    });
+    router.handle(KvmEvent::MouseMove {
+        x: 1925.0,
+        y: 400.0,
+    });
+
+    assert_eq!(
+        topology.lock().unwrap().entry_point("laptop"),
+        Some((5.0, 400.0))
+    );
+}
+
+#[test]
+fn direct_focus_enters_in_the_middle_or_where_jump_mode_left_off() {
+    let topology = test_topology(false);
+    let mut topo = topology.lock().unwrap();
+    topo.remember_cursor("laptop", (2000.0, 100.0));
+    topo.set_focus(Focus::Client("laptop".to_string()));
+    assert_eq!(topo.entry_point("laptop"), Some((960.0, 540.0)));
+
+    let topology = test_topology(true);
+    let mut topo = topology.lock().unwrap();
+    topo.remember_cursor("laptop", (5000.0, 100.0));
+    topo.set_focus(Focus::Client("laptop".to_string()));
+    // Clamped to the laptop's screen
+    assert_eq!(topo.entry_point("laptop"), Some((1919.0, 100.0)));
+    assert_eq!(topo.entry_point("desktop"), None);
+}
+
+#[test]
+fn held_input_is_released_once() {
+    let mut held = HeldInput::new();
+    held.track(&KvmEvent::key_press(Key::ShiftLeft));
+    held.track(&KvmEvent::key_press(Key::KeyA));
+    held.track(&KvmEvent::key_release(Key::KeyA));
+    held.track(&KvmEvent::ButtonPress(Button::Left));
+    held.track(&KvmEvent::MouseMove { x: 1.0, y: 2.0 });
+
+    assert_eq!(
+        held.release_all(),
+        vec![
+            KvmEvent::ButtonRelease(Button::Left),
+            KvmEvent::key_release(Key::ShiftLeft),
+        ]
+    );
+    assert!(held.release_all().is_empty());
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use aurora_kvm::config::{Config, FocusOverlay};
+use aurora_kvm::edge::Edge;
+use aurora_kvm::input::router::InputRouter;
+use aurora_kvm::overlay::{self, FocusNotice};
+use aurora_kvm::topology::Focus;
+use common::{at, test_topology};
+use tokio::sync::broadcast;
+
+#[test]
+fn arrows_point_the_way_the_cursor_came_in() {
+    assert_eq!(overlay::arrow(Some(Edge::Left)), "→");
+    assert_eq!(overlay::arrow(Some(Edge::Bottom)), "↑");
+    assert_eq!(overlay::arrow(None), "");
+}
+
+#[test]
+fn local_notices_follow_focus_and_the_edge_crossed() {
+    let topology = test_topology();
+    let mut notices = overlay::local_notices(&topology);
+    let (tx, _rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology.clone(), tx);
+
+    // Nothing to flash until focus moves; the server starts out focused
+    assert!(notices.try_recv().is_err());
+
+    // Onto the laptop and back across its left edge
+    router.handle(at(1900.0, 500.0));
+    router.handle(at(1925.0, 500.0));
+    assert_eq!(notices.blocking_recv(), Some(FocusNotice::Lost));
+    router.handle(at(1915.0, 500.0));
+    router.handle(at(1900.0, 500.0));
+    assert_eq!(
+        notices.blocking_recv(),
+        Some(FocusNotice::Gained {
+            edge: Some(Edge::Right)
+        })
+    );
+
+    // A hotkey or control API switch has no edge
+    let mut topo = topology.lock().unwrap();
+    topo.set_focus(Focus::Client("laptop".to_string()));
+    topo.set_focus(Focus::Local);
+    drop(topo);
+    assert_eq!(notices.blocking_recv(), Some(FocusNotice::Lost));
+    assert_eq!(
+        notices.blocking_recv(),
+        Some(FocusNotice::Gained { edge: None })
+    );
+}
+
+#[test]
+fn overlay_is_off_by_default() {
+    let config: Config =
+        serde_json::from_str(r#"{ "port": 8080, "secret": null, "input_grab_hotkey": null }"#)
+            .unwrap();
+    assert_eq!(config.focus_overlay, FocusOverlay::default());
+    assert!(!config.focus_overlay.enabled);
+    assert_eq!(config.focus_overlay.flash_ms, 800);
+}
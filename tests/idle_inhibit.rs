@@ -0,0 +1,82 @@
+use anyhow::{Result, bail};
+use aurora_kvm::inhibit::{IdleInhibitor, KeepAwake, keep_awake_in_background};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Records calls, failing `inhibit` while `fail` is set
+#[derive(Default, Clone)]
+struct RecordingInhibitor {
+    calls: Arc<Mutex<Vec<&'static str>>>,
+    fail: Arc<Mutex<bool>>,
+}
+
+impl IdleInhibitor for RecordingInhibitor {
+    fn inhibit(&mut self) -> Result<()> {
+        if *self.fail.lock().unwrap() {
+            bail!("no D-Bus");
+        }
+        self.calls.lock().unwrap().push("inhibit");
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        self.calls.lock().unwrap().push("release");
+    }
+}
+
+#[test]
+fn inhibitor_is_held_once_while_focused() {
+    let inhibitor = RecordingInhibitor::default();
+    let mut awake = KeepAwake::new(Box::new(inhibitor.clone()));
+
+    awake.set_focused(false);
+    awake.set_focused(true);
+    awake.set_focused(true);
+    awake.set_focused(false);
+    awake.set_focused(false);
+
+    assert_eq!(*inhibitor.calls.lock().unwrap(), vec!["inhibit", "release"]);
+}
+
+#[test]
+fn inhibitor_is_released_when_the_connection_goes() {
+    let inhibitor = RecordingInhibitor::default();
+    let mut awake = KeepAwake::new(Box::new(inhibitor.clone()));
+
+    awake.set_focused(true);
+    drop(awake);
+
+    assert_eq!(*inhibitor.calls.lock().unwrap(), vec!["inhibit", "release"]);
+}
+
+#[test]
+fn failed_inhibit_is_retried_on_the_next_focus() {
+    let inhibitor = RecordingInhibitor::default();
+    *inhibitor.fail.lock().unwrap() = true;
+    let mut awake = KeepAwake::new(Box::new(inhibitor.clone()));
+
+    awake.set_focused(true);
+    // Nothing to release
+    awake.set_focused(false);
+    *inhibitor.fail.lock().unwrap() = false;
+    awake.set_focused(true);
+
+    assert_eq!(*inhibitor.calls.lock().unwrap(), vec!["inhibit"]);
+}
+
+#[test]
+fn background_inhibitor_follows_focus_and_releases_when_closed() {
+    let inhibitor = RecordingInhibitor::default();
+    let calls = inhibitor.calls.clone();
+    let awake = keep_awake_in_background(move || Some(Box::new(inhibitor) as _));
+
+    awake.send(true).unwrap();
+    awake.send(true).unwrap();
+    drop(awake);
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while calls.lock().unwrap().len() < 2 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(*calls.lock().unwrap(), vec!["inhibit", "release"]);
+}
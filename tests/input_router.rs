@@ -0,0 +1,300 @@
+mod common;
+
+use aurora_kvm::config::Profile;
+use aurora_kvm::event::{Button, ClientEvent, Key, KvmEvent};
+use aurora_kvm::events::ServerEvent;
+use aurora_kvm::input::mock::{MockCapture, RecordingInjector};
+use aurora_kvm::input::router::InputRouter;
+use aurora_kvm::input::{Disposition, InputCapture, InputInjector};
+use aurora_kvm::topology::{Focus, SharedTopology};
+use common::test_topology;
+use tokio::sync::broadcast;
+
+/// Run `events` through a router via the mock capture backend
+fn run_capture(
+    topology: &SharedTopology,
+    events: Vec<KvmEvent>,
+) -> (Vec<Disposition>, broadcast::Receiver<ClientEvent>) {
+    let (tx, rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology.clone(), tx);
+
+    let capture = Box::new(MockCapture::new(events));
+    let dispositions = capture.dispositions();
+    capture
+        .run(Box::new(move |event| router.handle(event)))
+        .unwrap();
+
+    let dispositions = dispositions.lock().unwrap().clone();
+    (dispositions, rx)
+}
+
+#[test]
+fn local_events_pass_through() {
+    let topology = test_topology();
+    let (dispositions, mut rx) = run_capture(
+        &topology,
+        vec![
+            KvmEvent::MouseMove { x: 100.0, y: 100.0 },
+            KvmEvent::key_press(Key::KeyA),
+            KvmEvent::key_release(Key::KeyA),
+        ],
+    );
+
+    assert_eq!(dispositions, vec![Disposition::Pass; 3]);
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn crossing_an_edge_focuses_the_client() {
+    let topology = test_topology();
+    let (dispositions, mut rx) = run_capture(
+        &topology,
+        vec![
+            KvmEvent::MouseMove {
+                x: 1919.0,
+                y: 500.0,
+            },
+            KvmEvent::MouseMove {
+                x: 1925.0,
+                y: 500.0,
+            },
+            KvmEvent::key_press(Key::KeyA),
+        ],
+    );
+
+    assert_eq!(
+        dispositions,
+        vec![Disposition::Pass, Disposition::Pass, Disposition::Swallow]
+    );
+    assert_eq!(
+        *topology.lock().unwrap().get_focus(),
+        Focus::Client("laptop".to_string())
+    );
+    assert_eq!(
+        rx.try_recv().unwrap(),
+        ClientEvent {
+            client: "laptop".to_string(),
+            event: KvmEvent::key_press(Key::KeyA),
+        }
+    );
+}
+
+#[test]
+fn moving_back_inside_returns_focus() {
+    let topology = test_topology();
+    let (dispositions, _rx) = run_capture(
+        &topology,
+        vec![
+            KvmEvent::MouseMove {
+                x: 1925.0,
+                y: 500.0,
+            },
+            KvmEvent::MouseMove {
+                x: 2500.0,
+                y: 500.0,
+            },
+            KvmEvent::MouseMove {
+                x: 1900.0,
+                y: 500.0,
+            },
+        ],
+    );
+
+    assert_eq!(
+        dispositions,
+        vec![Disposition::Pass, Disposition::Swallow, Disposition::Pass]
+    );
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+}
+
+#[test]
+fn forwarded_events_reach_the_injector() {
+    let topology = test_topology();
+    let events = vec![
+        KvmEvent::MouseMove {
+            x: 1925.0,
+            y: 500.0,
+        },
+        KvmEvent::MouseMove {
+            x: 2000.0,
+            y: 600.0,
+        },
+        KvmEvent::Wheel {
+            delta_x: 0.0,
+            delta_y: -1.0,
+        },
+    ];
+    let (_, mut rx) = run_capture(&topology, events.clone());
+
+    let mut injector = RecordingInjector::new();
+    while let Ok(ClientEvent { event, .. }) = rx.try_recv() {
+        injector.inject(&event).unwrap();
+    }
+
+    // The client gets positions relative to its own top-left corner
+    assert_eq!(
+        injector.events(),
+        vec![KvmEvent::MouseMove { x: 80.0, y: 600.0 }, events[2].clone()]
+    );
+}
+
+#[test]
+fn scrolling_follows_the_client_settings() {
+    let topology = test_topology();
+    {
+        let mut topo = topology.lock().unwrap();
+        let mut config = topo.get_config().clone();
+        let client = &mut config.active_mut().clients[0];
+        client.invert_scroll = true;
+        client.scroll_speed = 2.5;
+        topo.update_config(config);
+    }
+    let (_, mut rx) = run_capture(
+        &topology,
+        vec![
+            KvmEvent::MouseMove {
+                x: 1925.0,
+                y: 500.0,
+            },
+            KvmEvent::Wheel {
+                delta_x: 0.25,
+                delta_y: -1.0,
+            },
+        ],
+    );
+
+    assert_eq!(
+        rx.try_recv().unwrap().event,
+        KvmEvent::Wheel {
+            delta_x: -0.625,
+            delta_y: 2.5
+        }
+    );
+}
+
+#[test]
+fn dragging_can_keep_focus_local() {
+    let topology = test_topology();
+    {
+        let mut topo = topology.lock().unwrap();
+        let mut config = topo.get_config().clone();
+        config.edge_policy.block_while_dragging = true;
+        topo.update_config(config);
+    }
+    let (dispositions, _rx) = run_capture(
+        &topology,
+        vec![
+            KvmEvent::ButtonPress(Button::Left),
+            KvmEvent::MouseMove {
+                x: 1925.0,
+                y: 500.0,
+            },
+            KvmEvent::ButtonRelease(Button::Left),
+            KvmEvent::MouseMove {
+                x: 1925.0,
+                y: 500.0,
+            },
+        ],
+    );
+
+    assert_eq!(dispositions, vec![Disposition::Pass; 4]);
+    assert_eq!(
+        *topology.lock().unwrap().get_focus(),
+        Focus::Client("laptop".to_string())
+    );
+}
+
+#[test]
+fn focus_lock_pins_focus_in_both_directions() {
+    let topology = test_topology();
+    {
+        let mut topo = topology.lock().unwrap();
+        let mut config = topo.get_config().clone();
+        config.focus_lock_hotkey = Some("ScrollLock".to_string());
+        topo.update_config(config);
+    }
+    let (dispositions, _rx) = run_capture(
+        &topology,
+        vec![
+            KvmEvent::key_press(Key::ScrollLock),
+            // Autorepeat must not toggle it back
+            KvmEvent::key_press(Key::ScrollLock),
+            KvmEvent::key_release(Key::ScrollLock),
+            KvmEvent::MouseMove {
+                x: 1925.0,
+                y: 500.0,
+            },
+        ],
+    );
+
+    assert_eq!(
+        dispositions,
+        vec![
+            Disposition::Swallow,
+            Disposition::Swallow,
+            Disposition::Swallow,
+            Disposition::Pass
+        ]
+    );
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+
+    // Locked on a client, moving 1000 px left from its centre stays on its edge
+    topology
+        .lock()
+        .unwrap()
+        .set_focus(Focus::Client("laptop".to_string()));
+    let moves = vec![
+        KvmEvent::MouseMove {
+            x: 1000.0,
+            y: 500.0,
+        },
+        KvmEvent::MouseMove { x: 0.0, y: 500.0 },
+    ];
+    let (dispositions, mut rx) = run_capture(&topology, moves.clone());
+    assert_eq!(dispositions, vec![Disposition::Swallow; 2]);
+    assert_eq!(
+        rx.try_recv().unwrap().event,
+        KvmEvent::MouseMove { x: 0.0, y: 540.0 }
+    );
+
+    topology.lock().unwrap().set_focus_locked(false);
+    let (dispositions, _rx) = run_capture(&topology, moves);
+    assert_eq!(dispositions, vec![Disposition::Swallow, Disposition::Pass]);
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+}
+
+#[test]
+fn profile_hotkeys_swallow_autorepeat_and_release() {
+    let topology = test_topology();
+    {
+        let mut topo = topology.lock().unwrap();
+        let mut config = topo.get_config().clone();
+        config.profiles.push(Profile {
+            name: "desk".to_string(),
+            hotkey: Some("F9".to_string()),
+            ..Default::default()
+        });
+        topo.update_config(config);
+    }
+    let mut events = topology.lock().unwrap().subscribe();
+    let (dispositions, _rx) = run_capture(
+        &topology,
+        vec![
+            KvmEvent::key_press(Key::F9),
+            KvmEvent::key_press(Key::F9),
+            KvmEvent::key_release(Key::F9),
+        ],
+    );
+
+    assert_eq!(dispositions, vec![Disposition::Swallow; 3]);
+    assert_eq!(topology.lock().unwrap().get_config().active_profile, "desk");
+    // Switched once, despite the autorepeat
+    let mut switches = 0;
+    while let Ok(event) = events.try_recv() {
+        if matches!(event, ServerEvent::ProfileChanged { .. }) {
+            switches += 1;
+        }
+    }
+    assert_eq!(switches, 1);
+}
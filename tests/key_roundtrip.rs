@@ -0,0 +1,94 @@
+use aurora_kvm::event::{Key, KvmEvent, MACOS_VK_CODES, WINDOWS_VK_CODES};
+use aurora_kvm::input::evdev_map::{EvdevEncoder, EvdevTranslator};
+use aurora_kvm::scancode::{NO_USAGE, usage_for_key};
+use std::collections::HashSet;
+
+/// Every `Key` variant, found by decoding successive bincode variant indices
+fn every_variant() -> Vec<Key> {
+    let mut keys = Vec::new();
+    for index in 0u32.. {
+        // Variant index followed by a zeroed payload for `Unknown(u32)`
+        let mut bytes = index.to_le_bytes().to_vec();
+        bytes.extend([0; 4]);
+        match bincode::deserialize::<Key>(&bytes) {
+            Ok(key) => keys.push(key),
+            Err(_) => break,
+        }
+    }
+    keys
+}
+
+#[test]
+fn all_lists_every_named_variant() {
+    let listed: HashSet<Key> = Key::ALL.iter().copied().collect();
+    assert_eq!(listed.len(), Key::ALL.len(), "Key::ALL has duplicates");
+
+    for key in every_variant() {
+        if !matches!(key, Key::Unknown(_)) {
+            assert!(listed.contains(&key), "{:?} is missing from Key::ALL", key);
+        }
+    }
+}
+
+#[test]
+fn every_key_survives_the_wire() {
+    for key in Key::ALL {
+        for event in [KvmEvent::key_press(*key), KvmEvent::key_release(*key)] {
+            let bytes = bincode::serialize(&event).unwrap();
+            let decoded: KvmEvent = bincode::deserialize(&bytes).unwrap();
+            assert_eq!(decoded, event);
+        }
+    }
+}
+
+#[test]
+fn platform_code_tables_round_trip() {
+    for (platform, table) in [("Windows", WINDOWS_VK_CODES), ("macOS", MACOS_VK_CODES)] {
+        for (key, code) in table {
+            let by_code: Vec<Key> = table
+                .iter()
+                .filter(|(_, c)| c == code)
+                .map(|(k, _)| *k)
+                .collect();
+            assert_eq!(by_code, vec![*key], "{} code {:#x}", platform, code);
+            let by_key = table.iter().filter(|(k, _)| k == key).count();
+            assert_eq!(by_key, 1, "{} lists {:?} more than once", platform, key);
+        }
+    }
+}
+
+// Elsewhere rdev only has virtual-key codes, which some keys lack; those are
+// injected by scancode instead
+#[cfg(target_os = "linux")]
+#[test]
+fn every_key_survives_rdev_conversion() {
+    for key in Key::ALL {
+        let rdev_key = rdev::Key::from(*key);
+        assert_eq!(Key::from(rdev_key), *key, "via {:?}", rdev_key);
+    }
+}
+
+#[test]
+fn every_key_survives_the_evdev_backend() {
+    let mut encoder = EvdevEncoder::new();
+    let mut translator = EvdevTranslator::new();
+
+    for key in Key::ALL {
+        let event = KvmEvent::key_press(*key);
+        let decoded: Vec<KvmEvent> = encoder
+            .encode(&event)
+            .into_iter()
+            .flat_map(|raw| translator.feed(raw))
+            .collect();
+        assert_eq!(decoded, vec![event], "{:?}", key);
+    }
+}
+
+#[test]
+fn every_key_but_fn_has_a_physical_position() {
+    for key in Key::ALL {
+        if *key != Key::Function {
+            assert_ne!(usage_for_key(*key), NO_USAGE, "{:?}", key);
+        }
+    }
+}
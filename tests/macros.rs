@@ -0,0 +1,259 @@
+use aurora_kvm::config::{ClientConfig, Config, MacroConfig, MacroStep};
+use aurora_kvm::connected::create_connected_clients;
+use aurora_kvm::event::{ClientEvent, Key, KvmEvent};
+use aurora_kvm::input::Disposition;
+use aurora_kvm::input::router::InputRouter;
+use aurora_kvm::macros::{self, MacroAction};
+use aurora_kvm::topology::{Focus, SharedTopology, create_shared_topology};
+use aurora_kvm::validate::{Severity, validate};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+fn send(event: KvmEvent) -> MacroAction {
+    MacroAction::Send(event)
+}
+
+fn tap(keys: &str) -> MacroStep {
+    MacroStep::Tap(keys.to_string())
+}
+
+/// Two clients, `mac` focused, with a terminal macro on Ctrl+Alt+T and an
+/// unlock macro that always goes to `linux`
+fn test_topology() -> SharedTopology {
+    let mut config = Config::default();
+    config.active_mut().clients = vec![
+        ClientConfig {
+            name: "mac".to_string(),
+            x: 1920,
+            ..Default::default()
+        },
+        ClientConfig {
+            name: "linux".to_string(),
+            x: -1920,
+            ..Default::default()
+        },
+    ];
+    config.macros = vec![
+        MacroConfig {
+            name: "terminal".to_string(),
+            hotkey: Some("Ctrl+Alt+T".to_string()),
+            target: None,
+            steps: vec![tap("Meta+Return")],
+        },
+        MacroConfig {
+            name: "unlock".to_string(),
+            hotkey: None,
+            target: Some("linux".to_string()),
+            steps: vec![tap("Escape"), MacroStep::Text("hunter2".to_string())],
+        },
+    ];
+    let topology = create_shared_topology(config, create_connected_clients());
+    topology
+        .lock()
+        .unwrap()
+        .set_focus(Focus::Client("mac".to_string()));
+    topology
+}
+
+fn recv(rx: &mut broadcast::Receiver<ClientEvent>, count: usize) -> Vec<ClientEvent> {
+    (0..count).map(|_| rx.blocking_recv().unwrap()).collect()
+}
+
+fn to(client: &str, event: KvmEvent) -> ClientEvent {
+    ClientEvent {
+        client: client.to_string(),
+        event,
+    }
+}
+
+#[test]
+fn chords_press_modifiers_first_and_release_them_last() {
+    assert_eq!(
+        macros::expand(&[tap("Ctrl+Shift+T")]).unwrap(),
+        vec![
+            send(KvmEvent::key_press(Key::ControlLeft)),
+            send(KvmEvent::key_press(Key::ShiftLeft)),
+            send(KvmEvent::key_press(Key::KeyT)),
+            send(KvmEvent::key_release(Key::KeyT)),
+            send(KvmEvent::key_release(Key::ShiftLeft)),
+            send(KvmEvent::key_release(Key::ControlLeft)),
+        ]
+    );
+}
+
+#[test]
+fn steps_expand_in_order_with_delays() {
+    let steps = vec![
+        MacroStep::Press("ShiftLeft".to_string()),
+        MacroStep::DelayMs(250),
+        MacroStep::Release("ShiftLeft".to_string()),
+        MacroStep::Text("hi".to_string()),
+    ];
+
+    assert_eq!(
+        macros::expand(&steps).unwrap(),
+        vec![
+            send(KvmEvent::key_press(Key::ShiftLeft)),
+            MacroAction::Wait(Duration::from_millis(250)),
+            send(KvmEvent::key_release(Key::ShiftLeft)),
+            send(KvmEvent::Text("hi".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn hotkey_runs_the_macro_on_the_focused_client_and_is_swallowed() {
+    let topology = test_topology();
+    let (tx, mut rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology, tx);
+
+    router.handle(KvmEvent::key_press(Key::ControlLeft));
+    assert_eq!(
+        router.handle(KvmEvent::key_press(Key::Alt)),
+        Disposition::Swallow
+    );
+    // Both modifiers were forwarded as usual
+    recv(&mut rx, 2);
+
+    assert_eq!(
+        router.handle(KvmEvent::key_press(Key::KeyT)),
+        Disposition::Swallow
+    );
+    let mut events = recv(&mut rx, 6);
+    // The held modifiers come up first, in any order
+    let mut released: Vec<ClientEvent> = events.drain(..2).collect();
+    released.sort_by_key(|e| format!("{:?}", e));
+    assert_eq!(
+        released,
+        vec![
+            to("mac", KvmEvent::key_release(Key::Alt)),
+            to("mac", KvmEvent::key_release(Key::ControlLeft)),
+        ]
+    );
+    assert_eq!(
+        events,
+        vec![
+            to("mac", KvmEvent::key_press(Key::MetaLeft)),
+            to("mac", KvmEvent::key_press(Key::Return)),
+            to("mac", KvmEvent::key_release(Key::Return)),
+            to("mac", KvmEvent::key_release(Key::MetaLeft)),
+        ]
+    );
+
+    // Autorepeat and release of the trigger key go nowhere
+    router.handle(KvmEvent::key_press(Key::KeyT));
+    router.handle(KvmEvent::key_release(Key::KeyT));
+    router.handle(KvmEvent::key_release(Key::Alt));
+    assert_eq!(
+        rx.blocking_recv().unwrap(),
+        to("mac", KvmEvent::key_release(Key::Alt))
+    );
+}
+
+#[test]
+fn targeted_macro_ignores_focus() {
+    let topology = test_topology();
+    let (tx, mut rx) = broadcast::channel(100);
+
+    let client = macros::start(&topology.lock().unwrap(), &tx, "unlock", &HashSet::new());
+    assert_eq!(client.unwrap(), "linux");
+    assert_eq!(
+        recv(&mut rx, 3),
+        vec![
+            to("linux", KvmEvent::key_press(Key::Escape)),
+            to("linux", KvmEvent::key_release(Key::Escape)),
+            to("linux", KvmEvent::Text("hunter2".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn long_macros_are_paced_to_fit_the_channel() {
+    let topology = test_topology();
+    let mut config = topology.lock().unwrap().get_config().clone();
+    config.macros[1].steps = (0..100).map(|_| tap("Ctrl+A")).collect();
+    topology.lock().unwrap().update_config(config);
+    // Far smaller than the 400 events the macro sends
+    let (tx, mut rx) = broadcast::channel(16);
+
+    macros::start(&topology.lock().unwrap(), &tx, "unlock", &HashSet::new()).unwrap();
+    for _ in 0..400 {
+        assert_eq!(rx.blocking_recv().unwrap().client, "linux");
+    }
+}
+
+#[test]
+fn untargeted_macro_needs_a_focused_client() {
+    let topology = test_topology();
+    topology.lock().unwrap().set_focus(Focus::Local);
+    let (tx, _rx) = broadcast::channel(100);
+
+    let topo = topology.lock().unwrap();
+    assert!(macros::start(&topo, &tx, "terminal", &HashSet::new()).is_err());
+    assert!(macros::start(&topo, &tx, "missing", &HashSet::new()).is_err());
+}
+
+#[test]
+fn invalid_macros_are_reported_by_the_validator() {
+    let config = Config {
+        macros: vec![
+            MacroConfig {
+                name: "a".to_string(),
+                hotkey: Some("Ctrl+Banana".to_string()),
+                target: Some("nowhere".to_string()),
+                steps: vec![tap("Escape"), tap("Meta+")],
+            },
+            MacroConfig {
+                name: "a".to_string(),
+                hotkey: None,
+                target: None,
+                steps: vec![],
+            },
+        ],
+        ..Default::default()
+    };
+
+    let diagnostics = validate(&config);
+    let found = |path: &str, severity: Severity| {
+        diagnostics
+            .iter()
+            .any(|d| d.path == path && d.severity == severity)
+    };
+    assert!(
+        found("macros[0].hotkey", Severity::Error),
+        "{:?}",
+        diagnostics
+    );
+    assert!(
+        found("macros[0].target", Severity::Warning),
+        "{:?}",
+        diagnostics
+    );
+    assert!(
+        found("macros[0].steps[1]", Severity::Error),
+        "{:?}",
+        diagnostics
+    );
+    assert!(
+        !found("macros[0].steps[0]", Severity::Error),
+        "{:?}",
+        diagnostics
+    );
+    assert!(
+        found("macros[1].name", Severity::Error),
+        "{:?}",
+        diagnostics
+    );
+}
+
+#[test]
+fn macros_do_not_run_while_sharing_is_paused() {
+    let topology = test_topology();
+    topology.lock().unwrap().set_paused(true);
+    let (tx, mut rx) = broadcast::channel(100);
+
+    let topo = topology.lock().unwrap();
+    assert!(macros::start(&topo, &tx, "unlock", &HashSet::new()).is_err());
+    assert!(rx.try_recv().is_err());
+}
@@ -0,0 +1,51 @@
+use aurora_kvm::pairing::Pairing;
+use std::net::SocketAddr;
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn a_second_client_with_a_pending_name_is_turned_away() {
+    let mut pairing = Pairing::default();
+    let first = addr("10.0.0.3:5000");
+    let second = addr("10.0.0.4:5000");
+
+    let mut approved = pairing.request("tablet", first).unwrap();
+    assert!(pairing.request("tablet", second).is_none());
+    assert_eq!(pairing.pending(), vec![("tablet".to_string(), first)]);
+
+    // The rejected client giving up leaves the first request alone
+    pairing.cancel("tablet", second);
+    assert_eq!(pairing.pending().len(), 1);
+    assert!(approved.try_recv().is_err());
+
+    assert!(pairing.approve("tablet"));
+    assert!(approved.try_recv().is_ok());
+    assert!(pairing.pending().is_empty());
+}
+
+#[test]
+fn approval_covers_the_name_from_the_approved_host_only() {
+    let mut pairing = Pairing::default();
+    let _approved = pairing.request("tablet", addr("10.0.0.3:5000")).unwrap();
+    assert!(pairing.approve("tablet"));
+
+    // Reconnecting uses a new port
+    assert!(pairing.is_approved("tablet", addr("10.0.0.3:6000")));
+    assert!(!pairing.is_approved("tablet", addr("10.0.0.4:5000")));
+    assert!(!pairing.is_approved("phone", addr("10.0.0.3:5000")));
+}
+
+#[test]
+fn cancelling_removes_only_the_callers_request() {
+    let mut pairing = Pairing::default();
+    let first = addr("10.0.0.3:5000");
+    let _timed_out = pairing.request("tablet", first).unwrap();
+    pairing.cancel("tablet", first);
+
+    let second = addr("10.0.0.4:5000");
+    let _approved = pairing.request("tablet", second).unwrap();
+    pairing.cancel("tablet", first);
+    assert_eq!(pairing.pending(), vec![("tablet".to_string(), second)]);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use aurora_kvm::config::{ClientConfig, Config, LocalScreen};
+use aurora_kvm::connected::create_connected_clients;
+use aurora_kvm::event::ClientEvent;
+use aurora_kvm::input::pointer::{self, SpeedMeter};
+use aurora_kvm::input::router::InputRouter;
+use aurora_kvm::topology::create_shared_topology;
+use aurora_kvm::validate::{Severity, validate};
+use common::at;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const CURVE: [[f64; 2]; 3] = [[200.0, 1.0], [1000.0, 2.0], [3000.0, 3.0]];
+
+#[test]
+fn acceleration_interpolates_between_points_and_holds_at_the_ends() {
+    assert_eq!(pointer::acceleration(&[], 5000.0), 1.0);
+    assert_eq!(pointer::acceleration(&CURVE, 0.0), 1.0);
+    assert_eq!(pointer::acceleration(&CURVE, 600.0), 1.5);
+    assert_eq!(pointer::acceleration(&CURVE, 2000.0), 2.5);
+    assert_eq!(pointer::acceleration(&CURVE, 10000.0), 3.0);
+}
+
+#[test]
+fn gain_combines_speed_curve_and_display_scale() {
+    let client = ClientConfig {
+        pointer_speed: 1.5,
+        pointer_acceleration: CURVE.to_vec(),
+        ..Default::default()
+    };
+
+    assert_eq!(pointer::gain(&client, 1.0, 100.0), 1.5);
+    assert_eq!(pointer::gain(&client, 2.0, 1000.0), 6.0);
+    assert_eq!(pointer::gain(&ClientConfig::default(), 0.5, 1000.0), 0.5);
+}
+
+#[test]
+fn speed_is_measured_between_moves() {
+    let mut meter = SpeedMeter::new();
+    let start = Instant::now();
+
+    // The first move, and any after a pause, starts from rest
+    assert_eq!(meter.measure(10.0, start), 100.0);
+    assert_eq!(
+        meter.measure(10.0, start + Duration::from_millis(10)),
+        1000.0
+    );
+    // A burst of events counts as one millisecond apart
+    assert_eq!(
+        meter.measure(5.0, start + Duration::from_millis(10)),
+        5000.0
+    );
+    assert_eq!(meter.measure(10.0, start + Duration::from_secs(2)), 100.0);
+}
+
+#[test]
+fn client_pointer_speed_scales_forwarded_motion() {
+    let mut config = Config::default();
+    let profile = config.active_mut();
+    profile.local_screens = vec![LocalScreen {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    }];
+    profile.clients = vec![ClientConfig {
+        name: "laptop".to_string(),
+        x: 1920,
+        pointer_speed: 2.0,
+        ..Default::default()
+    }];
+    let topology = create_shared_topology(config, create_connected_clients());
+    let (tx, mut rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology, tx);
+
+    router.handle(at(1900.0, 500.0));
+    // Crosses onto the laptop
+    router.handle(at(1925.0, 500.0));
+    router.handle(at(1935.0, 505.0));
+
+    let ClientEvent { event, .. } = rx.try_recv().unwrap();
+    assert_eq!(event, at(25.0, 510.0));
+}
+
+#[test]
+fn invalid_pointer_settings_are_reported_by_the_validator() {
+    let mut config = Config::default();
+    config.active_mut().clients = vec![ClientConfig {
+        name: "laptop".to_string(),
+        ip: "10.0.0.2".to_string(),
+        pointer_speed: 0.0,
+        pointer_acceleration: vec![[500.0, 1.0], [100.0, 2.0], [900.0, -1.0]],
+        ..Default::default()
+    }];
+
+    let diagnostics = validate(&config);
+    let errors: Vec<&str> = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| d.path.as_str())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "profiles[0].clients[0].pointer_speed",
+            "profiles[0].clients[0].pointer_acceleration[1]",
+            "profiles[0].clients[0].pointer_acceleration[2]",
+        ]
+    );
+}
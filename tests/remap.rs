@@ -0,0 +1,235 @@
+use aurora_kvm::config::{ClientConfig, Config};
+use aurora_kvm::event::{Key, KvmEvent};
+use aurora_kvm::input::remap::{RemapRule, RemapTable, Remapper};
+use aurora_kvm::validate::validate;
+
+fn table(rules: &[(&str, &str)]) -> RemapTable {
+    RemapTable::new(
+        rules
+            .iter()
+            .map(|(from, to)| RemapRule::parse(from, to).unwrap())
+            .collect(),
+    )
+}
+
+fn press(key: Key) -> KvmEvent {
+    KvmEvent::key_press(key)
+}
+
+fn release(key: Key) -> KvmEvent {
+    KvmEvent::key_release(key)
+}
+
+/// Feed `events` through a fresh remapper and collect everything it sends
+fn run(table: &RemapTable, events: &[KvmEvent]) -> Vec<KvmEvent> {
+    let mut remapper = Remapper::new();
+    events
+        .iter()
+        .flat_map(|event| remapper.apply(table, event.clone()))
+        .collect()
+}
+
+#[test]
+fn every_key_can_be_remapped_by_name() {
+    for key in Key::ALL {
+        let table = table(&[(&format!("{:?}", key), "F24")]);
+        assert_eq!(
+            run(&table, &[press(*key), release(*key)]),
+            vec![press(Key::F24), release(Key::F24)],
+            "{:?}",
+            key
+        );
+    }
+}
+
+#[test]
+fn unmapped_keys_pass_through_untouched() {
+    let table = table(&[("CapsLock", "Escape")]);
+    // Usage from an AZERTY server, which must survive as-is
+    let event = KvmEvent::KeyPress {
+        key: Key::KeyA,
+        usage: 0x0007_0014,
+    };
+
+    assert_eq!(run(&table, std::slice::from_ref(&event)), vec![event]);
+}
+
+#[test]
+fn single_key_is_substituted() {
+    let table = table(&[("CapsLock", "Escape")]);
+
+    assert_eq!(
+        run(&table, &[press(Key::CapsLock), release(Key::CapsLock)]),
+        vec![press(Key::Escape), release(Key::Escape)]
+    );
+}
+
+#[test]
+fn swapped_modifiers_apply_to_shortcuts() {
+    let table = table(&[("MetaLeft", "ControlLeft"), ("ControlLeft", "MetaLeft")]);
+
+    assert_eq!(
+        run(
+            &table,
+            &[
+                press(Key::MetaLeft),
+                press(Key::KeyC),
+                release(Key::KeyC),
+                release(Key::MetaLeft),
+                press(Key::ControlLeft),
+                release(Key::ControlLeft),
+            ]
+        ),
+        vec![
+            press(Key::ControlLeft),
+            press(Key::KeyC),
+            release(Key::KeyC),
+            release(Key::ControlLeft),
+            press(Key::MetaLeft),
+            release(Key::MetaLeft),
+        ]
+    );
+}
+
+#[test]
+fn chord_becomes_chord_and_modifiers_are_restored() {
+    let table = table(&[("Ctrl+C", "Meta+C")]);
+
+    assert_eq!(
+        run(
+            &table,
+            &[
+                press(Key::ControlLeft),
+                press(Key::KeyC),
+                release(Key::KeyC),
+                // Still holding Ctrl: the client gets it back
+                press(Key::KeyV),
+                release(Key::KeyV),
+                release(Key::ControlLeft),
+            ]
+        ),
+        vec![
+            press(Key::ControlLeft),
+            release(Key::ControlLeft),
+            press(Key::MetaLeft),
+            press(Key::KeyC),
+            release(Key::KeyC),
+            release(Key::MetaLeft),
+            press(Key::ControlLeft),
+            press(Key::KeyV),
+            release(Key::KeyV),
+            release(Key::ControlLeft),
+        ]
+    );
+}
+
+#[test]
+fn chord_matches_either_side_of_a_modifier() {
+    let table = table(&[("Ctrl+C", "Meta+C")]);
+
+    assert_eq!(
+        run(&table, &[press(Key::ControlRight), press(Key::KeyC)]),
+        vec![
+            press(Key::ControlRight),
+            release(Key::ControlRight),
+            press(Key::MetaLeft),
+            press(Key::KeyC),
+        ]
+    );
+}
+
+#[test]
+fn modifier_released_first_is_not_sent_twice() {
+    let table = table(&[("Ctrl+H", "Backspace")]);
+
+    assert_eq!(
+        run(
+            &table,
+            &[
+                press(Key::ControlLeft),
+                press(Key::KeyH),
+                release(Key::ControlLeft),
+                release(Key::KeyH),
+            ]
+        ),
+        vec![
+            press(Key::ControlLeft),
+            release(Key::ControlLeft),
+            press(Key::Backspace),
+            release(Key::Backspace),
+        ]
+    );
+}
+
+#[test]
+fn target_modifiers_already_held_are_kept() {
+    let table = table(&[("Ctrl+Q", "Ctrl+Shift+W")]);
+
+    assert_eq!(
+        run(
+            &table,
+            &[
+                press(Key::ControlLeft),
+                press(Key::KeyQ),
+                release(Key::KeyQ),
+                release(Key::ControlLeft),
+            ]
+        ),
+        vec![
+            press(Key::ControlLeft),
+            press(Key::ShiftLeft),
+            press(Key::KeyW),
+            release(Key::KeyW),
+            release(Key::ShiftLeft),
+            release(Key::ControlLeft),
+        ]
+    );
+}
+
+#[test]
+fn chord_rules_win_over_single_keys() {
+    let table = table(&[("C", "KeyX"), ("Ctrl+C", "Meta+C")]);
+
+    assert_eq!(run(&table, &[press(Key::KeyC)]), vec![press(Key::KeyX)]);
+    assert_eq!(
+        run(&table, &[press(Key::ControlLeft), press(Key::KeyC)]).last(),
+        Some(&press(Key::KeyC))
+    );
+}
+
+#[test]
+fn autorepeat_repeats_the_target() {
+    let table = table(&[("Ctrl+C", "Meta+C")]);
+
+    assert_eq!(
+        run(
+            &table,
+            &[press(Key::ControlLeft), press(Key::KeyC), press(Key::KeyC)]
+        )
+        .last(),
+        Some(&press(Key::KeyC))
+    );
+}
+
+#[test]
+fn invalid_rules_are_reported_by_the_validator() {
+    assert!(RemapRule::parse("Ctrl+Banana", "Escape").is_err());
+
+    let mut config = Config::default();
+    config.active_mut().clients = vec![ClientConfig {
+        name: "mac".to_string(),
+        ip: "10.0.0.2".to_string(),
+        x: 1920,
+        remap: [("Ctrl+Banana".to_string(), "Escape".to_string())].into(),
+        ..Default::default()
+    }];
+
+    let diagnostics = validate(&config);
+    assert!(
+        diagnostics
+            .iter()
+            .any(|d| d.path == "profiles[0].clients[0].remap.Ctrl+Banana"),
+        "{:?}",
+        diagnostics
+    );
+}
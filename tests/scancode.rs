@@ -0,0 +1,78 @@
+use aurora_kvm::event::Key;
+use aurora_kvm::scancode::{
+    NO_USAGE, PHYSICAL_KEYS, evdev_from_usage, key_for_usage, scancode_from_usage, usage_for_key,
+    usage_from_evdev, usage_from_scancode, usage_from_x11, x11_from_usage,
+};
+use std::collections::HashSet;
+
+#[test]
+fn every_column_is_unique() {
+    let usages: HashSet<u32> = PHYSICAL_KEYS.iter().map(|k| k.usage).collect();
+    let evdev: HashSet<u16> = PHYSICAL_KEYS.iter().map(|k| k.evdev).collect();
+    let scancodes: Vec<u16> = PHYSICAL_KEYS.iter().filter_map(|k| k.scancode).collect();
+    let unique_scancodes: HashSet<u16> = scancodes.iter().copied().collect();
+    let keys: HashSet<Key> = PHYSICAL_KEYS.iter().map(|k| k.key).collect();
+
+    assert_eq!(usages.len(), PHYSICAL_KEYS.len());
+    assert_eq!(evdev.len(), PHYSICAL_KEYS.len());
+    assert_eq!(unique_scancodes.len(), scancodes.len());
+    assert_eq!(keys.len(), PHYSICAL_KEYS.len());
+    assert!(!usages.contains(&NO_USAGE));
+}
+
+#[test]
+fn every_platform_code_round_trips_through_the_usage() {
+    for physical in PHYSICAL_KEYS {
+        let usage = physical.usage;
+
+        let evdev = evdev_from_usage(usage).unwrap();
+        assert_eq!(usage_from_evdev(evdev), Some(usage));
+
+        let x11 = x11_from_usage(usage).unwrap();
+        assert_eq!(usage_from_x11(x11), Some(usage));
+
+        if let Some(scancode) = scancode_from_usage(usage) {
+            assert_eq!(usage_from_scancode(scancode), Some(usage));
+        }
+
+        assert_eq!(usage_for_key(physical.key), usage);
+        assert_eq!(key_for_usage(usage), Some(physical.key));
+    }
+}
+
+#[test]
+fn x11_keycodes_are_offset_evdev_codes() {
+    // KEY_A is evdev 30 and X11 keycode 38
+    let usage = usage_for_key(Key::KeyA);
+    assert_eq!(usage, 0x0007_0004);
+    assert_eq!(evdev_from_usage(usage), Some(30));
+    assert_eq!(x11_from_usage(usage), Some(38));
+    assert_eq!(usage_from_x11(5), None);
+}
+
+#[test]
+fn extended_windows_scancodes_keep_their_prefix() {
+    assert_eq!(scancode_from_usage(usage_for_key(Key::Return)), Some(0x1C));
+    assert_eq!(
+        scancode_from_usage(usage_for_key(Key::KpReturn)),
+        Some(0xE01C)
+    );
+    assert_eq!(
+        scancode_from_usage(usage_for_key(Key::ControlRight)),
+        Some(0xE01D)
+    );
+}
+
+#[test]
+fn media_keys_use_the_consumer_page() {
+    assert_eq!(usage_for_key(Key::MediaPlayPause), 0x000C_00CD);
+    assert_eq!(usage_for_key(Key::BrowserBack), 0x000C_0224);
+    assert_eq!(usage_for_key(Key::Sleep), 0x0001_0082);
+    assert_eq!(scancode_from_usage(usage_for_key(Key::BrightnessUp)), None);
+}
+
+#[test]
+fn keys_without_a_position_have_no_usage() {
+    assert_eq!(usage_for_key(Key::Function), NO_USAGE);
+    assert_eq!(usage_for_key(Key::Unknown(1234)), NO_USAGE);
+}
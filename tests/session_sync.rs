@@ -0,0 +1,100 @@
+use aurora_kvm::config::{ClientConfig, Config, SessionSync};
+use aurora_kvm::connected::create_connected_clients;
+use aurora_kvm::session::{self, MockSession, SessionChange, SessionEvent, SessionMirror};
+use aurora_kvm::topology::create_shared_topology;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+use SessionEvent::{Locked, ScreensaverStarted, ScreensaverStopped, Unlocked};
+
+fn change(from: Option<&str>, event: SessionEvent) -> SessionChange {
+    SessionChange {
+        from: from.map(str::to_string),
+        event,
+    }
+}
+
+#[test]
+fn policies_choose_what_is_shared() {
+    for event in [Locked, Unlocked, ScreensaverStarted, ScreensaverStopped] {
+        assert!(!event.shared_by(SessionSync::Ignore));
+        assert!(event.shared_by(SessionSync::MirrorLock));
+    }
+    assert!(ScreensaverStarted.shared_by(SessionSync::MirrorScreensaver));
+    assert!(!Locked.shared_by(SessionSync::MirrorScreensaver));
+}
+
+#[test]
+fn mirror_passes_on_news_but_not_echoes() {
+    let mut mirror = SessionMirror::new();
+
+    assert!(mirror.mirrored(Locked));
+    // The OS reporting the lock we just applied
+    assert!(!mirror.observed(Locked));
+    // Unlocking is never mirrored, but a local unlock is news
+    assert!(!mirror.mirrored(Unlocked));
+    assert!(mirror.observed(Unlocked));
+
+    assert!(mirror.observed(ScreensaverStarted));
+    assert!(!mirror.mirrored(ScreensaverStarted));
+    assert!(mirror.mirrored(ScreensaverStopped));
+}
+
+#[tokio::test]
+async fn server_applies_client_changes_by_policy_and_passes_them_on() {
+    let mut config = Config::default();
+    config.active_mut().clients = vec![
+        ClientConfig {
+            name: "mac".to_string(),
+            session_sync: SessionSync::MirrorLock,
+            ..Default::default()
+        },
+        ClientConfig {
+            name: "linux".to_string(),
+            session_sync: SessionSync::MirrorScreensaver,
+            ..Default::default()
+        },
+    ];
+    let topology = create_shared_topology(config, create_connected_clients());
+
+    let mut backend = MockSession::echoing();
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (out, mut changes) = broadcast::channel(16);
+    session::watch_into(&mut backend, tx.clone()).unwrap();
+    tokio::spawn(session::run_server(
+        topology,
+        Box::new(backend.clone()),
+        rx,
+        out,
+    ));
+
+    let mut next = async || {
+        tokio::time::timeout(Duration::from_millis(200), changes.recv())
+            .await
+            .ok()
+            .map(|change| change.unwrap())
+    };
+
+    // Not allowed by the linux client's policy
+    tx.send(change(Some("linux"), Locked)).unwrap();
+    tx.send(change(Some("mac"), Locked)).unwrap();
+    assert_eq!(next().await, Some(change(Some("mac"), Locked)));
+    // Applied on a thread of its own; let the lock land before unlocking
+    while backend.applied().is_empty() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    // The server unlocking itself is passed on
+    backend.emit(Unlocked);
+    assert_eq!(next().await, Some(change(None, Unlocked)));
+
+    tx.send(change(Some("linux"), ScreensaverStarted)).unwrap();
+    assert_eq!(
+        next().await,
+        Some(change(Some("linux"), ScreensaverStarted))
+    );
+
+    // The echo of each applied change went nowhere
+    assert_eq!(next().await, None);
+    assert_eq!(backend.applied(), vec![Locked, ScreensaverStarted]);
+}
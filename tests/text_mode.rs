@@ -0,0 +1,176 @@
+use aurora_kvm::config::{ClientConfig, Config, KeyboardMode};
+use aurora_kvm::connected::create_connected_clients;
+use aurora_kvm::event::{Key, KvmEvent};
+use aurora_kvm::input::router::InputRouter;
+use aurora_kvm::input::text::{TextResolver, TextTranslator};
+use aurora_kvm::topology::{Focus, create_shared_topology};
+use tokio::sync::broadcast;
+
+/// A tiny German-ish layout: Y and Z swapped, Shift for capitals
+#[derive(Default)]
+struct FakeLayout {
+    shift: bool,
+}
+
+impl TextResolver for FakeLayout {
+    fn resolve(&mut self, event: &KvmEvent) -> Option<String> {
+        let (key, pressed) = match *event {
+            KvmEvent::KeyPress { key, .. } => (key, true),
+            KvmEvent::KeyRelease { key, .. } => (key, false),
+            _ => return None,
+        };
+        if matches!(key, Key::ShiftLeft | Key::ShiftRight) {
+            self.shift = pressed;
+            return None;
+        }
+        if !pressed {
+            return None;
+        }
+
+        let text = match key {
+            Key::KeyY => "z",
+            Key::KeyZ => "y",
+            Key::KeyA => "a",
+            Key::Num2 if self.shift => "\"",
+            Key::Num2 => "2",
+            Key::Return => "\r",
+            _ => return None,
+        };
+        Some(if self.shift {
+            text.to_uppercase()
+        } else {
+            text.to_string()
+        })
+    }
+
+    fn reset(&mut self) {
+        self.shift = false;
+    }
+}
+
+fn translator() -> TextTranslator {
+    TextTranslator::new(Box::new(FakeLayout::default()))
+}
+
+fn text(s: &str) -> KvmEvent {
+    KvmEvent::Text(s.to_string())
+}
+
+#[test]
+fn typing_becomes_text_and_releases_are_dropped() {
+    let mut t = translator();
+
+    assert_eq!(t.translate(KvmEvent::key_press(Key::KeyY)), vec![text("z")]);
+    assert_eq!(t.translate(KvmEvent::key_release(Key::KeyY)), vec![]);
+}
+
+#[test]
+fn shift_is_forwarded_and_applied_by_the_layout() {
+    let mut t = translator();
+    let shift = KvmEvent::key_press(Key::ShiftLeft);
+
+    assert_eq!(t.translate(shift.clone()), vec![shift]);
+    assert_eq!(
+        t.translate(KvmEvent::key_press(Key::Num2)),
+        vec![text("\"")]
+    );
+    assert_eq!(t.translate(KvmEvent::key_release(Key::Num2)), vec![]);
+    t.translate(KvmEvent::key_release(Key::ShiftLeft));
+    assert_eq!(t.translate(KvmEvent::key_press(Key::Num2)), vec![text("2")]);
+}
+
+#[test]
+fn chords_go_through_as_keys() {
+    let mut t = translator();
+    let events = [
+        KvmEvent::key_press(Key::ControlLeft),
+        KvmEvent::key_press(Key::KeyZ),
+        KvmEvent::key_release(Key::KeyZ),
+        KvmEvent::key_release(Key::ControlLeft),
+    ];
+
+    for event in events {
+        assert_eq!(t.translate(event.clone()), vec![event]);
+    }
+    // Typing works again once the chord is released
+    assert_eq!(t.translate(KvmEvent::key_press(Key::KeyZ)), vec![text("y")]);
+}
+
+#[test]
+fn key_converted_before_a_chord_is_released_as_a_key() {
+    let mut t = translator();
+
+    assert_eq!(t.translate(KvmEvent::key_press(Key::KeyA)), vec![text("a")]);
+    t.translate(KvmEvent::key_press(Key::MetaLeft));
+    // Autorepeat while Meta is held is a real key press now
+    let repeat = KvmEvent::key_press(Key::KeyA);
+    assert_eq!(t.translate(repeat.clone()), vec![repeat]);
+    let release = KvmEvent::key_release(Key::KeyA);
+    assert_eq!(t.translate(release.clone()), vec![release]);
+}
+
+#[test]
+fn control_characters_and_unmapped_keys_stay_keys() {
+    let mut t = translator();
+
+    for key in [Key::Return, Key::LeftArrow, Key::F5] {
+        let press = KvmEvent::key_press(key);
+        let release = KvmEvent::key_release(key);
+        assert_eq!(t.translate(press.clone()), vec![press]);
+        assert_eq!(t.translate(release.clone()), vec![release]);
+    }
+}
+
+#[test]
+fn caps_lock_is_not_forwarded() {
+    let mut t = translator();
+
+    assert_eq!(t.translate(KvmEvent::key_press(Key::CapsLock)), vec![]);
+    assert_eq!(t.translate(KvmEvent::key_release(Key::CapsLock)), vec![]);
+}
+
+#[test]
+fn pointer_events_pass_through() {
+    let mut t = translator();
+    let event = KvmEvent::MouseMove { x: 1.0, y: 2.0 };
+
+    assert_eq!(t.translate(event.clone()), vec![event]);
+}
+
+#[test]
+fn router_sends_text_only_to_text_mode_clients() {
+    let mut config = Config::default();
+    config.active_mut().clients = vec![
+        ClientConfig {
+            name: "mac".to_string(),
+            x: 1920,
+            keyboard_mode: KeyboardMode::Text,
+            ..Default::default()
+        },
+        ClientConfig {
+            name: "linux".to_string(),
+            x: -1920,
+            ..Default::default()
+        },
+    ];
+    let topology = create_shared_topology(config, create_connected_clients());
+    let (tx, mut rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology.clone(), tx);
+    router.set_text_resolver(Box::new(FakeLayout::default()));
+
+    topology
+        .lock()
+        .unwrap()
+        .set_focus(Focus::Client("mac".to_string()));
+    router.handle(KvmEvent::key_press(Key::KeyY));
+    router.handle(KvmEvent::key_release(Key::KeyY));
+    assert_eq!(rx.try_recv().unwrap().event, text("z"));
+    assert!(rx.try_recv().is_err());
+
+    topology
+        .lock()
+        .unwrap()
+        .set_focus(Focus::Client("linux".to_string()));
+    router.handle(KvmEvent::key_press(Key::KeyY));
+    assert_eq!(rx.try_recv().unwrap().event, KvmEvent::key_press(Key::KeyY));
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use aurora_kvm::config::Profile;
+use aurora_kvm::connected::create_connected_clients;
+use aurora_kvm::event::{Key, KvmEvent};
+use aurora_kvm::input::Disposition;
+use aurora_kvm::input::router::InputRouter;
+use aurora_kvm::topology::{Focus, SharedTopology, create_shared_topology};
+use aurora_kvm::tray::{self, MenuEntry, TrayAction, TrayStatus};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// `common::test_topology` with Ctrl+Alt+L focusing the laptop
+fn hotkey_topology() -> SharedTopology {
+    let mut config = common::test_config();
+    config.active_mut().clients[0].hotkey = Some("Ctrl+Alt+L".to_string());
+    create_shared_topology(config, create_connected_clients())
+}
+
+fn labels(menu: &[MenuEntry]) -> Vec<String> {
+    menu.iter()
+        .map(|entry| match entry {
+            MenuEntry::Info(label)
+            | MenuEntry::Action { label, .. }
+            | MenuEntry::Toggle { label, .. } => label.clone(),
+            MenuEntry::Profiles { options, .. } => options.join("/"),
+            MenuEntry::Separator => "-".to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn server_menu_shows_status_and_offers_every_action() {
+    let topology = hotkey_topology();
+    {
+        let mut topo = topology.lock().unwrap();
+        let mut config = topo.get_config().clone();
+        config.profiles.push(Profile {
+            name: "travel".to_string(),
+            ..Default::default()
+        });
+        topo.update_config(config);
+        topo.set_focus(Focus::Client("laptop".to_string()));
+        topo.set_focus_locked(true);
+    }
+
+    let status = TrayStatus::for_server(&topology.lock().unwrap(), 1);
+    let menu = status.menu();
+
+    assert_eq!(
+        labels(&menu),
+        vec![
+            "1 client connected; input on laptop",
+            "-",
+            "Open configuration",
+            "Lock to screen",
+            "Pause sharing",
+            "default/travel",
+            "-",
+            "Quit",
+        ]
+    );
+    assert!(menu.contains(&MenuEntry::Toggle {
+        label: "Lock to screen".to_string(),
+        checked: true,
+        action: TrayAction::ToggleFocusLock,
+    }));
+    assert!(menu.contains(&MenuEntry::Profiles {
+        options: vec!["default".to_string(), "travel".to_string()],
+        selected: 0,
+    }));
+}
+
+#[test]
+fn client_menu_only_quits() {
+    let status = TrayStatus::for_client("10.0.0.1:8080", true);
+
+    assert_eq!(
+        labels(&status.menu()),
+        vec![
+            "Connected to 10.0.0.1:8080; input on this machine",
+            "-",
+            "Quit"
+        ]
+    );
+}
+
+#[test]
+fn pausing_keeps_input_on_the_server() {
+    let topology = hotkey_topology();
+    topology
+        .lock()
+        .unwrap()
+        .set_focus(Focus::Client("laptop".to_string()));
+
+    assert_eq!(tray::apply(&topology, TrayAction::TogglePause), None);
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+
+    let (tx, _rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology.clone(), tx);
+    router.handle(KvmEvent::MouseMove {
+        x: 1900.0,
+        y: 500.0,
+    });
+    router.handle(KvmEvent::MouseMove {
+        x: 1925.0,
+        y: 500.0,
+    });
+    router.handle(KvmEvent::key_press(Key::ControlLeft));
+    router.handle(KvmEvent::key_press(Key::Alt));
+    assert_eq!(
+        router.handle(KvmEvent::key_press(Key::KeyL)),
+        Disposition::Pass
+    );
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+
+    tray::apply(&topology, TrayAction::TogglePause);
+    assert!(!topology.lock().unwrap().is_paused());
+    // The binary opens the window and quits
+    assert_eq!(
+        tray::apply(&topology, TrayAction::Quit),
+        Some(TrayAction::Quit)
+    );
+}
+
+#[tokio::test]
+async fn server_status_follows_the_topology() {
+    let topology = hotkey_topology();
+    let mut status = tray::watch_server(topology.clone(), create_connected_clients());
+    assert_eq!(status.borrow().connection, "No clients connected");
+    assert!(!status.borrow().paused);
+
+    tray::apply(&topology, TrayAction::TogglePause);
+    tokio::time::timeout(Duration::from_millis(200), status.changed())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(status.borrow().paused);
+    assert_eq!(
+        status.borrow().summary(),
+        "No clients connected; sharing paused"
+    );
+}
@@ -0,0 +1,168 @@
+use aurora_kvm::validate::{Diagnostic, Severity, check_source, has_errors};
+
+/// A config with one of each layout mistake. The secret's quotes and brackets
+/// must not throw off the line numbers that follow.
+const BAD_CONFIG: &str = r#"{
+  "version": 2,
+  "port": 8080,
+  "secret": "tricky \" } ] { [",
+  "local_hotkey": "Ctrl+Banana",
+  "active_profile": "desk",
+  "profiles": [
+    {
+      "name": "desk",
+      "local_screens": [
+        { "x": 0, "y": 0, "width": 1920, "height": 1080 },
+        { "x": 1000, "y": 0, "width": 1920, "height": 1080 },
+        { "x": 0, "y": 2000, "width": 0, "height": 1080 }
+      ],
+      "clients": [
+        {
+          "name": "laptop",
+          "ip": "10.0.0.300",
+          "x": 2920,
+          "y": 0,
+          "width": 1920,
+          "height": 1080
+        },
+        {
+          "name": "laptop",
+          "ip": "10.0.0.2:8080",
+          "x": 9000,
+          "y": 9000,
+          "width": 1920,
+          "height": 1080,
+          "hotkey": "Ctrl+Banana"
+        }
+      ]
+    }
+  ]
+}"#;
+
+fn find<'a>(diagnostics: &'a [Diagnostic], path: &str) -> &'a Diagnostic {
+    diagnostics
+        .iter()
+        .find(|d| d.path == path)
+        .unwrap_or_else(|| panic!("no diagnostic for {}: {:#?}", path, diagnostics))
+}
+
+#[test]
+fn each_mistake_points_at_its_path_and_line() {
+    let (config, diagnostics) = check_source(BAD_CONFIG);
+    assert!(config.is_some());
+    assert!(has_errors(&diagnostics));
+
+    for (path, line, severity, message) in [
+        ("local_hotkey", 5, Severity::Error, "Banana"),
+        (
+            "profiles[0].local_screens[1]",
+            12,
+            Severity::Error,
+            "overlaps profiles[0].local_screens[0]",
+        ),
+        (
+            "profiles[0].local_screens[2]",
+            13,
+            Severity::Error,
+            "zero size (0x1080)",
+        ),
+        (
+            "profiles[0].clients[0].ip",
+            18,
+            Severity::Error,
+            "'10.0.0.300' is not a valid IP",
+        ),
+        (
+            "profiles[0].clients[1].name",
+            25,
+            Severity::Error,
+            "duplicate client name 'laptop'",
+        ),
+        (
+            "profiles[0].clients[1]",
+            24,
+            Severity::Warning,
+            "not adjacent to any other screen",
+        ),
+        (
+            "profiles[0].clients[1].hotkey",
+            31,
+            Severity::Error,
+            "Banana",
+        ),
+    ] {
+        let diagnostic = find(&diagnostics, path);
+        assert_eq!(diagnostic.line, Some(line), "{}", diagnostic);
+        assert_eq!(diagnostic.severity, severity, "{}", diagnostic);
+        assert!(diagnostic.message.contains(message), "{}", diagnostic);
+    }
+
+    // Touching the second local screen, so reachable
+    assert!(
+        !diagnostics
+            .iter()
+            .any(|d| d.path == "profiles[0].clients[0]"),
+        "{:#?}",
+        diagnostics
+    );
+    assert_eq!(diagnostics.len(), 7, "{:#?}", diagnostics);
+}
+
+#[test]
+fn a_good_config_has_nothing_to_report() {
+    let (config, diagnostics) = check_source(
+        r#"{
+  "version": 2,
+  "port": 8080,
+  "active_profile": "desk",
+  "profiles": [
+    {
+      "name": "desk",
+      "local_screens": [{ "x": 0, "y": 0, "width": 1920, "height": 1080 }],
+      "clients": [
+        { "name": "laptop", "ip": "10.0.0.2", "x": 1920, "y": 0, "width": 1920, "height": 1080 }
+      ]
+    }
+  ]
+}"#,
+    );
+
+    assert!(config.is_some());
+    assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+}
+
+#[test]
+fn values_are_located_where_they_start() {
+    // A client on one line inside a one-line array, and a value on the line
+    // after its key
+    let (_, diagnostics) = check_source(
+        r#"{
+  "version": 2,
+  "port": 8080,
+  "active_profile":
+    "missing",
+  "profiles": [
+    {
+      "name": "desk",
+      "local_screens": [{ "x": 0, "y": 0, "width": 1920, "height": 1080 }],
+      "clients": [{ "name": "flat", "ip": "10.0.0.2", "x": 1920, "height": 0 }]
+    }
+  ]
+}"#,
+    );
+
+    let diagnostic = find(&diagnostics, "profiles[0].clients[0]");
+    assert!(diagnostic.message.contains("zero size"), "{}", diagnostic);
+    assert_eq!(diagnostic.line, Some(10));
+    assert_eq!(find(&diagnostics, "active_profile").line, Some(5));
+}
+
+#[test]
+fn syntax_errors_report_the_parser_line() {
+    let (config, diagnostics) = check_source("{\n  \"port\": 8080,\n  \"secret\": ,\n}");
+
+    assert!(config.is_none());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, "$");
+    assert_eq!(diagnostics[0].line, Some(3));
+}
@@ -0,0 +1,222 @@
+mod common;
+
+use aurora_kvm::event::{ClientEvent, Key, KvmEvent};
+use aurora_kvm::input::mock::{MockCapture, RecordingWarp};
+use aurora_kvm::input::router::InputRouter;
+use aurora_kvm::input::{Disposition, InputCapture};
+use aurora_kvm::topology::{Focus, SharedTopology};
+use common::{at, test_topology};
+use tokio::sync::broadcast;
+
+use Disposition::{Pass, Swallow};
+
+/// Run backend positions through a router, returning the dispositions and the
+/// positions sent to the client
+fn run(
+    topology: &SharedTopology,
+    warp: Option<RecordingWarp>,
+    positions: &[(f64, f64)],
+) -> (Vec<Disposition>, Vec<KvmEvent>) {
+    let (tx, mut rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology.clone(), tx);
+    if let Some(warp) = warp {
+        router.set_cursor_warp(Box::new(warp));
+    }
+
+    let capture = Box::new(MockCapture::new(
+        positions.iter().map(|&(x, y)| at(x, y)).collect(),
+    ));
+    let dispositions = capture.dispositions();
+    capture
+        .run(Box::new(move |event| router.handle(event)))
+        .unwrap();
+
+    let mut sent = Vec::new();
+    while let Ok(ClientEvent { event, .. }) = rx.try_recv() {
+        sent.push(event);
+    }
+    let dispositions = dispositions.lock().unwrap().clone();
+    (dispositions, sent)
+}
+
+#[test]
+fn relative_motion_moves_the_cursor_within_the_client() {
+    let topology = test_topology();
+    // Positions summed from raw motion, as the evdev backend reports them
+    let (dispositions, sent) = run(
+        &topology,
+        None,
+        &[
+            (1900.0, 500.0),
+            (1925.0, 500.0),
+            (3000.0, 500.0),
+            // Far past the client's right edge
+            (5000.0, 500.0),
+            // Any motion back left counts at once
+            (4000.0, 500.0),
+            (3000.0, 500.0),
+            // Local again, offset by the distance lost at the client's edge
+            (2990.0, 500.0),
+        ],
+    );
+
+    assert_eq!(
+        dispositions,
+        vec![Pass, Pass, Swallow, Swallow, Swallow, Pass, Pass]
+    );
+    assert_eq!(
+        sent,
+        vec![at(1080.0, 500.0), at(1919.0, 500.0), at(919.0, 500.0)]
+    );
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+}
+
+#[test]
+fn warped_cursor_is_parked_and_put_back_where_focus_returns() {
+    let topology = test_topology();
+    let warp = RecordingWarp::new();
+    // Absolute positions, as the OS reports them around the parking spot
+    let (dispositions, sent) = run(
+        &topology,
+        Some(warp.clone()),
+        &[
+            (1925.0, 500.0),
+            // Echo of the warp to the parking spot
+            (960.0, 540.0),
+            (1060.0, 540.0),
+            // Strays too far and is parked again
+            (1360.0, 540.0),
+            (960.0, 540.0),
+            (360.0, 540.0),
+            (1725.0, 500.0),
+        ],
+    );
+
+    assert_eq!(
+        dispositions,
+        vec![Swallow, Swallow, Swallow, Swallow, Swallow, Swallow, Pass]
+    );
+    assert_eq!(sent, vec![at(105.0, 500.0), at(405.0, 500.0)]);
+    assert_eq!(
+        warp.warps(),
+        vec![(960.0, 540.0), (960.0, 540.0), (1725.0, 500.0)]
+    );
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+}
+
+#[test]
+fn focus_set_directly_starts_in_the_middle_of_the_client() {
+    let topology = test_topology();
+    topology
+        .lock()
+        .unwrap()
+        .set_focus(Focus::Client("laptop".to_string()));
+
+    let (_, sent) = run(&topology, None, &[(100.0, 100.0), (110.0, 90.0)]);
+
+    assert_eq!(sent, vec![at(970.0, 530.0)]);
+}
+
+/// `test_topology` with Ctrl+Alt+L focusing the laptop and Ctrl+Alt+H focusing
+/// local
+fn hotkey_topology(jump_mode: bool) -> SharedTopology {
+    let topology = test_topology();
+    {
+        let mut topo = topology.lock().unwrap();
+        let mut config = topo.get_config().clone();
+        config.jump_mode = jump_mode;
+        config.local_hotkey = Some("Ctrl+Alt+H".to_string());
+        config.active_mut().clients[0].hotkey = Some("Ctrl+Alt+L".to_string());
+        topo.update_config(config);
+    }
+    topology
+}
+
+fn moves(sent: &[ClientEvent]) -> Vec<KvmEvent> {
+    sent.iter()
+        .map(|e| e.event.clone())
+        .filter(|event| matches!(event, KvmEvent::MouseMove { .. }))
+        .collect()
+}
+
+#[test]
+fn jump_mode_puts_the_cursor_back_on_each_machine() {
+    let topology = hotkey_topology(true);
+    // Where the laptop reported its cursor when it last lost focus
+    topology
+        .lock()
+        .unwrap()
+        .remember_cursor("laptop", (2000.0, 100.0));
+    let (tx, mut rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology.clone(), tx);
+    let warp = RecordingWarp::new();
+    router.set_cursor_warp(Box::new(warp.clone()));
+
+    assert_eq!(router.handle(at(500.0, 300.0)), Pass);
+    router.handle(KvmEvent::key_press(Key::ControlLeft));
+    router.handle(KvmEvent::key_press(Key::Alt));
+    assert_eq!(router.handle(KvmEvent::key_press(Key::KeyL)), Swallow);
+    assert_eq!(
+        *topology.lock().unwrap().get_focus(),
+        Focus::Client("laptop".to_string())
+    );
+
+    // Echo of the warp to the parking spot, then some motion
+    assert_eq!(router.handle(at(960.0, 540.0)), Swallow);
+    assert_eq!(router.handle(at(970.0, 540.0)), Swallow);
+    router.handle(KvmEvent::key_release(Key::KeyL));
+
+    assert_eq!(router.handle(KvmEvent::key_press(Key::KeyH)), Swallow);
+    assert_eq!(*topology.lock().unwrap().get_focus(), Focus::Local);
+    assert_eq!(router.handle(at(505.0, 300.0)), Pass);
+    assert_eq!(router.virtual_cursor(), (505.0, 300.0));
+
+    let mut sent = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        sent.push(event);
+    }
+    assert_eq!(moves(&sent), vec![at(80.0, 100.0), at(90.0, 100.0)]);
+    // The held modifiers were let go on the laptop before focus left it
+    assert!(sent.contains(&ClientEvent {
+        client: "laptop".to_string(),
+        event: KvmEvent::key_release(Key::Alt),
+    }));
+    assert_eq!(warp.warps(), vec![(960.0, 540.0), (500.0, 300.0)]);
+    assert_eq!(
+        topology.lock().unwrap().last_cursor("laptop"),
+        Some((2010.0, 100.0))
+    );
+}
+
+#[test]
+fn frozen_local_cursor_resumes_where_a_hotkey_took_focus_away() {
+    let topology = hotkey_topology(false);
+    topology
+        .lock()
+        .unwrap()
+        .remember_cursor("laptop", (2000.0, 100.0));
+    let (tx, mut rx) = broadcast::channel(100);
+    let mut router = InputRouter::new(topology.clone(), tx);
+
+    router.handle(at(500.0, 300.0));
+    router.handle(KvmEvent::key_press(Key::ControlLeft));
+    router.handle(KvmEvent::key_press(Key::Alt));
+    router.handle(KvmEvent::key_press(Key::KeyL));
+    // Without jump mode the client starts in the middle
+    assert_eq!(router.handle(at(510.0, 300.0)), Swallow);
+    router.handle(KvmEvent::key_press(Key::KeyH));
+
+    // The backend moved on by 10 px, the OS cursor did not
+    assert_eq!(router.handle(at(520.0, 300.0)), Pass);
+    assert_eq!(router.virtual_cursor(), (510.0, 300.0));
+    assert_eq!(
+        topology.lock().unwrap().last_cursor("local"),
+        Some((500.0, 300.0))
+    );
+
+    let mut sent = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        sent.push(event);
+    }
+    assert_eq!(moves(&sent), vec![at(970.0, 540.0)]);
+}